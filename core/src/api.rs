@@ -0,0 +1,433 @@
+//! Hack Hour API responses and the data the screens are updated with.
+
+use core::fmt;
+
+use heapless::{String, Vec};
+use serde::Deserialize;
+
+use crate::errors::ErrorKind;
+
+#[derive(Deserialize, Debug)]
+pub struct SessionResponse {
+    pub ok: bool,
+    pub data: Option<SessionData>,
+    pub error: Option<&'static str>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SessionData {
+    pub elapsed: u8,
+    pub goal: &'static str,
+    pub paused: bool,
+    pub completed: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct StatsResponse {
+    pub ok: bool,
+    pub data: Option<StatsData>,
+    pub error: Option<&'static str>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct StatsData {
+    pub sessions: u32,
+    pub total: u32,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GoalsResponse {
+    pub ok: bool,
+    pub data: Option<Vec<GoalData, 16>>,
+    pub error: Option<&'static str>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GoalData {
+    pub name: &'static str,
+    pub minutes: u32,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ProjectsResponse {
+    pub ok: bool,
+    pub data: Option<Vec<ProjectData, 16>>,
+    pub error: Option<&'static str>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ProjectData {
+    pub name: &'static str,
+    pub minutes: u32,
+    pub sessions: u32,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ShopResponse {
+    pub ok: bool,
+    pub data: Option<Vec<ShopItem, SHOP_PAGE>>,
+    pub error: Option<&'static str>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ShopItem {
+    pub name: &'static str,
+    pub tickets: u16,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LeaderboardResponse {
+    pub ok: bool,
+    pub data: Option<LeaderboardData>,
+    pub error: Option<&'static str>,
+}
+
+/// One page of the leaderboard, and where the user is on it.
+#[derive(Deserialize, Debug)]
+// the names borrow from the response, which serde can't see through the `Vec`
+#[serde(bound(deserialize = "'de: 'static"))]
+pub struct LeaderboardData {
+    pub rank: u16,
+    pub sessions: u32,
+    pub users: Vec<LeaderboardUser, LEADERBOARD_PAGE>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LeaderboardUser {
+    pub name: &'static str,
+    pub sessions: u32,
+}
+
+/// Response to a start, pause or cancel request.
+#[derive(Deserialize, Debug)]
+pub struct ActionResponse<'a> {
+    pub ok: bool,
+    pub error: Option<&'a str>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TimeData<'a> {
+    pub datetime: &'a str,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RequestType {
+    Stats,
+    Session,
+    Goals,
+    // Page of the leaderboard
+    Leaderboard(u8),
+    Projects,
+    // Page of the shop
+    Shop(u8),
+}
+
+/// Most goals that are passed on to the screens.
+pub const MAX_GOALS: usize = 8;
+
+/// Most projects that are passed on to the screens.
+pub const MAX_PROJECTS: usize = 8;
+
+/// Items per page of the shop.
+pub const SHOP_PAGE: usize = 6;
+
+/// Users per page of the leaderboard.
+pub const LEADERBOARD_PAGE: usize = 6;
+/// Pages that can be scrolled through, so only the top 30 are shown.
+pub const LEADERBOARD_PAGES: u8 = 5;
+
+#[derive(Debug)]
+pub enum RequestData {
+    // Ticket count
+    Stats(u16),
+    // Elapsed time, goal, paused, completed
+    Session(u8, &'static str, bool, bool),
+    // Goal names
+    Goals(Vec<&'static str, MAX_GOALS>),
+    // Page, and the users on it
+    Leaderboard(u8, LeaderboardData),
+    Projects(Vec<ProjectData, MAX_PROJECTS>),
+    // Page, and the items on it
+    Shop(u8, Vec<ShopItem, SHOP_PAGE>),
+    None,
+}
+
+/// Changes to the current session, POSTed to `/api/<path>/<slack id>`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SessionAction {
+    /// What is being worked on, shown as the session's goal.
+    Start(String<32>),
+    /// Pauses, or resumes if already paused.
+    Pause,
+    Cancel,
+}
+
+impl SessionAction {
+    pub fn path(&self) -> &'static str {
+        match self {
+            SessionAction::Start(_) => "start",
+            SessionAction::Pause => "pause",
+            SessionAction::Cancel => "cancel",
+        }
+    }
+
+    pub fn body(&self) -> String<80> {
+        let mut body = String::new();
+        match self {
+            SessionAction::Start(work) => {
+                let _ = body.push_str("{\"work\":\"");
+                for c in work.chars().filter(|c| !c.is_control()) {
+                    if matches!(c, '"' | '\\') {
+                        let _ = body.push('\\');
+                    }
+                    let _ = body.push(c);
+                }
+                let _ = body.push_str("\"}");
+            }
+            _ => {
+                let _ = body.push_str("{}");
+            }
+        }
+        body
+    }
+}
+
+/// Why a request didn't produce any data.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FetchError {
+    /// The host name couldn't be looked up.
+    Dns,
+    /// Connecting, sending or receiving failed.
+    Tcp,
+    /// The TLS handshake failed.
+    Tls,
+    /// The reply wasn't valid HTTP.
+    Http,
+    /// The server answered with something other than 2xx, and how many
+    /// seconds to wait before trying again if it said.
+    Status(u16, Option<u32>),
+    /// The reply didn't fit in the receive buffer.
+    TooLarge,
+    /// The body wasn't the JSON we expected.
+    Json,
+    /// The API answered with `ok: false`, and why (if it said).
+    Api(String<32>),
+}
+
+impl FetchError {
+    /// Keeps as much of the API's reason as fits.
+    pub fn api(error: Option<&str>) -> Self {
+        FetchError::Api(error.unwrap_or_default().chars().take(32).collect())
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            FetchError::Dns | FetchError::Tcp | FetchError::Tls => ErrorKind::Connection,
+            FetchError::Http | FetchError::TooLarge | FetchError::Json => ErrorKind::Parse,
+            FetchError::Status(..) | FetchError::Api(_) => ErrorKind::Rejected,
+        }
+    }
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchError::Dns => f.write_str("DNS lookup failed"),
+            FetchError::Tcp => f.write_str("connection failed"),
+            FetchError::Tls => f.write_str("TLS handshake failed"),
+            FetchError::Http => f.write_str("bad HTTP reply"),
+            FetchError::Status(status, _) => write!(f, "HTTP {}", status),
+            FetchError::TooLarge => f.write_str("reply too large"),
+            FetchError::Json => f.write_str("bad JSON"),
+            FetchError::Api(error) if error.is_empty() => f.write_str("no reason given"),
+            FetchError::Api(error) => f.write_str(error),
+        }
+    }
+}
+
+/// The `ok`, `data` and `error` fields every response shares.
+pub trait Response {
+    type Data;
+
+    /// The data, or why the API didn't send any.
+    fn into_data(self) -> Result<Self::Data, FetchError>;
+}
+
+macro_rules! response {
+    ($($response:ty => $data:ty),* $(,)?) => {
+        $(
+            impl Response for $response {
+                type Data = $data;
+
+                fn into_data(self) -> Result<$data, FetchError> {
+                    match (self.ok, self.data) {
+                        (true, Some(data)) => Ok(data),
+                        (true, None) => Err(FetchError::Json),
+                        (false, _) => Err(FetchError::api(self.error)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+response! {
+    StatsResponse => StatsData,
+    SessionResponse => SessionData,
+    GoalsResponse => Vec<GoalData, 16>,
+    ProjectsResponse => Vec<ProjectData, 16>,
+    ShopResponse => Vec<ShopItem, SHOP_PAGE>,
+    LeaderboardResponse => LeaderboardData,
+}
+
+impl ActionResponse<'_> {
+    pub fn into_result(self) -> Result<(), FetchError> {
+        match self.ok {
+            true => Ok(()),
+            false => Err(FetchError::api(self.error)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats() {
+        let (body, _): (StatsResponse, _) =
+            serde_json_core::from_slice(br#"{"ok":true,"data":{"sessions":86,"total":5160}}"#)
+                .unwrap();
+        assert!(body.ok);
+        let data = body.data.unwrap();
+        assert_eq!(data.sessions, 86);
+        assert_eq!(data.total, 5160);
+    }
+
+    #[test]
+    fn session() {
+        let (body, _): (SessionResponse, _) = serde_json_core::from_slice(
+            br#"{"ok":true,"data":{"id":"U0123","createdAt":"2024-08-01T11:35:00.000Z","time":60,"elapsed":25,"remaining":35,"endTime":"2024-08-01T12:35:00.000Z","goal":"No Goal","paused":false,"completed":false}}"#,
+        )
+        .unwrap();
+        assert!(body.ok);
+        let data = body.data.unwrap();
+        assert_eq!(data.elapsed, 25);
+        assert_eq!(data.goal, "No Goal");
+        assert!(!data.paused);
+        assert!(!data.completed);
+    }
+
+    #[test]
+    fn goals() {
+        let (body, _): (GoalsResponse, _) = serde_json_core::from_slice(
+            br#"{"ok":true,"data":[{"name":"No Goal","minutes":120},{"name":"Sprig Arcade","minutes":600}]}"#,
+        )
+        .unwrap();
+        let data = body.data.unwrap();
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[1].name, "Sprig Arcade");
+        assert_eq!(data[1].minutes, 600);
+    }
+
+    #[test]
+    fn projects() {
+        let (body, _): (ProjectsResponse, _) = serde_json_core::from_slice(
+            br#"{"ok":true,"data":[{"name":"Sprig Arcade","minutes":1260,"sessions":21},{"name":"No Goal","minutes":60,"sessions":1}]}"#,
+        )
+        .unwrap();
+        let data = body.data.unwrap();
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[0].name, "Sprig Arcade");
+        assert_eq!(data[0].minutes, 1260);
+        assert_eq!(data[0].sessions, 21);
+    }
+
+    #[test]
+    fn shop() {
+        let (body, _): (ShopResponse, _) = serde_json_core::from_slice(
+            br#"{"ok":true,"data":[{"id":"rec0123","name":"Pinecil","tickets":60},{"id":"rec0456","name":"Flipper Zero","tickets":300}]}"#,
+        )
+        .unwrap();
+        let data = body.data.unwrap();
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[1].name, "Flipper Zero");
+        assert_eq!(data[1].tickets, 300);
+    }
+
+    #[test]
+    fn leaderboard() {
+        let (body, _): (LeaderboardResponse, _) = serde_json_core::from_slice(
+            br#"{"ok":true,"data":{"rank":8,"sessions":87,"users":[{"slackId":"U0123","name":"Orpheus","sessions":212},{"slackId":"U0456","name":"Heidi","sessions":180}]}}"#,
+        )
+        .unwrap();
+        let data = body.data.unwrap();
+        assert_eq!(data.rank, 8);
+        assert_eq!(data.sessions, 87);
+        assert_eq!(data.users.len(), 2);
+        assert_eq!(data.users[0].name, "Orpheus");
+        assert_eq!(data.users[1].sessions, 180);
+
+        // a page longer than `LEADERBOARD_PAGE` doesn't fit
+        let long = br#"{"rank":1,"sessions":1,"users":[{"name":"A","sessions":1},{"name":"A","sessions":1},{"name":"A","sessions":1},{"name":"A","sessions":1},{"name":"A","sessions":1},{"name":"A","sessions":1},{"name":"B","sessions":0}]}"#;
+        assert!(serde_json_core::from_slice::<LeaderboardData>(long).is_err());
+    }
+
+    #[test]
+    fn action() {
+        let (body, _): (ActionResponse, _) = serde_json_core::from_slice(
+            br#"{"ok":true,"data":{"id":"U0123","slackId":"U0123","createdAt":"2024-08-01T11:35:00.000Z","paused":true}}"#,
+        )
+        .unwrap();
+        assert!(body.ok);
+
+        assert_eq!(SessionAction::Pause.path(), "pause");
+        assert_eq!(SessionAction::Cancel.body(), "{}");
+        let start = SessionAction::Start(String::try_from("say \"hi\"\n").unwrap());
+        assert_eq!(start.path(), "start");
+        assert_eq!(start.body(), r#"{"work":"say \"hi\""}"#);
+    }
+
+    #[test]
+    fn error() {
+        let (body, _): (StatsResponse, _) =
+            serde_json_core::from_slice(br#"{"ok":false,"error":"Unauthorized"}"#).unwrap();
+        assert!(!body.ok);
+        assert!(body.data.is_none());
+        assert_eq!(body.error, Some("Unauthorized"));
+    }
+
+    #[test]
+    fn into_data() {
+        let (body, _): (StatsResponse, _) =
+            serde_json_core::from_slice(br#"{"ok":true,"data":{"sessions":86,"total":5160}}"#)
+                .unwrap();
+        assert_eq!(body.into_data().unwrap().sessions, 86);
+
+        let (body, _): (StatsResponse, _) = serde_json_core::from_slice(br#"{"ok":true}"#).unwrap();
+        assert_eq!(body.into_data().unwrap_err(), FetchError::Json);
+
+        let (body, _): (StatsResponse, _) =
+            serde_json_core::from_slice(br#"{"ok":false,"error":"Unauthorized"}"#).unwrap();
+        let err = body.into_data().unwrap_err();
+        assert_eq!(
+            err,
+            FetchError::Api(String::try_from("Unauthorized").unwrap())
+        );
+        assert_eq!(err.kind(), ErrorKind::Rejected);
+
+        let (body, _): (ActionResponse, _) =
+            serde_json_core::from_slice(br#"{"ok":false}"#).unwrap();
+        let err = body.into_result().unwrap_err();
+        assert_eq!(err, FetchError::Api(String::new()));
+        assert_eq!(err.kind(), ErrorKind::Rejected);
+    }
+
+    #[test]
+    fn time() {
+        let (body, _): (TimeData, _) =
+            serde_json_core::from_slice(br#"{"datetime":"2024-08-01T12:00:00.000000-04:00"}"#)
+                .unwrap();
+        assert_eq!(body.datetime, "2024-08-01T12:00:00.000000-04:00");
+    }
+}
@@ -0,0 +1,179 @@
+//! When to try a failed request again.
+
+use core::str::from_utf8;
+
+use crate::api::FetchError;
+
+/// First wait after a connection error, doubled for each retry.
+const RETRY_BASE_MS: u32 = 2_000;
+/// Quick retries before waiting for the next scheduled update instead.
+const MAX_RETRIES: u8 = 4;
+/// First wait when the server is overloaded or rate limiting us.
+const THROTTLE_BASE_MS: u32 = 30_000;
+/// Longest we back off for, whatever the server asks.
+const MAX_BACKOFF_MS: u32 = 30 * 60_000;
+/// First wait before joining the network again, doubled each time.
+const REJOIN_BASE_MS: u32 = 2_000;
+/// Longest wait between attempts to join the network.
+const MAX_REJOIN_MS: u32 = 5 * 60_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Retry {
+    /// Try again after this many ms, or sooner if new data is wanted.
+    After(u32),
+    /// The server asked us to slow down, send nothing for this many ms.
+    Throttled(u32),
+    /// Not worth retrying, wait for the next scheduled update.
+    Wait,
+}
+
+/// Counts failures in a row to work out how long to back off for.
+#[derive(Debug, Default)]
+pub struct Backoff {
+    failures: u8,
+}
+
+impl Backoff {
+    pub const fn new() -> Self {
+        Self { failures: 0 }
+    }
+
+    pub fn failures(&self) -> u8 {
+        self.failures
+    }
+
+    pub fn success(&mut self) {
+        self.failures = 0;
+    }
+
+    /// `random` spreads the retries out, so a server coming back up isn't hit
+    /// by every Sprig at once.
+    pub fn failure(&mut self, err: &FetchError, random: u32) -> Retry {
+        let attempt = self.failures;
+        self.failures = self.failures.saturating_add(1);
+
+        match err {
+            FetchError::Status(429 | 500..=599, retry_after) => {
+                let backoff = jitter(exponential(THROTTLE_BASE_MS, attempt), random);
+                let asked = retry_after.map_or(0, |secs| secs.saturating_mul(1000));
+                Retry::Throttled(backoff.max(asked).min(MAX_BACKOFF_MS))
+            }
+            FetchError::Dns | FetchError::Tcp | FetchError::Tls | FetchError::Http
+                if attempt < MAX_RETRIES =>
+            {
+                Retry::After(jitter(exponential(RETRY_BASE_MS, attempt), random))
+            }
+            // the next scheduled update starts over
+            _ => {
+                self.failures = 0;
+                Retry::Wait
+            }
+        }
+    }
+
+    /// How long to wait before joining the network again. There's nothing
+    /// else to fall back on, so unlike requests this never gives up.
+    pub fn rejoin(&mut self, random: u32) -> u32 {
+        let attempt = self.failures;
+        self.failures = self.failures.saturating_add(1);
+        jitter(
+            exponential(REJOIN_BASE_MS, attempt).min(MAX_REJOIN_MS),
+            random,
+        )
+    }
+}
+
+fn exponential(base: u32, attempt: u8) -> u32 {
+    base.saturating_mul(1 << attempt.min(16))
+        .min(MAX_BACKOFF_MS)
+}
+
+/// Somewhere between half and all of `delay`.
+fn jitter(delay: u32, random: u32) -> u32 {
+    delay / 2 + random % (delay / 2 + 1)
+}
+
+/// The seconds in a `Retry-After` header. HTTP dates aren't supported, as the
+/// clock might not be set yet.
+pub fn retry_after(value: &[u8]) -> Option<u32> {
+    from_utf8(value).ok()?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries() {
+        let mut backoff = Backoff::new();
+        let delays: [Retry; 4] = core::array::from_fn(|_| backoff.failure(&FetchError::Tcp, 0));
+        assert_eq!(delays, [1_000, 2_000, 4_000, 8_000].map(Retry::After));
+        assert_eq!(backoff.failure(&FetchError::Dns, 0), Retry::Wait);
+        assert_eq!(backoff.failures(), 0);
+
+        // jitter stays within half to all of the delay
+        assert_eq!(
+            backoff.failure(&FetchError::Tls, 1_000),
+            Retry::After(2_000)
+        );
+        backoff.success();
+        assert_eq!(
+            backoff.failure(&FetchError::Tls, 1_001),
+            Retry::After(1_000)
+        );
+
+        // errors that won't go away by themselves
+        assert_eq!(backoff.failure(&FetchError::Json, 0), Retry::Wait);
+        assert_eq!(
+            backoff.failure(&FetchError::Status(401, None), 0),
+            Retry::Wait
+        );
+    }
+
+    #[test]
+    fn throttled() {
+        let mut backoff = Backoff::new();
+        let err = FetchError::Status(503, None);
+        assert_eq!(backoff.failure(&err, 0), Retry::Throttled(15_000));
+        assert_eq!(backoff.failure(&err, 0), Retry::Throttled(30_000));
+
+        // Retry-After wins when it's longer
+        let err = FetchError::Status(429, Some(300));
+        assert_eq!(backoff.failure(&err, 0), Retry::Throttled(300_000));
+        let err = FetchError::Status(429, Some(1));
+        assert_eq!(backoff.failure(&err, 0), Retry::Throttled(120_000));
+
+        // but within reason
+        let err = FetchError::Status(429, Some(u32::MAX));
+        assert_eq!(backoff.failure(&err, 0), Retry::Throttled(MAX_BACKOFF_MS));
+        // and tops out
+        for _ in 0..20 {
+            backoff.failure(&FetchError::Status(500, None), 0);
+        }
+        assert_eq!(
+            backoff.failure(&FetchError::Status(500, None), 0),
+            Retry::Throttled(MAX_BACKOFF_MS / 2)
+        );
+    }
+
+    #[test]
+    fn rejoin() {
+        let mut backoff = Backoff::new();
+        let delays: [u32; 4] = core::array::from_fn(|_| backoff.rejoin(0));
+        assert_eq!(delays, [1_000, 2_000, 4_000, 8_000]);
+        for _ in 0..20 {
+            backoff.rejoin(0);
+        }
+        assert_eq!(backoff.rejoin(0), MAX_REJOIN_MS / 2);
+
+        backoff.success();
+        assert_eq!(backoff.rejoin(0), 1_000);
+    }
+
+    #[test]
+    fn headers() {
+        assert_eq!(retry_after(b"120"), Some(120));
+        assert_eq!(retry_after(b" 5 "), Some(5));
+        assert_eq!(retry_after(b"Wed, 21 Oct 2015 07:28:00 GMT"), None);
+    }
+}
@@ -0,0 +1,544 @@
+//! Settings kept in a dedicated flash sector.
+//!
+//! The sector starts with a header followed by the fields, and ends with a
+//! CRC over everything after the magic:
+//!
+//! | bytes   | contents                        |
+//! | ------- | ------------------------------- |
+//! | 4       | `MAGIC`                         |
+//! | 2       | layout version, little endian   |
+//! | 2       | payload length, little endian   |
+//! | len     | payload                         |
+//! | 4       | CRC-32 of version, len, payload |
+//!
+//! Fields are only ever appended to the payload, with `VERSION` bumped each
+//! time, so a config saved by older firmware still loads and the new fields
+//! get their defaults.
+
+use chrono::{Datelike, NaiveDate};
+use embedded_storage::nor_flash::{NorFlash, NorFlashError, NorFlashErrorKind};
+use heapless::{String, Vec};
+
+use crate::sntp::DEFAULT_SERVERS;
+use crate::storage::crc32;
+use crate::tz::{self, TZ_LEN};
+
+const MAGIC: [u8; 4] = *b"SACF";
+pub const VERSION: u16 = 7;
+
+const HEADER_LEN: usize = 8;
+/// Largest payload that fits in the read/write buffer.
+pub const MAX_PAYLOAD: usize = 1024;
+const BUF_LEN: usize = HEADER_LEN + MAX_PAYLOAD + 4;
+
+/// Most items that can be starred in the Shop.
+pub const MAX_WISHLIST: usize = 8;
+
+/// A starred shop item, with its price from when the Shop was last opened.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WishlistItem {
+    pub name: String<32>,
+    pub tickets: u16,
+}
+
+/// Most Wi-Fi networks that can be remembered.
+pub const MAX_NETWORKS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Security {
+    Open,
+    Wpa2,
+    Wpa3,
+}
+
+/// Most time servers that can be set.
+pub const MAX_NTP_SERVERS: usize = 3;
+
+/// A Wi-Fi network to join when it's in range.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Network {
+    pub ssid: String<32>,
+    pub passwd: String<64>,
+    pub security: Security,
+    /// Higher is joined first when several are in range.
+    pub priority: u8,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub slack_id: String<16>,
+    pub api_token: String<64>,
+    // version 2
+    pub ticket_goal: u16,
+    /// Tickets earned before the season started.
+    pub ticket_offset: u16,
+    /// Minutes between fetches on the Home screen.
+    pub update_interval: u8,
+    pub end_date: NaiveDate,
+    /// Backlight brightness in percent.
+    pub backlight: u8,
+    // version 3
+    pub wishlist: Vec<WishlistItem, MAX_WISHLIST>,
+    // version 4
+    pub networks: Vec<Network, MAX_NETWORKS>,
+    // version 5
+    /// SNTP servers tried in order, `sntp::DEFAULT_SERVERS` if empty.
+    pub ntp_servers: Vec<String<32>, MAX_NTP_SERVERS>,
+    // version 6, replacing the fixed UTC offset from version 2
+    /// A POSIX TZ string, UTC if empty.
+    pub timezone: String<TZ_LEN>,
+    // version 7
+    /// The first day of the season, `end_date` is the last.
+    pub start_date: NaiveDate,
+}
+
+impl Config {
+    pub const fn new() -> Self {
+        Self {
+            slack_id: String::new(),
+            api_token: String::new(),
+            ticket_goal: 160,
+            ticket_offset: 14,
+            update_interval: 5,
+            end_date: NaiveDate::from_ymd_opt(2024, 10, 1).unwrap(),
+            backlight: 100,
+            wishlist: Vec::new(),
+            networks: Vec::new(),
+            ntp_servers: Vec::new(),
+            timezone: String::new(),
+            start_date: NaiveDate::from_ymd_opt(2024, 6, 18).unwrap(),
+        }
+    }
+
+    /// The SNTP servers to try, in order.
+    pub fn time_servers(&self) -> Vec<String<32>, MAX_NTP_SERVERS> {
+        match self.ntp_servers.is_empty() {
+            true => DEFAULT_SERVERS
+                .iter()
+                .map(|server| String::try_from(*server).unwrap())
+                .collect(),
+            false => self.ntp_servers.clone(),
+        }
+    }
+
+    /// Writes the payload into `buf`, returning its length.
+    fn encode(&self, buf: &mut [u8]) -> Result<usize, ConfigError> {
+        let mut w = Writer { buf, pos: 0 };
+        // the single network from version 1, moved to `networks`
+        w.str("")?;
+        w.str("")?;
+        w.str(&self.slack_id)?;
+        w.str(&self.api_token)?;
+        w.bytes(&self.ticket_goal.to_le_bytes())?;
+        w.bytes(&self.ticket_offset.to_le_bytes())?;
+        w.bytes(&[self.update_interval])?;
+        w.bytes(&self.end_date.num_days_from_ce().to_le_bytes())?;
+        // the UTC offset from version 2, moved to `timezone`
+        w.bytes(&0i16.to_le_bytes())?;
+        w.bytes(&[self.backlight])?;
+        w.bytes(&[self.wishlist.len() as u8])?;
+        for item in &self.wishlist {
+            w.str(&item.name)?;
+            w.bytes(&item.tickets.to_le_bytes())?;
+        }
+        w.bytes(&[self.networks.len() as u8])?;
+        for network in &self.networks {
+            w.str(&network.ssid)?;
+            w.str(&network.passwd)?;
+            w.bytes(&[network.security as u8, network.priority])?;
+        }
+        w.bytes(&[self.ntp_servers.len() as u8])?;
+        for server in &self.ntp_servers {
+            w.str(server)?;
+        }
+        w.str(&self.timezone)?;
+        w.bytes(&self.start_date.num_days_from_ce().to_le_bytes())?;
+        Ok(w.pos)
+    }
+
+    fn decode(version: u16, payload: &[u8]) -> Result<Self, ConfigError> {
+        let mut r = Reader {
+            buf: payload,
+            pos: 0,
+        };
+        let ssid: String<32> = r.str()?;
+        let passwd: String<64> = r.str()?;
+        let mut config = Self {
+            slack_id: r.str()?,
+            api_token: r.str()?,
+            ..Self::new()
+        };
+        if version < 4 && !ssid.is_empty() {
+            let security = match passwd.is_empty() {
+                true => Security::Open,
+                false => Security::Wpa2,
+            };
+            let network = Network {
+                ssid,
+                passwd,
+                security,
+                priority: 0,
+            };
+            // can't be full yet
+            let _ = config.networks.push(network);
+        }
+        if version >= 2 {
+            config.ticket_goal = u16::from_le_bytes(r.array()?);
+            config.ticket_offset = u16::from_le_bytes(r.array()?);
+            config.update_interval = r.array::<1>()?[0];
+            config.end_date = NaiveDate::from_num_days_from_ce_opt(i32::from_le_bytes(r.array()?))
+                .ok_or(ConfigError::Invalid)?;
+            let utc_offset = i16::from_le_bytes(r.array()?);
+            if version < 6 && utc_offset != 0 {
+                config.timezone = tz::fixed(utc_offset);
+            }
+            config.backlight = r.array::<1>()?[0];
+        }
+        if version >= 3 {
+            for _ in 0..r.array::<1>()?[0] {
+                let item = WishlistItem {
+                    name: r.str()?,
+                    tickets: u16::from_le_bytes(r.array()?),
+                };
+                config
+                    .wishlist
+                    .push(item)
+                    .map_err(|_| ConfigError::Invalid)?;
+            }
+        }
+        if version >= 4 {
+            for _ in 0..r.array::<1>()?[0] {
+                let ssid = r.str()?;
+                let passwd = r.str()?;
+                let [security, priority] = r.array()?;
+                let security = match security {
+                    0 => Security::Open,
+                    1 => Security::Wpa2,
+                    2 => Security::Wpa3,
+                    _ => return Err(ConfigError::Invalid),
+                };
+                let network = Network {
+                    ssid,
+                    passwd,
+                    security,
+                    priority,
+                };
+                config
+                    .networks
+                    .push(network)
+                    .map_err(|_| ConfigError::Invalid)?;
+            }
+        }
+        if version >= 5 {
+            for _ in 0..r.array::<1>()?[0] {
+                let server = r.str()?;
+                config
+                    .ntp_servers
+                    .push(server)
+                    .map_err(|_| ConfigError::Invalid)?;
+            }
+        }
+        if version >= 6 {
+            config.timezone = r.str()?;
+        }
+        if version >= 7 {
+            config.start_date =
+                NaiveDate::from_num_days_from_ce_opt(i32::from_le_bytes(r.array()?))
+                    .ok_or(ConfigError::Invalid)?;
+        }
+        Ok(config)
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigError {
+    Flash(NorFlashErrorKind),
+    /// Nothing has been saved yet (or it was saved by something else).
+    Missing,
+    /// Saved by newer firmware with a layout we don't know.
+    Version(u16),
+    Checksum,
+    /// The payload doesn't match the layout, or is too big to save.
+    Invalid,
+}
+
+impl<E: NorFlashError> From<E> for ConfigError {
+    fn from(err: E) -> Self {
+        ConfigError::Flash(err.kind())
+    }
+}
+
+/// Reads and writes the `Config` in one erase sector of `F`.
+pub struct ConfigStore<F: NorFlash> {
+    flash: F,
+    offset: u32,
+}
+
+impl<F: NorFlash> ConfigStore<F> {
+    /// `offset` has to be the start of an erase sector.
+    pub fn new(flash: F, offset: u32) -> Self {
+        Self { flash, offset }
+    }
+
+    pub fn load(&mut self) -> Result<Config, ConfigError> {
+        let mut buf = [0; BUF_LEN];
+        self.flash.read(self.offset, &mut buf[..HEADER_LEN])?;
+
+        if buf[..4] != MAGIC {
+            return Err(ConfigError::Missing);
+        }
+        let version = u16::from_le_bytes([buf[4], buf[5]]);
+        if version == 0 || version > VERSION {
+            return Err(ConfigError::Version(version));
+        }
+        let len = u16::from_le_bytes([buf[6], buf[7]]) as usize;
+        if len > MAX_PAYLOAD {
+            return Err(ConfigError::Invalid);
+        }
+
+        let end = HEADER_LEN + len;
+        self.flash.read(
+            self.offset + HEADER_LEN as u32,
+            &mut buf[HEADER_LEN..end + 4],
+        )?;
+
+        let crc = u32::from_le_bytes([buf[end], buf[end + 1], buf[end + 2], buf[end + 3]]);
+        if crc != crc32(&buf[4..end]) {
+            return Err(ConfigError::Checksum);
+        }
+
+        Config::decode(version, &buf[HEADER_LEN..end])
+    }
+
+    pub fn save(&mut self, config: &Config) -> Result<(), ConfigError> {
+        let mut buf = [0xFF; BUF_LEN];
+        let len = config.encode(&mut buf[HEADER_LEN..HEADER_LEN + MAX_PAYLOAD])?;
+        let end = HEADER_LEN + len;
+
+        buf[..4].copy_from_slice(&MAGIC);
+        buf[4..6].copy_from_slice(&VERSION.to_le_bytes());
+        buf[6..8].copy_from_slice(&(len as u16).to_le_bytes());
+        let crc = crc32(&buf[4..end]);
+        buf[end..end + 4].copy_from_slice(&crc.to_le_bytes());
+
+        // writes have to be a multiple of WRITE_SIZE, the padding is left erased
+        let write_len = (end + 4).next_multiple_of(F::WRITE_SIZE);
+
+        self.flash
+            .erase(self.offset, self.offset + F::ERASE_SIZE as u32)?;
+        self.flash.write(self.offset, &buf[..write_len])?;
+        Ok(())
+    }
+
+    pub fn release(self) -> F {
+        self.flash
+    }
+}
+
+struct Writer<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+}
+
+impl Writer<'_> {
+    fn bytes(&mut self, bytes: &[u8]) -> Result<(), ConfigError> {
+        let end = self.pos + bytes.len();
+        self.buf
+            .get_mut(self.pos..end)
+            .ok_or(ConfigError::Invalid)?
+            .copy_from_slice(bytes);
+        self.pos = end;
+        Ok(())
+    }
+
+    fn str(&mut self, s: &str) -> Result<(), ConfigError> {
+        let len = u8::try_from(s.len()).map_err(|_| ConfigError::Invalid)?;
+        self.bytes(&[len])?;
+        self.bytes(s.as_bytes())
+    }
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], ConfigError> {
+        let bytes = self
+            .buf
+            .get(self.pos..self.pos + len)
+            .ok_or(ConfigError::Invalid)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], ConfigError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.bytes(N)?);
+        Ok(array)
+    }
+
+    fn str<const N: usize>(&mut self) -> Result<String<N>, ConfigError> {
+        let len = self.bytes(1)?[0] as usize;
+        let s = core::str::from_utf8(self.bytes(len)?).map_err(|_| ConfigError::Invalid)?;
+        String::try_from(s).map_err(|_| ConfigError::Invalid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemFlash;
+
+    const OFFSET: u32 = 4096;
+
+    fn config() -> Config {
+        Config {
+            slack_id: String::try_from("U0123ABCDEF").unwrap(),
+            api_token: String::try_from("00000000-0000-0000-0000-000000000000").unwrap(),
+            ticket_goal: 200,
+            ticket_offset: 3,
+            update_interval: 10,
+            end_date: NaiveDate::from_ymd_opt(2024, 9, 1).unwrap(),
+            backlight: 60,
+            wishlist: Vec::from_slice(&[
+                WishlistItem {
+                    name: String::try_from("Pinecil").unwrap(),
+                    tickets: 60,
+                },
+                WishlistItem {
+                    name: String::try_from("Framework Laptop 13").unwrap(),
+                    tickets: 1_000,
+                },
+            ])
+            .unwrap(),
+            networks: Vec::from_slice(&[
+                Network {
+                    ssid: String::try_from("Hack Club").unwrap(),
+                    passwd: String::try_from("hunter22").unwrap(),
+                    security: Security::Wpa3,
+                    priority: 2,
+                },
+                Network {
+                    ssid: String::try_from("Hackathon Guest").unwrap(),
+                    passwd: String::new(),
+                    security: Security::Open,
+                    priority: 0,
+                },
+            ])
+            .unwrap(),
+            ntp_servers: Vec::from_slice(&[String::try_from("time.nist.gov").unwrap()]).unwrap(),
+            timezone: String::try_from("IST-5:30").unwrap(),
+            start_date: NaiveDate::from_ymd_opt(2024, 6, 1).unwrap(),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut store = ConfigStore::new(MemFlash::<8192>::new(), OFFSET);
+        store.save(&config()).unwrap();
+        assert_eq!(store.load(), Ok(config()));
+
+        // saving again has to erase first, or the bits would be ANDed together
+        let mut other = config();
+        other.slack_id = String::try_from("U0OTHER").unwrap();
+        store.save(&other).unwrap();
+        assert_eq!(store.load(), Ok(other));
+    }
+
+    #[test]
+    fn older_version() {
+        let config = config();
+        let mut payload = [0; 256];
+        let mut w = Writer {
+            buf: &mut payload,
+            pos: 0,
+        };
+        w.str("Hack Club").unwrap();
+        w.str("hunter22").unwrap();
+        w.str(&config.slack_id).unwrap();
+        w.str(&config.api_token).unwrap();
+        let len = w.pos;
+
+        let loaded = Config::decode(1, &payload[..len]).unwrap();
+        assert_eq!(loaded.slack_id, config.slack_id);
+        assert_eq!(loaded.ticket_goal, Config::new().ticket_goal);
+        assert_eq!(loaded.end_date, Config::new().end_date);
+        assert!(loaded.wishlist.is_empty());
+
+        // the network moves into the list
+        let network = &loaded.networks[0];
+        assert_eq!(network.ssid, "Hack Club");
+        assert_eq!(network.passwd, "hunter22");
+        assert_eq!(network.security, Security::Wpa2);
+        assert_eq!(loaded.time_servers()[0], DEFAULT_SERVERS[0]);
+    }
+
+    #[test]
+    fn utc_offset_becomes_timezone() {
+        let mut payload = [0; 256];
+        let mut w = Writer {
+            buf: &mut payload,
+            pos: 0,
+        };
+        for s in ["", "", "U0123ABCDEF", "token"] {
+            w.str(s).unwrap();
+        }
+        w.bytes(&160u16.to_le_bytes()).unwrap();
+        w.bytes(&14u16.to_le_bytes()).unwrap();
+        w.bytes(&[5]).unwrap();
+        w.bytes(&Config::new().end_date.num_days_from_ce().to_le_bytes())
+            .unwrap();
+        w.bytes(&(-4i16 * 60).to_le_bytes()).unwrap();
+        w.bytes(&[100]).unwrap();
+        let len = w.pos;
+
+        let loaded = Config::decode(2, &payload[..len]).unwrap();
+        assert_eq!(loaded.timezone, tz::fixed(-4 * 60));
+    }
+
+    #[test]
+    fn empty_flash() {
+        let mut store = ConfigStore::new(MemFlash::<8192>::new(), OFFSET);
+        assert_eq!(store.load(), Err(ConfigError::Missing));
+    }
+
+    #[test]
+    fn corrupted() {
+        let mut store = ConfigStore::new(MemFlash::<8192>::new(), OFFSET);
+        store.save(&config()).unwrap();
+
+        let mut flash = store.release();
+        flash.data[OFFSET as usize + HEADER_LEN + 2] ^= 0x01;
+        let mut store = ConfigStore::new(flash, OFFSET);
+        assert_eq!(store.load(), Err(ConfigError::Checksum));
+    }
+
+    #[test]
+    fn newer_version() {
+        let mut store = ConfigStore::new(MemFlash::<8192>::new(), OFFSET);
+        store.save(&config()).unwrap();
+
+        let mut flash = store.release();
+        flash.data[OFFSET as usize + 4..OFFSET as usize + 6]
+            .copy_from_slice(&(VERSION + 1).to_le_bytes());
+        let mut store = ConfigStore::new(flash, OFFSET);
+        assert_eq!(store.load(), Err(ConfigError::Version(VERSION + 1)));
+    }
+
+    #[test]
+    fn leaves_other_sectors_alone() {
+        let mut flash = MemFlash::<8192>::new();
+        flash.data[..4].copy_from_slice(&[1, 2, 3, 4]);
+        let mut store = ConfigStore::new(flash, OFFSET);
+        store.save(&config()).unwrap();
+        assert_eq!(store.release().data[..4], [1, 2, 3, 4]);
+    }
+}
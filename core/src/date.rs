@@ -0,0 +1,73 @@
+use chrono::{Datelike, NaiveDateTime};
+
+/// Days since 0001-01-01, only looking at the date.
+pub fn days_since_epoch(datetime: &NaiveDateTime) -> i32 {
+    let year = datetime.year();
+    let is_leap_year = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let mut days = (year - 1) * 365;
+    days += (year - 1) / 4 - (year - 1) / 100 + (year - 1) / 400;
+    let mut month_days = [0, 31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+    if is_leap_year {
+        month_days[2] = 29;
+    }
+    for i in 1..datetime.month() {
+        days += month_days[i as usize];
+    }
+    days += datetime.day() as i32;
+    days
+}
+
+/// Whole days between two dates, ignoring the time and order.
+pub fn days_between(date: &NaiveDateTime, other: &NaiveDateTime) -> i32 {
+    let days1 = days_since_epoch(date);
+    let days2 = days_since_epoch(other);
+    (days1 - days2).abs()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn epoch() {
+        assert_eq!(days_since_epoch(&date(1, 1, 1, 0)), 1);
+        assert_eq!(days_since_epoch(&date(2, 1, 1, 0)), 366);
+    }
+
+    #[test]
+    fn matches_chrono() {
+        for (year, month, day) in [(1900, 3, 1), (2000, 2, 29), (2024, 6, 18), (2024, 12, 31)] {
+            let datetime = date(year, month, day, 12);
+            assert_eq!(
+                days_since_epoch(&datetime),
+                datetime.date().num_days_from_ce(),
+            );
+        }
+    }
+
+    #[test]
+    fn leap_years() {
+        assert_eq!(days_between(&date(2024, 2, 28, 0), &date(2024, 3, 1, 0)), 2);
+        assert_eq!(days_between(&date(2023, 2, 28, 0), &date(2023, 3, 1, 0)), 1);
+        assert_eq!(days_between(&date(1900, 2, 28, 0), &date(1900, 3, 1, 0)), 1);
+        assert_eq!(days_between(&date(2000, 2, 28, 0), &date(2000, 3, 1, 0)), 2);
+    }
+
+    #[test]
+    fn between_ignores_time_and_order() {
+        let start = date(2024, 6, 18, 0);
+        let now = date(2024, 8, 1, 23);
+        assert_eq!(days_between(&now, &start), 44);
+        assert_eq!(days_between(&start, &now), 44);
+        assert_eq!(days_between(&now, &date(2024, 8, 1, 0)), 0);
+    }
+}
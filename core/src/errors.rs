@@ -0,0 +1,198 @@
+//! A bounded log of recent errors, shown on the Errors screen.
+
+use core::fmt::{self, Write};
+
+use chrono::NaiveDateTime;
+use heapless::{Deque, String};
+
+/// Records kept before the oldest ones are dropped.
+pub const MAX_ERRORS: usize = 16;
+/// Longest message kept, longer ones are cut short.
+pub const MESSAGE_LEN: usize = 48;
+
+/// The part of the firmware an error came from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    Wifi,
+    Api,
+    Rtc,
+    Config,
+    History,
+    Gui,
+}
+
+impl Source {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Source::Wifi => "Wifi",
+            Source::Api => "API",
+            Source::Rtc => "RTC",
+            Source::Config => "Config",
+            Source::History => "History",
+            Source::Gui => "GUI",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorKind {
+    /// Couldn't reach the server.
+    Connection,
+    /// The response wasn't what we expected.
+    Parse,
+    /// The server answered, but with an error.
+    Rejected,
+    /// Reading or writing flash failed.
+    Storage,
+    /// Something that shouldn't happen.
+    Unexpected,
+}
+
+impl ErrorKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ErrorKind::Connection => "Connection",
+            ErrorKind::Parse => "Bad response",
+            ErrorKind::Rejected => "Rejected",
+            ErrorKind::Storage => "Storage",
+            ErrorKind::Unexpected => "Unexpected",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorRecord {
+    /// `None` if it happened before the RTC was set.
+    pub time: Option<NaiveDateTime>,
+    pub source: Source,
+    pub kind: ErrorKind,
+    pub message: String<MESSAGE_LEN>,
+}
+
+impl ErrorRecord {
+    pub fn new(
+        time: Option<NaiveDateTime>,
+        source: Source,
+        kind: ErrorKind,
+        args: fmt::Arguments,
+    ) -> Self {
+        let mut message = Truncate(String::new());
+        // only fails once it is full, which is fine
+        let _ = message.write_fmt(args);
+        Self {
+            time,
+            source,
+            kind,
+            message: message.0,
+        }
+    }
+}
+
+/// Writes as much as fits, without splitting a character.
+struct Truncate<const N: usize>(String<N>);
+
+impl<const N: usize> Write for Truncate<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            self.0.push(c).map_err(|_| fmt::Error)?;
+        }
+        Ok(())
+    }
+}
+
+/// The last `N` errors, dropping the oldest when full.
+pub struct ErrorLog<const N: usize> {
+    records: Deque<ErrorRecord, N>,
+}
+
+impl<const N: usize> ErrorLog<N> {
+    pub const fn new() -> Self {
+        Self {
+            records: Deque::new(),
+        }
+    }
+
+    pub fn push(&mut self, record: ErrorRecord) {
+        if self.records.is_full() {
+            self.records.pop_front();
+        }
+        let _ = self.records.push_back(record);
+    }
+
+    /// Newest first.
+    pub fn iter(&self) -> impl Iterator<Item = &ErrorRecord> {
+        self.records.iter().rev()
+    }
+
+    /// The `index`th newest record.
+    pub fn get(&self, index: usize) -> Option<&ErrorRecord> {
+        self.iter().nth(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+}
+
+impl<const N: usize> Default for ErrorLog<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(n: u32) -> ErrorRecord {
+        ErrorRecord::new(
+            None,
+            Source::Api,
+            ErrorKind::Parse,
+            format_args!("error {}", n),
+        )
+    }
+
+    #[test]
+    fn newest_first() {
+        let mut log = ErrorLog::<3>::new();
+        for n in 0..5 {
+            log.push(record(n));
+        }
+        assert_eq!(log.len(), 3);
+        let messages: [&str; 3] = core::array::from_fn(|i| log.get(i).unwrap().message.as_str());
+        assert_eq!(messages, ["error 4", "error 3", "error 2"]);
+        assert!(log.get(3).is_none());
+
+        log.clear();
+        assert!(log.is_empty());
+    }
+
+    #[test]
+    fn truncates() {
+        let long = ErrorRecord::new(
+            None,
+            Source::Wifi,
+            ErrorKind::Connection,
+            format_args!("{:?}", [0xFFFF_u16; 16]),
+        );
+        assert_eq!(long.message.len(), MESSAGE_LEN);
+
+        // multi-byte characters aren't split
+        let wide = ErrorRecord::new(
+            None,
+            Source::Wifi,
+            ErrorKind::Connection,
+            format_args!("{}", "éééééééééééééééééééééééééééééé"),
+        );
+        assert_eq!(wide.message.chars().count(), MESSAGE_LEN / 2);
+    }
+}
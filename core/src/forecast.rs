@@ -0,0 +1,186 @@
+//! Where the recent pace ends up: when the goal is reached, and how many
+//! tickets there will be when the season ends.
+
+use chrono::{Days as DayCount, NaiveDate};
+
+use crate::history::Days;
+use crate::season::Season;
+use crate::stats::{days_until, earned};
+
+/// How far back the pace is measured.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Window {
+    Week,
+    Fortnight,
+    Season,
+}
+
+impl Window {
+    pub const ALL: [Window; 3] = [Window::Week, Window::Fortnight, Window::Season];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Window::Week => "7 day",
+            Window::Fortnight => "14 day",
+            Window::Season => "season",
+        }
+    }
+
+    pub fn next(&self) -> Window {
+        match self {
+            Window::Week => Window::Fortnight,
+            Window::Fortnight => Window::Season,
+            Window::Season => Window::Week,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Forecast {
+    /// Tickets per day over the window.
+    pub pace: f32,
+    /// When the goal is reached, `None` if it never is at this pace.
+    pub finish: Option<NaiveDate>,
+    /// Tickets earned in the season by the end of its last day.
+    pub at_end: u16,
+}
+
+impl Forecast {
+    /// Whether the goal is reached before the season ends.
+    pub fn on_time(&self, season: &Season) -> bool {
+        self.finish.is_some_and(|finish| finish <= season.end)
+    }
+}
+
+/// The forecast from the pace over `window` up to today, when there are
+/// `tickets`. Only the days in `days` can be used, so a window going back
+/// further than the history is cut short. `None` outside the season.
+pub fn forecast(
+    days: &Days,
+    season: &Season,
+    window: Window,
+    tickets: u16,
+    today: NaiveDate,
+) -> Option<Forecast> {
+    if today < season.start || today > season.end {
+        return None;
+    }
+
+    let (span, before) = match window {
+        // the offset is what there was before the season, no history needed
+        Window::Season => ((today - season.start).num_days() as u64 + 1, season.offset),
+        Window::Week | Window::Fortnight => {
+            let wanted = if window == Window::Week { 7 } else { 14 };
+            (1..=wanted)
+                .rev()
+                .find_map(|span| Some((span, days.tickets(today - DayCount::new(span))?)))?
+        }
+    };
+    let pace = tickets.saturating_sub(before) as f32 / span as f32;
+
+    let earned = earned(tickets, season);
+    let finish =
+        days_until(season.goal, earned, pace).map(|days| today + DayCount::new(days as u64));
+    let left = (season.end - today).num_days() as f32;
+    let at_end = (earned as f32 + pace * left).min(u16::MAX as f32) as u16;
+
+    Some(Forecast {
+        pace,
+        finish,
+        at_end,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::history::DAYS;
+
+    fn day(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    /// The tickets at the end of each day up to `last`.
+    fn history(last: NaiveDate, counts: &[u16]) -> Days {
+        let mut days = Days::new();
+        days.last = last;
+        for (count, tickets) in days.counts[DAYS - counts.len()..].iter_mut().zip(counts) {
+            *count = Some(*tickets);
+        }
+        days
+    }
+
+    /// 160 tickets from June 18th to October 1st, with 14 from before.
+    fn season() -> Season {
+        Season::from_config(&Config::new())
+    }
+
+    #[test]
+    fn recent_pace() {
+        // 2 a day for the last week, 1 a day the week before
+        let counts = [50, 51, 52, 53, 54, 55, 56, 57, 59, 61, 63, 65, 67, 69, 71];
+        let days = history(day(8, 1), &counts);
+
+        let week = forecast(&days, &season(), Window::Week, 71, day(8, 1)).unwrap();
+        assert_eq!(week.pace, 2.);
+        // 57 earned, 103 to go
+        assert_eq!(week.finish, Some(day(9, 22)));
+        assert_eq!(week.at_end, 57 + 2 * 61);
+        assert!(week.on_time(&season()));
+
+        let fortnight = forecast(&days, &season(), Window::Fortnight, 71, day(8, 1)).unwrap();
+        assert_eq!(fortnight.pace, 1.5);
+        assert_eq!(fortnight.finish, Some(day(10, 9)));
+        assert_eq!(fortnight.at_end, 57 + 91);
+        assert!(!fortnight.on_time(&season()));
+    }
+
+    #[test]
+    fn whole_season() {
+        // 45 days in, 45 earned
+        let forecast = forecast(&Days::new(), &season(), Window::Season, 59, day(8, 1)).unwrap();
+        assert_eq!(forecast.pace, 1.);
+        assert_eq!(forecast.finish, Some(day(8, 1) + DayCount::new(115)));
+        assert_eq!(forecast.at_end, 45 + 61);
+    }
+
+    #[test]
+    fn short_history() {
+        // only three days logged, so the week is cut to two
+        let days = history(day(8, 1), &[60, 63, 66]);
+        let week = forecast(&days, &season(), Window::Week, 66, day(8, 1)).unwrap();
+        assert_eq!(week.pace, 3.);
+
+        assert_eq!(
+            forecast(&Days::new(), &season(), Window::Week, 66, day(8, 1)),
+            None
+        );
+    }
+
+    #[test]
+    fn stalled_and_done() {
+        let days = history(day(8, 1), &[60; 8]);
+        let stalled = forecast(&days, &season(), Window::Week, 60, day(8, 1)).unwrap();
+        assert_eq!(stalled.finish, None);
+        assert_eq!(stalled.at_end, 46);
+        assert!(!stalled.on_time(&season()));
+
+        let done = forecast(&days, &season(), Window::Week, 180, day(8, 1)).unwrap();
+        assert_eq!(done.finish, Some(day(8, 1)));
+        assert!(done.on_time(&season()));
+    }
+
+    #[test]
+    fn outside_the_season() {
+        let days = history(day(8, 1), &[60; 8]);
+        assert_eq!(
+            forecast(&days, &season(), Window::Season, 60, day(6, 17)),
+            None
+        );
+        assert_eq!(
+            forecast(&days, &season(), Window::Season, 60, day(10, 2)),
+            None
+        );
+    }
+}
@@ -0,0 +1,467 @@
+//! Ticket counts over time, logged to a ring of flash sectors.
+//!
+//! Each sector starts with a header, and is then filled with records one at
+//! a time. When the newest sector is full the oldest one is erased and
+//! reused, so every sector is erased as often as the others.
+//!
+//! | bytes | contents                                 |
+//! | ----- | ---------------------------------------- |
+//! | 4     | `MAGIC`                                  |
+//! | 4     | sequence number, little endian           |
+//! | 8 * n | records                                  |
+//!
+//! | bytes | contents                                 |
+//! | ----- | ---------------------------------------- |
+//! | 4     | seconds since 1970 in UTC, little endian |
+//! | 2     | tickets, little endian                   |
+//! | 2     | low half of the CRC-32 of the above      |
+//!
+//! A record that was cut short by losing power fails its CRC and is skipped.
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use embedded_storage::nor_flash::{NorFlash, NorFlashError, NorFlashErrorKind};
+
+use crate::storage::crc32;
+use crate::tz::TimeZone;
+
+const MAGIC: [u8; 4] = *b"SAHL";
+const HEADER_LEN: usize = 8;
+const RECORD_LEN: usize = 8;
+
+/// Days the screens get from the log, enough for a whole season.
+pub const DAYS: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    /// In UTC, like the RTC.
+    pub time: NaiveDateTime,
+    pub tickets: u16,
+}
+
+impl Sample {
+    fn encode(&self) -> Result<[u8; RECORD_LEN], HistoryError> {
+        let secs =
+            u32::try_from(self.time.and_utc().timestamp()).map_err(|_| HistoryError::Time)?;
+        let mut record = [0; RECORD_LEN];
+        record[..4].copy_from_slice(&secs.to_le_bytes());
+        record[4..6].copy_from_slice(&self.tickets.to_le_bytes());
+        let crc = crc32(&record[..6]) as u16;
+        record[6..].copy_from_slice(&crc.to_le_bytes());
+        Ok(record)
+    }
+
+    fn decode(record: &[u8; RECORD_LEN]) -> Option<Self> {
+        if crc32(&record[..6]) as u16 != u16::from_le_bytes([record[6], record[7]]) {
+            return None;
+        }
+        let secs = u32::from_le_bytes(record[..4].try_into().unwrap());
+        Some(Self {
+            time: DateTime::from_timestamp(secs as i64, 0)?.naive_utc(),
+            tickets: u16::from_le_bytes([record[4], record[5]]),
+        })
+    }
+}
+
+/// The tickets at the end of each of the `DAYS` days up to `last`, see
+/// `History::recent`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Days {
+    pub last: NaiveDate,
+    pub counts: [Option<u16>; DAYS],
+}
+
+impl Days {
+    pub const fn new() -> Self {
+        Self {
+            last: NaiveDate::MIN,
+            counts: [None; DAYS],
+        }
+    }
+
+    /// The tickets at the end of `day`. Nothing has been logged after `last`
+    /// yet, so later days still have its count.
+    pub fn tickets(&self, day: NaiveDate) -> Option<u16> {
+        let back = (self.last - day).num_days().max(0);
+        let index = (DAYS as i64 - 1).checked_sub(back)?;
+        self.counts[usize::try_from(index).ok()?]
+    }
+
+    /// The tickets earned on `day`, if it and the day before are known.
+    pub fn earned(&self, day: NaiveDate) -> Option<u16> {
+        let before = self.tickets(day.pred_opt()?)?;
+        Some(self.tickets(day)?.saturating_sub(before))
+    }
+}
+
+impl Default for Days {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HistoryError {
+    Flash(NorFlashErrorKind),
+    /// Before 1970 or after 2106, which don't fit in a record.
+    Time,
+}
+
+impl<E: NorFlashError> From<E> for HistoryError {
+    fn from(err: E) -> Self {
+        HistoryError::Flash(err.kind())
+    }
+}
+
+/// An append only log of `Sample`s in `sectors` erase sectors of `F`.
+pub struct History<F: NorFlash> {
+    flash: F,
+    offset: u32,
+    sectors: u32,
+    /// The sector being written to, and its sequence number.
+    head: u32,
+    seq: u32,
+    /// Records already in the head sector.
+    used: u32,
+    last: Option<Sample>,
+}
+
+impl<F: NorFlash> History<F> {
+    /// Records that fit in a sector.
+    const PER_SECTOR: u32 = ((F::ERASE_SIZE - HEADER_LEN) / RECORD_LEN) as u32;
+
+    /// Picks up where the log left off, or starts a new one. `offset` has to
+    /// be the start of an erase sector, and there have to be at least two.
+    pub fn open(flash: F, offset: u32, sectors: u32) -> Result<Self, HistoryError> {
+        let mut history = Self {
+            flash,
+            offset,
+            sectors,
+            head: 0,
+            seq: 0,
+            used: 0,
+            last: None,
+        };
+
+        let mut newest = None;
+        for sector in 0..sectors {
+            if let Some(seq) = history.sequence(sector)? {
+                if newest.is_none_or(|(_, newest)| seq > newest) {
+                    newest = Some((sector, seq));
+                }
+            }
+        }
+
+        match newest {
+            Some((sector, seq)) => {
+                history.head = sector;
+                history.seq = seq;
+                while history.used < Self::PER_SECTOR {
+                    if history.read(sector, history.used)? == [0xFF; RECORD_LEN] {
+                        break;
+                    }
+                    history.used += 1;
+                }
+                let mut last = None;
+                history.for_each(|sample| last = Some(sample))?;
+                history.last = last;
+            }
+            None => history.start(0, 0)?,
+        }
+        Ok(history)
+    }
+
+    /// The newest sample.
+    pub fn last(&self) -> Option<Sample> {
+        self.last
+    }
+
+    /// Appends `sample`, unless the tickets haven't changed since the last
+    /// one. Returns whether it was written.
+    pub fn record(&mut self, sample: Sample) -> Result<bool, HistoryError> {
+        if self.last.is_some_and(|last| last.tickets == sample.tickets) {
+            return Ok(false);
+        }
+        let record = sample.encode()?;
+
+        if self.used == Self::PER_SECTOR {
+            // the oldest sector makes way
+            self.start((self.head + 1) % self.sectors, self.seq.wrapping_add(1))?;
+        }
+        let offset = self.record_offset(self.head, self.used);
+        self.flash.write(offset, &record)?;
+        self.used += 1;
+        self.last = Some(sample);
+        Ok(true)
+    }
+
+    /// Every sample, oldest first.
+    pub fn for_each(&mut self, mut f: impl FnMut(Sample)) -> Result<(), HistoryError> {
+        for step in 1..=self.sectors {
+            let sector = (self.head + step) % self.sectors;
+            // erased, or left over from before the log wrapped to the start
+            match self.sequence(sector)? {
+                Some(seq) if self.seq.wrapping_sub(seq) < self.sectors => {}
+                _ => continue,
+            }
+            for index in 0..Self::PER_SECTOR {
+                let record = self.read(sector, index)?;
+                if record == [0xFF; RECORD_LEN] {
+                    break;
+                }
+                if let Some(sample) = Sample::decode(&record) {
+                    f(sample);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The samples taken from the start of `from` to the end of `to`, in
+    /// `zone`'s days.
+    pub fn samples(
+        &mut self,
+        from: NaiveDate,
+        to: NaiveDate,
+        zone: &TimeZone,
+        mut f: impl FnMut(Sample),
+    ) -> Result<(), HistoryError> {
+        self.for_each(|sample| {
+            let day = zone.to_local(sample.time).date();
+            if from <= day && day <= to {
+                f(sample);
+            }
+        })
+    }
+
+    /// The tickets at the end of each of `counts.len()` days from `first`, in
+    /// `zone`'s days. A day without samples keeps the day before's count,
+    /// days before the first sample are `None`.
+    pub fn days(
+        &mut self,
+        first: NaiveDate,
+        zone: &TimeZone,
+        counts: &mut [Option<u16>],
+    ) -> Result<(), HistoryError> {
+        counts.fill(None);
+        let mut before = None;
+        self.for_each(|sample| {
+            let day = zone.to_local(sample.time).date();
+            match usize::try_from((day - first).num_days()) {
+                Ok(i) if i < counts.len() => counts[i] = Some(sample.tickets),
+                Ok(_) => {}
+                Err(_) => before = Some(sample.tickets),
+            }
+        })?;
+
+        let mut carried = before;
+        for count in counts {
+            match count {
+                Some(tickets) => carried = Some(*tickets),
+                None => *count = carried,
+            }
+        }
+        Ok(())
+    }
+
+    /// The `DAYS` days up to `today`.
+    pub fn recent(&mut self, today: NaiveDate, zone: &TimeZone) -> Result<Days, HistoryError> {
+        let mut days = Days {
+            last: today,
+            counts: [None; DAYS],
+        };
+        let first = today - chrono::Days::new(DAYS as u64 - 1);
+        self.days(first, zone, &mut days.counts)?;
+        Ok(days)
+    }
+
+    pub fn release(self) -> F {
+        self.flash
+    }
+
+    /// Erases `sector` and makes it the head.
+    fn start(&mut self, sector: u32, seq: u32) -> Result<(), HistoryError> {
+        let start = self.offset + sector * F::ERASE_SIZE as u32;
+        self.flash.erase(start, start + F::ERASE_SIZE as u32)?;
+
+        let mut header = [0; HEADER_LEN];
+        header[..4].copy_from_slice(&MAGIC);
+        header[4..].copy_from_slice(&seq.to_le_bytes());
+        self.flash.write(start, &header)?;
+
+        self.head = sector;
+        self.seq = seq;
+        self.used = 0;
+        Ok(())
+    }
+
+    /// `sector`'s sequence number, if it has been started.
+    fn sequence(&mut self, sector: u32) -> Result<Option<u32>, HistoryError> {
+        let mut header = [0; HEADER_LEN];
+        self.flash
+            .read(self.offset + sector * F::ERASE_SIZE as u32, &mut header)?;
+        if header[..4] != MAGIC {
+            return Ok(None);
+        }
+        Ok(Some(u32::from_le_bytes(header[4..].try_into().unwrap())))
+    }
+
+    fn read(&mut self, sector: u32, index: u32) -> Result<[u8; RECORD_LEN], HistoryError> {
+        let mut record = [0; RECORD_LEN];
+        self.flash
+            .read(self.record_offset(sector, index), &mut record)?;
+        Ok(record)
+    }
+
+    fn record_offset(&self, sector: u32, index: u32) -> u32 {
+        self.offset + sector * F::ERASE_SIZE as u32 + HEADER_LEN as u32 + index * RECORD_LEN as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemFlash;
+
+    /// Three small sectors, 31 records each.
+    type Flash = MemFlash<768, 256>;
+
+    fn at(day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 7, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    fn sample(day: u32, hour: u32, tickets: u16) -> Sample {
+        Sample {
+            time: at(day, hour),
+            tickets,
+        }
+    }
+
+    fn all(history: &mut History<Flash>) -> heapless::Vec<Sample, 128> {
+        let mut samples = heapless::Vec::new();
+        history
+            .for_each(|sample| samples.push(sample).unwrap())
+            .unwrap();
+        samples
+    }
+
+    #[test]
+    fn records_and_reopens() {
+        let mut history = History::open(Flash::new(), 0, 3).unwrap();
+        assert_eq!(history.last(), None);
+
+        assert!(history.record(sample(1, 9, 10)).unwrap());
+        assert!(!history.record(sample(1, 10, 10)).unwrap());
+        assert!(history.record(sample(1, 11, 11)).unwrap());
+        assert_eq!(all(&mut history), [sample(1, 9, 10), sample(1, 11, 11)]);
+
+        let mut history = History::open(history.release(), 0, 3).unwrap();
+        assert_eq!(history.last(), Some(sample(1, 11, 11)));
+        assert!(!history.record(sample(1, 12, 11)).unwrap());
+        assert!(history.record(sample(1, 13, 12)).unwrap());
+        assert_eq!(all(&mut history).len(), 3);
+    }
+
+    #[test]
+    fn wraps_around() {
+        let mut history = History::open(Flash::new(), 0, 3).unwrap();
+        for tickets in 0..100 {
+            history
+                .record(sample(
+                    1 + tickets as u32 / 24,
+                    tickets as u32 % 24,
+                    tickets,
+                ))
+                .unwrap();
+        }
+
+        // the first sector was reused for the last 7
+        let samples = all(&mut history);
+        assert_eq!(samples.len(), 31 + 31 + 7);
+        assert!(samples
+            .iter()
+            .zip(31..)
+            .all(|(sample, tickets)| sample.tickets == tickets));
+
+        let mut history = History::open(history.release(), 0, 3).unwrap();
+        assert_eq!(history.last().unwrap().tickets, 99);
+        history.record(sample(20, 0, 100)).unwrap();
+        assert_eq!(all(&mut history).len(), 70);
+    }
+
+    #[test]
+    fn skips_torn_records() {
+        let mut history = History::open(Flash::new(), 0, 3).unwrap();
+        for tickets in 1..=3 {
+            history.record(sample(1, tickets as u32, tickets)).unwrap();
+        }
+
+        let mut flash = history.release();
+        flash.data[HEADER_LEN + RECORD_LEN + 4] = 0;
+        let mut history = History::open(flash, 0, 3).unwrap();
+        assert_eq!(all(&mut history), [sample(1, 1, 1), sample(1, 3, 3)]);
+    }
+
+    #[test]
+    fn out_of_range() {
+        let mut history = History::open(Flash::new(), 0, 3).unwrap();
+        let old = Sample {
+            time: NaiveDate::from_ymd_opt(1960, 1, 1)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+            tickets: 1,
+        };
+        assert_eq!(history.record(old), Err(HistoryError::Time));
+        assert_eq!(history.last(), None);
+    }
+
+    #[test]
+    fn by_day() {
+        let mut history = History::open(Flash::new(), 0, 3).unwrap();
+        for (day, hour, tickets) in [(1, 12, 5), (3, 2, 6), (3, 20, 8), (6, 1, 9)] {
+            history.record(sample(day, hour, tickets)).unwrap();
+        }
+        let day = |day| NaiveDate::from_ymd_opt(2024, 7, day).unwrap();
+
+        let mut counts = [Some(0); 5];
+        history.days(day(2), &TimeZone::UTC, &mut counts).unwrap();
+        assert_eq!(counts, [Some(5), Some(8), Some(8), Some(8), Some(9)]);
+
+        // 20:00 and 01:00 UTC are the evening before in New York
+        let new_york = TimeZone::parse("EST5EDT,M3.2.0,M11.1.0").unwrap();
+        history.days(day(1), &new_york, &mut counts).unwrap();
+        assert_eq!(counts, [Some(5), Some(6), Some(8), Some(8), Some(9)]);
+
+        let mut counts = [Some(0); 2];
+        history.days(day(30), &TimeZone::UTC, &mut counts).unwrap();
+        assert_eq!(counts, [Some(9), Some(9)]);
+        let mut before = [Some(0); 1];
+        history
+            .days(
+                NaiveDate::from_ymd_opt(2024, 6, 30).unwrap(),
+                &TimeZone::UTC,
+                &mut before,
+            )
+            .unwrap();
+        assert_eq!(before, [None]);
+
+        let days = history.recent(day(6), &TimeZone::UTC).unwrap();
+        assert_eq!(days.tickets(day(4)), Some(8));
+        assert_eq!(days.tickets(day(9)), Some(9));
+        assert_eq!(days.earned(day(3)), Some(3));
+        assert_eq!(days.earned(day(4)), Some(0));
+        assert_eq!(days.earned(day(1)), None);
+        assert_eq!(days.tickets(day(6) - chrono::Days::new(DAYS as u64)), None);
+
+        let mut samples = heapless::Vec::<_, 4>::new();
+        history
+            .samples(day(3), day(5), &TimeZone::UTC, |sample| {
+                samples.push(sample.tickets).unwrap()
+            })
+            .unwrap();
+        assert_eq!(samples, [6, 8]);
+    }
+}
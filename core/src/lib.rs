@@ -0,0 +1,25 @@
+//! Hardware-free logic and data models for Sprig Arcade.
+//!
+//! Everything in here builds for the host, so it can be tested with
+//! `cargo test-host`.
+
+#![no_std]
+
+pub mod api;
+pub mod backoff;
+pub mod config;
+pub mod date;
+pub mod errors;
+pub mod forecast;
+pub mod history;
+pub mod nav;
+pub mod networks;
+pub mod provision;
+pub mod season;
+pub mod settings;
+pub mod shop;
+pub mod sntp;
+pub mod stats;
+pub mod storage;
+pub mod streak;
+pub mod tz;
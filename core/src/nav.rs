@@ -0,0 +1,57 @@
+//! Moving along a nav bar of `len` buttons, which wraps around at both ends.
+//! Buttons are numbered from the left.
+
+pub fn left(button: usize, len: usize) -> usize {
+    (button + len - 1) % len
+}
+
+pub fn right(button: usize, len: usize) -> usize {
+    (button + 1) % len
+}
+
+pub fn are_neighbours(a: usize, b: usize, len: usize) -> bool {
+    a != b && (left(a, len) == b || right(a, len) == b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEN: usize = 8;
+
+    #[test]
+    fn right_visits_every_button() {
+        let mut button = 0;
+        for expected in 0..LEN {
+            assert_eq!(button, expected);
+            button = right(button, LEN);
+        }
+        assert_eq!(button, 0);
+    }
+
+    #[test]
+    fn left_undoes_right() {
+        for button in 0..LEN {
+            assert_eq!(left(right(button, LEN), LEN), button);
+            assert_eq!(right(left(button, LEN), LEN), button);
+        }
+    }
+
+    #[test]
+    fn neighbours_match_left_and_right() {
+        for button in 0..LEN {
+            for other in 0..LEN {
+                let expected = other == left(button, LEN) || other == right(button, LEN);
+                assert_eq!(are_neighbours(button, other, LEN), expected);
+                assert_eq!(are_neighbours(other, button, LEN), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn single_button() {
+        assert_eq!(left(0, 1), 0);
+        assert_eq!(right(0, 1), 0);
+        assert!(!are_neighbours(0, 0, 1));
+    }
+}
@@ -0,0 +1,82 @@
+//! Picking which of the known Wi-Fi networks to join.
+
+use heapless::Vec;
+
+use crate::config::{Network, MAX_NETWORKS};
+
+/// There are already `MAX_NETWORKS` networks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NetworksFull;
+
+/// Adds a network, or updates it if its SSID is already known.
+pub fn remember(
+    networks: &mut Vec<Network, MAX_NETWORKS>,
+    network: Network,
+) -> Result<(), NetworksFull> {
+    match networks.iter_mut().find(|known| known.ssid == network.ssid) {
+        Some(known) => *known = network,
+        None => networks.push(network).map_err(|_| NetworksFull)?,
+    }
+    Ok(())
+}
+
+/// The indices of `known` in the order they should be tried: the ones in
+/// range first, then the rest in case they're hidden. Each group goes by
+/// priority, then by the order they were added.
+pub fn join_order<S: AsRef<str>>(known: &[Network], in_range: &[S]) -> Vec<usize, MAX_NETWORKS> {
+    let mut order: Vec<usize, MAX_NETWORKS> = (0..known.len().min(MAX_NETWORKS)).collect();
+    order.sort_unstable_by_key(|&i| {
+        let seen = in_range.iter().any(|ssid| ssid.as_ref() == known[i].ssid);
+        (!seen, u8::MAX - known[i].priority, i)
+    });
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use heapless::String;
+
+    use super::*;
+    use crate::config::Security;
+
+    fn network(ssid: &str, priority: u8) -> Network {
+        Network {
+            ssid: String::try_from(ssid).unwrap(),
+            passwd: String::try_from("hunter22").unwrap(),
+            security: Security::Wpa2,
+            priority,
+        }
+    }
+
+    #[test]
+    fn order() {
+        let known = [
+            network("Home", 1),
+            network("School", 0),
+            network("Hackathon", 2),
+            network("Phone", 1),
+        ];
+
+        // everything is tried when nothing is in range
+        assert_eq!(join_order::<&str>(&known, &[]), [2, 0, 3, 1]);
+        assert_eq!(join_order(&known, &["School", "Cafe"]), [1, 2, 0, 3]);
+        assert_eq!(join_order(&known, &["Phone", "Home"]), [0, 3, 2, 1]);
+    }
+
+    #[test]
+    fn remembering() {
+        let mut networks = Vec::new();
+        remember(&mut networks, network("Home", 0)).unwrap();
+        remember(&mut networks, network("School", 0)).unwrap();
+        remember(&mut networks, network("Home", 3)).unwrap();
+        assert_eq!(networks.len(), 2);
+        assert_eq!(networks[0].priority, 3);
+
+        remember(&mut networks, network("Hackathon", 0)).unwrap();
+        remember(&mut networks, network("Phone", 0)).unwrap();
+        assert_eq!(
+            remember(&mut networks, network("Cafe", 0)),
+            Err(NetworksFull)
+        );
+    }
+}
@@ -0,0 +1,641 @@
+//! Setting a Sprig up from a phone. While provisioning, the Sprig runs its
+//! own open network, hands out addresses, answers every DNS lookup with
+//! itself and serves a form for the Wi-Fi and API details.
+
+use core::fmt::Write as _;
+
+use embedded_io_async::{Read, Write};
+use heapless::{String, Vec};
+
+use crate::config::{Config, Network, Security};
+use crate::networks::remember;
+
+/// The Sprig's address on its own network.
+pub const ADDRESS: [u8; 4] = [192, 168, 4, 1];
+
+/// Longest request read, the form is a lot smaller.
+pub const MAX_REQUEST: usize = 1024;
+
+/// How long phones keep their address, in seconds.
+const LEASE_SECS: u32 = 60 * 60;
+
+/// What happened to a request.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Handled {
+    /// Sent the form.
+    Form,
+    /// The form was filled in and the config updated.
+    Saved,
+    /// The form was filled in wrong, and sent back saying why.
+    Invalid(FormError),
+    /// Not a request we understand.
+    BadRequest,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FormError {
+    MissingSsid,
+    /// WPA needs at least 8 characters.
+    ShortPassword,
+    TooLong,
+    NetworksFull,
+    Malformed,
+}
+
+impl FormError {
+    pub fn message(&self) -> &'static str {
+        match self {
+            FormError::MissingSsid => "Enter the name of the Wi-Fi network.",
+            FormError::ShortPassword => "WPA passwords are at least 8 characters.",
+            FormError::TooLong => "One of the fields is too long.",
+            FormError::NetworksFull => "Already remembering 4 networks.",
+            FormError::Malformed => "Couldn't read the form, try again.",
+        }
+    }
+}
+
+const FORM_HEAD: &str = "<!DOCTYPE html><html><head>\
+<meta name=\"viewport\" content=\"width=device-width\">\
+<title>Sprig Arcade</title></head><body><h1>Sprig Arcade setup</h1><p>";
+
+const FORM_TAIL: &str = "</p><form method=\"post\" action=\"/save\">\
+<p><label>Wi-Fi network<br><input name=\"ssid\" maxlength=\"32\" required></label></p>\
+<p><label>Password<br><input name=\"passwd\" type=\"password\" maxlength=\"64\"></label></p>\
+<p><label>Security<br><select name=\"security\">\
+<option value=\"wpa2\">WPA2</option><option value=\"wpa3\">WPA3</option>\
+<option value=\"open\">Open</option></select></label></p>\
+<p><label>Slack ID<br><input name=\"slack_id\" maxlength=\"16\"></label></p>\
+<p><label>API token<br><input name=\"api_token\" maxlength=\"64\"></label></p>\
+<p>Leave the Slack ID or API token empty to keep the current one.</p>\
+<button>Save</button></form></body></html>";
+
+const SAVED: &str = "<!DOCTYPE html><html><head>\
+<meta name=\"viewport\" content=\"width=device-width\">\
+<title>Sprig Arcade</title></head><body><h1>Saved!</h1>\
+<p>The Sprig is restarting and will join the network.</p></body></html>";
+
+/// Reads one request from `stream` and answers it. Every GET gets the form,
+/// so phones checking for a captive portal show it straight away. A POST to
+/// `/save` updates `config`, which is left alone unless it returns `Saved`.
+pub async fn serve<S: Read + Write>(
+    stream: &mut S,
+    config: &mut Config,
+) -> Result<Handled, S::Error> {
+    let mut buf = [0; MAX_REQUEST];
+    let handled = match read_request(stream, &mut buf).await? {
+        Some(Request::Get) => Handled::Form,
+        Some(Request::Save(body)) => {
+            let mut updated = config.clone();
+            match parse_form(body).and_then(|form| apply(form, &mut updated)) {
+                Ok(()) => {
+                    *config = updated;
+                    Handled::Saved
+                }
+                Err(err) => Handled::Invalid(err),
+            }
+        }
+        None => Handled::BadRequest,
+    };
+
+    match handled {
+        Handled::Form => respond(stream, "200 OK", &[FORM_HEAD, FORM_TAIL]).await?,
+        Handled::Saved => respond(stream, "200 OK", &[SAVED]).await?,
+        Handled::Invalid(err) => {
+            respond(
+                stream,
+                "400 Bad Request",
+                &[FORM_HEAD, err.message(), FORM_TAIL],
+            )
+            .await?
+        }
+        Handled::BadRequest => respond(stream, "400 Bad Request", &[]).await?,
+    }
+    Ok(handled)
+}
+
+enum Request<'a> {
+    Get,
+    /// The urlencoded body.
+    Save(&'a [u8]),
+}
+
+/// Reads the headers, and the body if there is one.
+async fn read_request<'a, S: Read>(
+    stream: &mut S,
+    buf: &'a mut [u8],
+) -> Result<Option<Request<'a>>, S::Error> {
+    let mut len = 0;
+    let head = loop {
+        if let Some(end) = find(&buf[..len], b"\r\n\r\n") {
+            break end + 4;
+        }
+        if len == buf.len() {
+            return Ok(None);
+        }
+        let read = stream.read(&mut buf[len..]).await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        len += read;
+    };
+
+    let Ok(headers) = core::str::from_utf8(&buf[..head]) else {
+        return Ok(None);
+    };
+    let mut lines = headers.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default();
+    let path = request_line.next().unwrap_or_default();
+
+    match (method, path) {
+        ("GET", _) => Ok(Some(Request::Get)),
+        ("POST", "/save") => {
+            let length = lines
+                .filter_map(|line| line.split_once(':'))
+                .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+                .and_then(|(_, value)| value.trim().parse::<usize>().ok());
+            let Some(end) = length.and_then(|length| head.checked_add(length)) else {
+                return Ok(None);
+            };
+            if end > buf.len() {
+                return Ok(None);
+            }
+            while len < end {
+                let read = stream.read(&mut buf[len..end]).await?;
+                if read == 0 {
+                    return Ok(None);
+                }
+                len += read;
+            }
+            Ok(Some(Request::Save(&buf[head..end])))
+        }
+        _ => Ok(None),
+    }
+}
+
+async fn respond<S: Write>(stream: &mut S, status: &str, body: &[&str]) -> Result<(), S::Error> {
+    let length: usize = body.iter().map(|part| part.len()).sum();
+    let mut head: String<128> = String::new();
+    // fits, the status and length are short
+    let _ = write!(
+        head,
+        "HTTP/1.1 {}\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status, length
+    );
+    stream.write_all(head.as_bytes()).await?;
+    for part in body {
+        stream.write_all(part.as_bytes()).await?;
+    }
+    stream.flush().await
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Form {
+    ssid: String<32>,
+    passwd: String<64>,
+    security: Option<Security>,
+    slack_id: String<16>,
+    api_token: String<64>,
+}
+
+fn parse_form(body: &[u8]) -> Result<Form, FormError> {
+    let mut form = Form::default();
+    for field in body.split(|&b| b == b'&').filter(|field| !field.is_empty()) {
+        let (name, value) = match field.iter().position(|&b| b == b'=') {
+            Some(i) => (&field[..i], &field[i + 1..]),
+            None => (field, &field[..0]),
+        };
+        match name {
+            b"ssid" => form.ssid = decode(value)?,
+            b"passwd" => form.passwd = decode(value)?,
+            b"security" => {
+                form.security = Some(match value {
+                    b"open" => Security::Open,
+                    b"wpa2" => Security::Wpa2,
+                    b"wpa3" => Security::Wpa3,
+                    _ => return Err(FormError::Malformed),
+                })
+            }
+            b"slack_id" => form.slack_id = decode(value)?,
+            b"api_token" => form.api_token = decode(value)?,
+            // anything else is ignored
+            _ => {}
+        }
+    }
+    Ok(form)
+}
+
+/// Undoes urlencoding: `+` is a space and `%XX` a byte.
+fn decode<const N: usize>(value: &[u8]) -> Result<String<N>, FormError> {
+    let mut bytes: Vec<u8, N> = Vec::new();
+    let mut i = 0;
+    while i < value.len() {
+        let byte = match value[i] {
+            b'+' => b' ',
+            b'%' => {
+                let hex = value.get(i + 1..i + 3).ok_or(FormError::Malformed)?;
+                let hex = core::str::from_utf8(hex).map_err(|_| FormError::Malformed)?;
+                i += 2;
+                u8::from_str_radix(hex, 16).map_err(|_| FormError::Malformed)?
+            }
+            byte => byte,
+        };
+        bytes.push(byte).map_err(|_| FormError::TooLong)?;
+        i += 1;
+    }
+    String::from_utf8(bytes).map_err(|_| FormError::Malformed)
+}
+
+fn apply(form: Form, config: &mut Config) -> Result<(), FormError> {
+    let ssid = form.ssid.trim();
+    if ssid.is_empty() {
+        return Err(FormError::MissingSsid);
+    }
+    let security = form.security.unwrap_or(if form.passwd.is_empty() {
+        Security::Open
+    } else {
+        Security::Wpa2
+    });
+    if security != Security::Open && form.passwd.len() < 8 {
+        return Err(FormError::ShortPassword);
+    }
+
+    // the network set up last is the one to join first
+    let priority = config
+        .networks
+        .iter()
+        .filter(|known| known.ssid != ssid)
+        .map(|known| known.priority.saturating_add(1))
+        .max()
+        .unwrap_or(0);
+    let network = Network {
+        // no longer than the untrimmed one
+        ssid: String::try_from(ssid).unwrap(),
+        passwd: form.passwd,
+        security,
+        priority,
+    };
+    remember(&mut config.networks, network).map_err(|_| FormError::NetworksFull)?;
+
+    if !form.slack_id.is_empty() {
+        config.slack_id = form.slack_id;
+    }
+    if !form.api_token.is_empty() {
+        config.api_token = form.api_token;
+    }
+    Ok(())
+}
+
+/// Answers a DHCP discover with an offer and a request with an ack, writing
+/// the reply to `out`. Each phone gets an address picked from its MAC, and is
+/// told to use the Sprig as its router and DNS server.
+pub fn dhcp_reply(request: &[u8], out: &mut [u8; 300]) -> Option<usize> {
+    const MAGIC: [u8; 4] = [99, 130, 83, 99];
+
+    if request.len() < 240 || request[0] != 1 || request[236..240] != MAGIC {
+        return None;
+    }
+    let reply = match dhcp_option(&request[240..], 53)? {
+        // discover
+        [1] => 2,
+        // request
+        [3] => 5,
+        _ => return None,
+    };
+
+    out.fill(0);
+    out[0] = 2;
+    out[1] = 1;
+    out[2] = 6;
+    // transaction id and flags
+    out[4..8].copy_from_slice(&request[4..8]);
+    out[10..12].copy_from_slice(&request[10..12]);
+    out[16..20].copy_from_slice(&lease(&request[28..34]));
+    out[20..24].copy_from_slice(&ADDRESS);
+    out[28..44].copy_from_slice(&request[28..44]);
+    out[236..240].copy_from_slice(&MAGIC);
+
+    let [a, b, c, d] = ADDRESS;
+    let [l0, l1, l2, l3] = LEASE_SECS.to_be_bytes();
+    let options = [
+        53, 1, reply, // message type
+        54, 4, a, b, c, d, // server
+        51, 4, l0, l1, l2, l3, // lease time
+        1, 4, 255, 255, 255, 0, // subnet mask
+        3, 4, a, b, c, d, // router
+        6, 4, a, b, c, d, // dns
+        255,
+    ];
+    out[240..240 + options.len()].copy_from_slice(&options);
+    // some clients want at least a BOOTP sized packet
+    Some(out.len())
+}
+
+/// The address handed to the phone with this MAC.
+pub fn lease(mac: &[u8]) -> [u8; 4] {
+    let [a, b, c, _] = ADDRESS;
+    [a, b, c, 10 + mac[5] % 240]
+}
+
+fn dhcp_option(mut options: &[u8], code: u8) -> Option<&[u8]> {
+    loop {
+        match options {
+            [255, ..] | [] => return None,
+            [0, rest @ ..] => options = rest,
+            [found, len, rest @ ..] => {
+                let data = rest.get(..*len as usize)?;
+                if *found == code {
+                    return Some(data);
+                }
+                options = &rest[*len as usize..];
+            }
+            [_] => return None,
+        }
+    }
+}
+
+/// Answers any DNS lookup for an IPv4 address with the Sprig's own, so every
+/// page a phone opens is the form. Other lookups get an empty answer.
+pub fn dns_reply(query: &[u8], out: &mut [u8; 512]) -> Option<usize> {
+    // a single question, and not a response
+    if query.len() < 12 || query[2] & 0x80 != 0 || query[4..6] != [0, 1] {
+        return None;
+    }
+    let mut end = 12;
+    loop {
+        let len = *query.get(end)? as usize;
+        end += 1;
+        if len == 0 {
+            break;
+        }
+        // compression isn't allowed in questions
+        if len & 0xC0 != 0 {
+            return None;
+        }
+        end += len;
+    }
+    let question = query.get(12..end + 4)?;
+    let is_a = question[question.len() - 4..] == [0, 1, 0, 1];
+    end += 4;
+
+    if end + 16 > out.len() {
+        return None;
+    }
+    out[..end].copy_from_slice(&query[..end]);
+    // response, recursion desired and available
+    out[2] = 0x81;
+    out[3] = 0x80;
+    out[6..12].copy_from_slice(&[0, is_a as u8, 0, 0, 0, 0]);
+    if !is_a {
+        return Some(end);
+    }
+
+    let [a, b, c, d] = ADDRESS;
+    let answer = [
+        0xC0, 12, // the name in the question
+        0, 1, 0, 1, // A, IN
+        0, 0, 0, 60, // ttl
+        0, 4, a, b, c, d,
+    ];
+    out[end..end + answer.len()].copy_from_slice(&answer);
+    Some(end + answer.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use core::convert::Infallible;
+
+    use embassy_futures::block_on;
+    use embedded_io_async::ErrorType;
+
+    use super::*;
+
+    /// Hands out the request a few bytes at a time, like a socket would.
+    struct Stream<'a> {
+        input: &'a [u8],
+        output: Vec<u8, 4096>,
+    }
+
+    impl<'a> Stream<'a> {
+        fn new(input: &'a str) -> Self {
+            Self {
+                input: input.as_bytes(),
+                output: Vec::new(),
+            }
+        }
+
+        fn output(&self) -> &str {
+            core::str::from_utf8(&self.output).unwrap()
+        }
+    }
+
+    impl ErrorType for Stream<'_> {
+        type Error = Infallible;
+    }
+
+    impl Read for Stream<'_> {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Infallible> {
+            let len = buf.len().min(self.input.len()).min(7);
+            buf[..len].copy_from_slice(&self.input[..len]);
+            self.input = &self.input[len..];
+            Ok(len)
+        }
+    }
+
+    impl Write for Stream<'_> {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, Infallible> {
+            self.output.extend_from_slice(buf).unwrap();
+            Ok(buf.len())
+        }
+    }
+
+    fn post(body: &str, stream: &mut Vec<u8, 1024>) {
+        let mut request: String<1024> = String::new();
+        write!(
+            request,
+            "POST /save HTTP/1.1\r\nHost: 192.168.4.1\r\ncontent-length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .unwrap();
+        stream.extend_from_slice(request.as_bytes()).unwrap();
+    }
+
+    fn serve_str(request: &str, config: &mut Config) -> (Handled, Vec<u8, 4096>) {
+        let mut stream = Stream::new(request);
+        let handled = block_on(serve(&mut stream, config)).unwrap();
+        (handled, stream.output)
+    }
+
+    fn serve_post(body: &str, config: &mut Config) -> (Handled, Vec<u8, 4096>) {
+        let mut request = Vec::new();
+        post(body, &mut request);
+        serve_str(core::str::from_utf8(&request).unwrap(), config)
+    }
+
+    #[test]
+    fn form() {
+        let mut config = Config::new();
+        let mut stream = Stream::new(
+            "GET /generate_204 HTTP/1.1\r\nHost: connectivitycheck.gstatic.com\r\n\r\n",
+        );
+        let handled = block_on(serve(&mut stream, &mut config)).unwrap();
+        assert_eq!(handled, Handled::Form);
+
+        let output = stream.output();
+        assert!(output.starts_with("HTTP/1.1 200 OK\r\n"));
+        let (head, body) = output.split_once("\r\n\r\n").unwrap();
+        let mut length: String<8> = String::new();
+        write!(length, "{}", body.len()).unwrap();
+        assert!(head.contains(length.as_str()));
+        assert!(body.contains("name=\"ssid\""));
+        assert!(body.contains("name=\"api_token\""));
+        assert_eq!(config, Config::new());
+    }
+
+    #[test]
+    fn save() {
+        let mut config = Config::new();
+        let (handled, output) = serve_post(
+            "ssid=Caf%C3%A9+Wifi&passwd=hunter22&security=wpa2&slack_id=U123&api_token=abc%2Bdef",
+            &mut config,
+        );
+        assert_eq!(handled, Handled::Saved);
+        assert!(output.starts_with(b"HTTP/1.1 200 OK\r\n"));
+        assert_eq!(config.networks.len(), 1);
+        assert_eq!(config.networks[0].ssid, "Café Wifi");
+        assert_eq!(config.networks[0].passwd, "hunter22");
+        assert_eq!(config.networks[0].security, Security::Wpa2);
+        assert_eq!(config.slack_id, "U123");
+        assert_eq!(config.api_token, "abc+def");
+
+        // a second network goes first, and empty fields keep what was there
+        let (handled, _) = serve_post(
+            "ssid=Phone&passwd=&security=open&slack_id=&api_token=",
+            &mut config,
+        );
+        assert_eq!(handled, Handled::Saved);
+        assert_eq!(config.networks[1].ssid, "Phone");
+        assert_eq!(config.networks[1].security, Security::Open);
+        assert!(config.networks[1].priority > config.networks[0].priority);
+        assert_eq!(config.slack_id, "U123");
+        assert_eq!(config.api_token, "abc+def");
+    }
+
+    #[test]
+    fn invalid() {
+        let mut config = Config::new();
+        for (body, err) in [
+            ("ssid=&passwd=hunter22", FormError::MissingSsid),
+            (
+                "ssid=Home&passwd=short&security=wpa2",
+                FormError::ShortPassword,
+            ),
+            ("ssid=Home&security=wep", FormError::Malformed),
+            ("ssid=Home&passwd=%G1", FormError::Malformed),
+            (
+                "ssid=123456789012345678901234567890123&security=open",
+                FormError::TooLong,
+            ),
+        ] {
+            let (handled, output) = serve_post(body, &mut config);
+            assert_eq!(handled, Handled::Invalid(err));
+            assert!(output.starts_with(b"HTTP/1.1 400 Bad Request\r\n"));
+            let output = core::str::from_utf8(&output).unwrap();
+            assert!(output.contains(err.message()));
+        }
+        assert_eq!(config, Config::new());
+
+        for ssid in ["A", "B", "C", "D"] {
+            let mut body: String<32> = String::new();
+            write!(body, "ssid={}&security=open", ssid).unwrap();
+            assert_eq!(serve_post(&body, &mut config).0, Handled::Saved);
+        }
+        let (handled, _) = serve_post("ssid=E&security=open", &mut config);
+        assert_eq!(handled, Handled::Invalid(FormError::NetworksFull));
+    }
+
+    #[test]
+    fn bad_request() {
+        let mut config = Config::new();
+        for request in [
+            "DELETE / HTTP/1.1\r\n\r\n",
+            "POST /save HTTP/1.1\r\n\r\nssid=Home",
+            "POST /other HTTP/1.1\r\nContent-Length: 9\r\n\r\nssid=Home",
+            // cut off before the headers end
+            "GET / HTTP/1.1\r\n",
+            // longer than it really is
+            "POST /save HTTP/1.1\r\nContent-Length: 50\r\n\r\nssid=Home",
+        ] {
+            let (handled, output) = serve_str(request, &mut config);
+            assert_eq!(handled, Handled::BadRequest);
+            assert!(output.starts_with(b"HTTP/1.1 400 Bad Request\r\n"));
+        }
+        assert_eq!(config, Config::new());
+    }
+
+    fn dhcp_request(kind: u8) -> [u8; 300] {
+        let mut request = [0; 300];
+        request[0] = 1;
+        request[4..8].copy_from_slice(&[1, 2, 3, 4]);
+        request[28..34].copy_from_slice(&[0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0x05]);
+        request[236..240].copy_from_slice(&[99, 130, 83, 99]);
+        // a pad, a hostname, then the message type
+        request[240..251].copy_from_slice(&[0, 12, 4, b's', b'p', b'r', b'g', 53, 1, kind, 255]);
+        request
+    }
+
+    #[test]
+    fn dhcp() {
+        let mut out = [0; 300];
+        for (kind, reply) in [(1, 2), (3, 5)] {
+            let len = dhcp_reply(&dhcp_request(kind), &mut out).unwrap();
+            assert_eq!(len, 300);
+            assert_eq!(out[0], 2);
+            assert_eq!(out[4..8], [1, 2, 3, 4]);
+            assert_eq!(out[16..20], [192, 168, 4, 15]);
+            assert_eq!(out[28..34], [0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0x05]);
+            assert_eq!(dhcp_option(&out[240..], 53), Some(&[reply][..]));
+            assert_eq!(dhcp_option(&out[240..], 6), Some(&ADDRESS[..]));
+        }
+
+        // releases and anything that isn't a request are ignored
+        assert!(dhcp_reply(&dhcp_request(7), &mut out).is_none());
+        let mut reply = dhcp_request(1);
+        reply[0] = 2;
+        assert!(dhcp_reply(&reply, &mut out).is_none());
+        assert!(dhcp_reply(&[1; 100], &mut out).is_none());
+    }
+
+    #[test]
+    fn dns() {
+        let mut query = [0; 64];
+        let question = b"\x12\x34\x01\x00\x00\x01\x00\x00\x00\x00\x00\x00\x07example\x03com\x00";
+        query[..question.len()].copy_from_slice(question);
+        query[question.len()..question.len() + 4].copy_from_slice(&[0, 1, 0, 1]);
+        let query = &query[..question.len() + 4];
+
+        let mut out = [0; 512];
+        let len = dns_reply(query, &mut out).unwrap();
+        assert_eq!(len, query.len() + 16);
+        assert_eq!(out[..2], [0x12, 0x34]);
+        assert_eq!(out[6..8], [0, 1]);
+        assert_eq!(out[len - 4..len], ADDRESS);
+
+        // AAAA gets no answer, so the phone falls back to IPv4
+        let mut aaaa = [0; 64];
+        aaaa[..query.len()].copy_from_slice(query);
+        aaaa[query.len() - 3] = 28;
+        let len = dns_reply(&aaaa[..query.len()], &mut out).unwrap();
+        assert_eq!(len, query.len());
+        assert_eq!(out[6..8], [0, 0]);
+
+        assert!(dns_reply(&query[..20], &mut out).is_none());
+    }
+}
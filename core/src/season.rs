@@ -0,0 +1,89 @@
+//! The event tickets are counted for, and where we are in it.
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::config::Config;
+use crate::date::days_between;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Season {
+    /// The first day.
+    pub start: NaiveDate,
+    /// The last day.
+    pub end: NaiveDate,
+    /// Tickets to earn during the season.
+    pub goal: u16,
+    /// Tickets earned before the season started.
+    pub offset: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    /// Starting in this many days.
+    Upcoming(i32),
+    Running,
+    Over,
+}
+
+impl Season {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            start: config.start_date,
+            // a season ending before it starts is over on its first day
+            end: config.end_date.max(config.start_date),
+            goal: config.ticket_goal,
+            offset: config.ticket_offset,
+        }
+    }
+
+    /// Midnight on the first day.
+    pub fn starts(&self) -> NaiveDateTime {
+        self.start.and_hms_opt(0, 0, 0).unwrap()
+    }
+
+    /// The last second of the last day.
+    pub fn ends(&self) -> NaiveDateTime {
+        self.end.and_hms_opt(23, 59, 59).unwrap()
+    }
+
+    pub fn phase(&self, now: &NaiveDateTime) -> Phase {
+        if *now < self.starts() {
+            Phase::Upcoming(days_between(now, &self.starts()))
+        } else if *now > self.ends() {
+            Phase::Over
+        } else {
+            Phase::Running
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(month: u32, day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, month, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn phases() {
+        let season = Season::from_config(&Config::new());
+        assert_eq!(season.phase(&at(6, 1, 12)), Phase::Upcoming(17));
+        assert_eq!(season.phase(&at(6, 17, 23)), Phase::Upcoming(1));
+        assert_eq!(season.phase(&at(6, 18, 0)), Phase::Running);
+        assert_eq!(season.phase(&at(10, 1, 23)), Phase::Running);
+        assert_eq!(season.phase(&at(10, 2, 0)), Phase::Over);
+    }
+
+    #[test]
+    fn backwards() {
+        let mut config = Config::new();
+        config.end_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let season = Season::from_config(&config);
+        assert_eq!(season.end, season.start);
+        assert_eq!(season.phase(&at(6, 19, 0)), Phase::Over);
+    }
+}
@@ -0,0 +1,211 @@
+//! The items on the Settings screen and how the d-pad edits them.
+
+use core::fmt::{self, Write};
+
+use chrono::{Datelike, NaiveDate};
+use heapless::String;
+
+use crate::config::Config;
+use crate::tz;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Setting {
+    TicketGoal,
+    TicketOffset,
+    UpdateInterval,
+    StartDate,
+    EndDate,
+    Timezone,
+    Backlight,
+}
+
+/// Parts of the end date that can be picked with left and right.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateField {
+    Year,
+    Month,
+    Day,
+}
+
+impl DateField {
+    pub fn left(&self) -> Self {
+        match self {
+            DateField::Year => DateField::Day,
+            DateField::Month => DateField::Year,
+            DateField::Day => DateField::Month,
+        }
+    }
+
+    pub fn right(&self) -> Self {
+        match self {
+            DateField::Year => DateField::Month,
+            DateField::Month => DateField::Day,
+            DateField::Day => DateField::Year,
+        }
+    }
+}
+
+impl Setting {
+    pub const ALL: [Setting; 7] = [
+        Setting::TicketGoal,
+        Setting::TicketOffset,
+        Setting::UpdateInterval,
+        Setting::StartDate,
+        Setting::EndDate,
+        Setting::Timezone,
+        Setting::Backlight,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Setting::TicketGoal => "Ticket goal",
+            Setting::TicketOffset => "Ticket offset",
+            Setting::UpdateInterval => "Update interval",
+            Setting::StartDate => "Start date",
+            Setting::EndDate => "End date",
+            Setting::Timezone => "Timezone",
+            Setting::Backlight => "Backlight",
+        }
+    }
+
+    pub fn value(&self, config: &Config) -> String<16> {
+        let mut value = String::new();
+        let _ = match self {
+            Setting::TicketGoal => write!(value, "{}", config.ticket_goal),
+            Setting::TicketOffset => write!(value, "{}", config.ticket_offset),
+            Setting::UpdateInterval => write!(value, "{} min", config.update_interval),
+            Setting::StartDate | Setting::EndDate => {
+                let date = match self {
+                    Setting::StartDate => config.start_date,
+                    _ => config.end_date,
+                };
+                write!(
+                    value,
+                    "{}-{:02}-{:02}",
+                    date.year(),
+                    date.month(),
+                    date.day()
+                )
+            }
+            Setting::Timezone => value
+                .push_str(&tz::label(&config.timezone))
+                .map_err(|_| fmt::Error),
+            Setting::Backlight => write!(value, "{}%", config.backlight),
+        };
+        value
+    }
+
+    /// Moves the value `steps` up (or down if negative), staying in range.
+    /// `field` is only used by the dates.
+    pub fn adjust(&self, config: &mut Config, steps: i32, field: DateField) {
+        match self {
+            Setting::TicketGoal => {
+                config.ticket_goal = (config.ticket_goal as i32 + steps).clamp(1, 999) as u16;
+            }
+            Setting::TicketOffset => {
+                config.ticket_offset = (config.ticket_offset as i32 + steps).clamp(0, 999) as u16;
+            }
+            Setting::UpdateInterval => {
+                config.update_interval = (config.update_interval as i32 + steps).clamp(1, 60) as u8;
+            }
+            Setting::StartDate => config.start_date = adjust_date(config.start_date, steps, field),
+            Setting::EndDate => config.end_date = adjust_date(config.end_date, steps, field),
+            Setting::Timezone => {
+                let zone = tz::step(&config.timezone, steps);
+                // every zone in the list fits
+                config.timezone = String::try_from(zone).unwrap();
+            }
+            Setting::Backlight => {
+                config.backlight = (config.backlight as i32 + steps * 10).clamp(10, 100) as u8;
+            }
+        }
+    }
+}
+
+/// Months and days wrap around without carrying, like a clock's setup menu.
+fn adjust_date(date: NaiveDate, steps: i32, field: DateField) -> NaiveDate {
+    let (mut year, mut month, mut day) = (date.year(), date.month() as i32, date.day() as i32);
+    match field {
+        DateField::Year => year = (year + steps).clamp(2024, 2099),
+        DateField::Month => month = (month - 1 + steps).rem_euclid(12) + 1,
+        DateField::Day => {}
+    }
+
+    let days = days_in_month(year, month as u32) as i32;
+    day = match field {
+        DateField::Day => (day - 1 + steps).rem_euclid(days) + 1,
+        _ => day.min(days),
+    };
+
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32).unwrap_or(date)
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let (next_year, next_month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    NaiveDate::from_ymd_opt(next_year, next_month, 1)
+        .and_then(|next| next.pred_opt())
+        .map_or(31, |last| last.day())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn values() {
+        let mut config = Config::new();
+        assert_eq!(Setting::TicketGoal.value(&config), "160");
+        assert_eq!(Setting::UpdateInterval.value(&config), "5 min");
+        assert_eq!(Setting::StartDate.value(&config), "2024-06-18");
+        assert_eq!(Setting::EndDate.value(&config), "2024-10-01");
+        assert_eq!(Setting::Timezone.value(&config), "UTC");
+        assert_eq!(Setting::Backlight.value(&config), "100%");
+
+        config.timezone = tz::fixed(5 * 60 + 45);
+        assert_eq!(Setting::Timezone.value(&config), "UTC+5:45");
+        config.timezone = String::try_from("EST5EDT,M3.2.0,M11.1.0").unwrap();
+        assert_eq!(Setting::Timezone.value(&config), "New York");
+    }
+
+    #[test]
+    fn clamps() {
+        let mut config = Config::new();
+        Setting::TicketOffset.adjust(&mut config, -20, DateField::Year);
+        assert_eq!(config.ticket_offset, 0);
+        Setting::UpdateInterval.adjust(&mut config, 100, DateField::Year);
+        assert_eq!(config.update_interval, 60);
+        Setting::Backlight.adjust(&mut config, 1, DateField::Year);
+        assert_eq!(config.backlight, 100);
+        Setting::Backlight.adjust(&mut config, -20, DateField::Year);
+        assert_eq!(config.backlight, 10);
+        Setting::Timezone.adjust(&mut config, -100, DateField::Year);
+        assert_eq!(Setting::Timezone.value(&config), "Honolulu");
+        Setting::Timezone.adjust(&mut config, 2, DateField::Year);
+        assert_eq!(Setting::Timezone.value(&config), "Los Angeles");
+    }
+
+    #[test]
+    fn dates() {
+        let end = date(2024, 10, 31);
+        assert_eq!(adjust_date(end, 1, DateField::Day), date(2024, 10, 1));
+        assert_eq!(adjust_date(end, -1, DateField::Month), date(2024, 9, 30));
+        assert_eq!(adjust_date(end, 3, DateField::Month), date(2024, 1, 31));
+        assert_eq!(
+            adjust_date(date(2024, 2, 29), 1, DateField::Year),
+            date(2025, 2, 28)
+        );
+        assert_eq!(
+            adjust_date(date(2024, 2, 1), -1, DateField::Day),
+            date(2024, 2, 29)
+        );
+        assert_eq!(adjust_date(end, -1, DateField::Year), end);
+    }
+}
@@ -0,0 +1,123 @@
+//! Keeping the wishlist in step with the Shop.
+
+use heapless::{String, Vec};
+
+use crate::config::{WishlistItem, MAX_WISHLIST};
+
+/// The wishlist already has `MAX_WISHLIST` items.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WishlistFull;
+
+/// A Shop name cut down to what fits on the wishlist, at a whole character.
+pub fn wishlist_name(name: &str) -> String<32> {
+    let mut short = String::new();
+    for c in name.chars() {
+        if short.push(c).is_err() {
+            break;
+        }
+    }
+    short
+}
+
+/// Whether a wishlist item is the Shop item called `name`.
+fn is_item(item: &WishlistItem, name: &str) -> bool {
+    item.name == wishlist_name(name)
+}
+
+pub fn wishlisted(wishlist: &[WishlistItem], name: &str) -> bool {
+    wishlist.iter().any(|item| is_item(item, name))
+}
+
+/// Stars or unstars an item, returning whether it is now on the wishlist.
+pub fn toggle(
+    wishlist: &mut Vec<WishlistItem, MAX_WISHLIST>,
+    name: &str,
+    tickets: u16,
+) -> Result<bool, WishlistFull> {
+    if let Some(index) = wishlist.iter().position(|item| is_item(item, name)) {
+        wishlist.remove(index);
+        return Ok(false);
+    }
+
+    let item = WishlistItem {
+        name: wishlist_name(name),
+        tickets,
+    };
+    wishlist.push(item).map_err(|_| WishlistFull)?;
+    Ok(true)
+}
+
+/// Copies new prices from the Shop, returning whether any changed.
+pub fn update_prices<'a>(
+    wishlist: &mut [WishlistItem],
+    items: impl IntoIterator<Item = (&'a str, u16)>,
+) -> bool {
+    let mut changed = false;
+    for (name, tickets) in items {
+        for item in wishlist.iter_mut().filter(|item| is_item(item, name)) {
+            changed |= item.tickets != tickets;
+            item.tickets = tickets;
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn starring() {
+        let mut wishlist = Vec::new();
+        assert_eq!(toggle(&mut wishlist, "Pinecil", 60), Ok(true));
+        assert_eq!(toggle(&mut wishlist, "Flipper Zero", 300), Ok(true));
+        assert!(wishlisted(&wishlist, "Pinecil"));
+
+        assert_eq!(toggle(&mut wishlist, "Pinecil", 60), Ok(false));
+        assert!(!wishlisted(&wishlist, "Pinecil"));
+        assert_eq!(wishlist[0].name, "Flipper Zero");
+    }
+
+    #[test]
+    fn full() {
+        let mut wishlist = Vec::new();
+        for price in 0..MAX_WISHLIST as u16 {
+            let name: String<8> = core::iter::repeat_n('a', price as usize + 1).collect();
+            toggle(&mut wishlist, &name, price).unwrap();
+        }
+        assert_eq!(toggle(&mut wishlist, "Pinecil", 60), Err(WishlistFull));
+        // unstarring still works
+        assert_eq!(toggle(&mut wishlist, "a", 0), Ok(false));
+    }
+
+    #[test]
+    fn prices() {
+        let mut wishlist = Vec::new();
+        toggle(&mut wishlist, "Pinecil", 60).unwrap();
+        assert!(!update_prices(
+            &mut wishlist,
+            [("Pinecil", 60), ("Other", 5)]
+        ));
+        assert!(update_prices(&mut wishlist, [("Pinecil", 75)]));
+        assert_eq!(wishlist[0].tickets, 75);
+    }
+
+    #[test]
+    fn long_names() {
+        let name = "Raspberry Pi 5 with the official case and power supply";
+        let mut wishlist = Vec::new();
+        assert_eq!(toggle(&mut wishlist, name, 200), Ok(true));
+        assert_eq!(wishlist[0].name, "Raspberry Pi 5 with the official");
+        assert!(wishlisted(&wishlist, name));
+
+        assert!(update_prices(&mut wishlist, [(name, 180)]));
+        assert_eq!(wishlist[0].tickets, 180);
+
+        // pressing A again unstars it rather than adding it twice
+        assert_eq!(toggle(&mut wishlist, name, 180), Ok(false));
+        assert!(wishlist.is_empty());
+
+        // cut at a whole character
+        assert_eq!(wishlist_name(&"é".repeat(20)), "é".repeat(16).as_str());
+    }
+}
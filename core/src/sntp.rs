@@ -0,0 +1,166 @@
+//! Just enough SNTP (RFC 4330) to set the clock, and the HTTP `Date` header
+//! to fall back on when UDP is blocked.
+
+use chrono::{DateTime, Utc};
+
+pub const PORT: u16 = 123;
+pub const PACKET_LEN: usize = 48;
+
+/// Used when the config doesn't list any servers.
+pub const DEFAULT_SERVERS: [&str; 3] = ["pool.ntp.org", "time.cloudflare.com", "time.google.com"];
+
+/// Seconds from the NTP epoch, 1900, to the Unix one.
+const UNIX_OFFSET: i64 = 2_208_988_800;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SntpError {
+    /// Shorter than a packet.
+    Short,
+    /// Not a reply from a server.
+    NotServer,
+    /// The server wants us to go away, or to try another one.
+    KissOfDeath,
+    /// The server doesn't know the time itself.
+    Unsynchronized,
+    /// A reply to some other request.
+    Mismatch,
+}
+
+/// A client request. The server echoes `transmit` back, which is how the
+/// reply is matched to it, so a random one is as good as the time.
+pub fn request(transmit: u64) -> [u8; PACKET_LEN] {
+    let mut packet = [0; PACKET_LEN];
+    // no leap warning, version 4, client mode
+    packet[0] = (4 << 3) | 3;
+    packet[40..48].copy_from_slice(&transmit.to_be_bytes());
+    packet
+}
+
+/// The time in a server's reply to `request(transmit)`. The round trip isn't
+/// taken off, it's well under the second the RTC counts in.
+pub fn decode(reply: &[u8], transmit: u64) -> Result<DateTime<Utc>, SntpError> {
+    if reply.len() < PACKET_LEN {
+        return Err(SntpError::Short);
+    }
+    if reply[0] & 0b111 != 4 {
+        return Err(SntpError::NotServer);
+    }
+    if reply[24..32] != transmit.to_be_bytes() {
+        return Err(SntpError::Mismatch);
+    }
+    if reply[1] == 0 {
+        return Err(SntpError::KissOfDeath);
+    }
+    let time = u64::from_be_bytes(reply[40..48].try_into().unwrap());
+    if reply[0] >> 6 == 3 || time == 0 {
+        return Err(SntpError::Unsynchronized);
+    }
+    timestamp(time).ok_or(SntpError::Unsynchronized)
+}
+
+/// An NTP timestamp, seconds and a fraction since 1900 in 32 bits each.
+fn timestamp(time: u64) -> Option<DateTime<Utc>> {
+    let secs = time >> 32;
+    // the seconds wrap in 2036, anything below 1968 is the next era
+    let era = if secs & 0x8000_0000 == 0 { 1 << 32 } else { 0 };
+    let nanos = ((time & 0xFFFF_FFFF) * 1_000_000_000) >> 32;
+    DateTime::from_timestamp(secs as i64 + era - UNIX_OFFSET, nanos as u32)
+}
+
+/// The time in an HTTP `Date` header, like `Sun, 06 Nov 1994 08:49:37 GMT`.
+pub fn http_date(value: &[u8]) -> Option<DateTime<Utc>> {
+    let value = core::str::from_utf8(value).ok()?;
+    DateTime::parse_from_rfc2822(value.trim())
+        .ok()
+        .map(|date| date.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, Timelike};
+
+    use super::*;
+
+    const TRANSMIT: u64 = 0x0123_4567_89AB_CDEF;
+
+    fn reply(secs: u32, fraction: u32) -> [u8; PACKET_LEN] {
+        let mut reply = [0; PACKET_LEN];
+        // version 4, server mode
+        reply[0] = (4 << 3) | 4;
+        reply[1] = 2;
+        reply[24..32].copy_from_slice(&TRANSMIT.to_be_bytes());
+        reply[40..44].copy_from_slice(&secs.to_be_bytes());
+        reply[44..48].copy_from_slice(&fraction.to_be_bytes());
+        reply
+    }
+
+    #[test]
+    fn encode() {
+        let packet = request(TRANSMIT);
+        assert_eq!(packet[0], 0x23);
+        assert!(packet[1..40].iter().all(|&b| b == 0));
+        assert_eq!(packet[40..], TRANSMIT.to_be_bytes());
+    }
+
+    #[test]
+    fn time() {
+        // 2024-06-01 12:00:00.5 UTC
+        let time = decode(&reply(3_926_232_000, 1 << 31), TRANSMIT).unwrap();
+        let expected = NaiveDate::from_ymd_opt(2024, 6, 1)
+            .unwrap()
+            .and_hms_milli_opt(12, 0, 0, 500)
+            .unwrap()
+            .and_utc();
+        assert_eq!(time, expected);
+
+        // after the seconds wrap in 2036
+        let wrapped = decode(&reply(1_000, 0), TRANSMIT).unwrap();
+        assert_eq!(
+            wrapped,
+            NaiveDate::from_ymd_opt(2036, 2, 7)
+                .unwrap()
+                .and_hms_opt(6, 44, 56)
+                .unwrap()
+                .and_utc()
+        );
+        assert_eq!(wrapped.nanosecond(), 0);
+    }
+
+    #[test]
+    fn rejected() {
+        let good = reply(3_926_232_000, 0);
+        assert_eq!(decode(&good[..40], TRANSMIT), Err(SntpError::Short));
+        assert_eq!(decode(&good, TRANSMIT + 1), Err(SntpError::Mismatch));
+
+        let mut client = good;
+        client[0] = 0x23;
+        assert_eq!(decode(&client, TRANSMIT), Err(SntpError::NotServer));
+
+        let mut kiss = good;
+        kiss[1] = 0;
+        assert_eq!(decode(&kiss, TRANSMIT), Err(SntpError::KissOfDeath));
+
+        let mut unsynced = good;
+        unsynced[0] |= 0b1100_0000;
+        assert_eq!(decode(&unsynced, TRANSMIT), Err(SntpError::Unsynchronized));
+        assert_eq!(
+            decode(&reply(0, 0), TRANSMIT),
+            Err(SntpError::Unsynchronized)
+        );
+    }
+
+    #[test]
+    fn date_header() {
+        let date = http_date(b"Sat, 01 Jun 2024 12:00:00 GMT").unwrap();
+        assert_eq!(
+            date,
+            NaiveDate::from_ymd_opt(2024, 6, 1)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap()
+                .and_utc()
+        );
+        assert_eq!(http_date(b"yesterday"), None);
+        assert_eq!(http_date(&[0xFF, 0xFE]), None);
+    }
+}
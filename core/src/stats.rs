@@ -0,0 +1,136 @@
+//! Progress and pacing formulas shown on the Home screen, for the time
+//! between the start and end of a `Season`.
+
+use chrono::NaiveDateTime;
+
+use crate::date::days_between;
+use crate::season::Season;
+
+/// Tickets earned during the season.
+pub fn earned(tickets: u16, season: &Season) -> u16 {
+    tickets.saturating_sub(season.offset)
+}
+
+/// Tickets still needed to reach the goal.
+pub fn tickets_left(tickets: u16, season: &Season) -> u16 {
+    season.goal.saturating_sub(earned(tickets, season))
+}
+
+/// Fraction of the goal that has been earned, can go over 1.
+pub fn percent_done(tickets: u16, season: &Season) -> f32 {
+    earned(tickets, season) as f32 / season.goal as f32
+}
+
+/// Fraction of the goal that should have been earned by now to finish on time.
+pub fn ideal_percent(season: &Season, now: &NaiveDateTime) -> f32 {
+    let length = days_between(&season.ends(), &season.starts());
+    (days_between(now, &season.starts()) as f32 + 1.0) / (length - 1).max(1) as f32
+}
+
+/// Average tickets (hours) per day so far, counting today.
+pub fn average_per_day(tickets: u16, season: &Season, now: &NaiveDateTime) -> f32 {
+    earned(tickets, season) as f32 / (days_between(now, &season.starts()) as f32 + 1.)
+}
+
+/// Tickets per day needed over the whole season to reach the goal.
+pub fn ideal_per_day(season: &Season) -> f32 {
+    season.goal as f32 / days_between(&season.ends(), &season.starts()).max(1) as f32
+}
+
+/// Full days left before the season ends, not counting today.
+pub fn days_left(season: &Season, now: &NaiveDateTime) -> i32 {
+    days_between(&season.ends(), now) - 1
+}
+
+/// Tickets per day needed from now on to still reach the goal.
+pub fn needed_per_day(tickets: u16, season: &Season, now: &NaiveDateTime) -> f32 {
+    tickets_left(tickets, season) as f32 / days_between(&season.ends(), now).max(1) as f32
+}
+
+/// Whole days until `tickets` reaches `price` at `per_day`, or `None` if it
+/// never will.
+pub fn days_until(price: u16, tickets: u16, per_day: f32) -> Option<u32> {
+    if tickets >= price {
+        return Some(0);
+    }
+    if per_day <= 0. {
+        return None;
+    }
+
+    let days = (price - tickets) as f32 / per_day;
+    let whole = days as u32;
+    Some(if (whole as f32) < days {
+        whole + 1
+    } else {
+        whole
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    fn at(month: u32, day: u32) -> NaiveDateTime {
+        date(month, day).and_hms_opt(12, 0, 0).unwrap()
+    }
+
+    const SEASON: Season = Season {
+        start: NaiveDate::from_ymd_opt(2024, 6, 18).unwrap(),
+        end: NaiveDate::from_ymd_opt(2024, 10, 1).unwrap(),
+        goal: 160,
+        offset: 14,
+    };
+
+    #[test]
+    fn tickets() {
+        assert_eq!(earned(100, &SEASON), 86);
+        assert_eq!(earned(10, &SEASON), 0);
+        assert_eq!(tickets_left(100, &SEASON), 74);
+        assert_eq!(tickets_left(200, &SEASON), 0);
+        assert_eq!(percent_done(94, &SEASON), 0.5);
+        assert_eq!(percent_done(334, &SEASON), 2.0);
+    }
+
+    #[test]
+    fn ideal() {
+        assert_eq!(ideal_percent(&SEASON, &at(6, 18)), 1. / 104.);
+        assert_eq!(ideal_percent(&SEASON, &at(9, 29)), 1.);
+        assert_eq!(ideal_per_day(&SEASON), 160. / 105.);
+    }
+
+    #[test]
+    fn pacing() {
+        let now = at(8, 1);
+        assert_eq!(average_per_day(100, &SEASON, &now), 86. / 45.);
+        assert_eq!(days_left(&SEASON, &now), 60);
+        assert_eq!(needed_per_day(100, &SEASON, &now), 74. / 61.);
+        assert_eq!(needed_per_day(200, &SEASON, &now), 0.);
+    }
+
+    #[test]
+    fn short_seasons() {
+        let day = Season {
+            start: date(6, 18),
+            end: date(6, 18),
+            ..SEASON
+        };
+        assert!(ideal_percent(&day, &at(6, 18)).is_finite());
+        assert!(ideal_per_day(&day).is_finite());
+        assert!(needed_per_day(0, &day, &at(6, 18)).is_finite());
+    }
+
+    #[test]
+    fn affordable() {
+        assert_eq!(days_until(100, 120, 2.), Some(0));
+        assert_eq!(days_until(100, 100, 0.), Some(0));
+        assert_eq!(days_until(100, 90, 2.), Some(5));
+        assert_eq!(days_until(100, 90, 3.), Some(4));
+        assert_eq!(days_until(100, 90, 0.), None);
+    }
+}
@@ -0,0 +1,135 @@
+//! Helpers for data kept in flash.
+
+use embedded_storage::nor_flash::{
+    check_erase, check_read, check_write, ErrorType, NorFlash, NorFlashError, NorFlashErrorKind,
+    ReadNorFlash,
+};
+
+/// CRC-32 (IEEE), used to check that data read back from flash is intact.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Flash backed by RAM, for the simulator and host tests.
+///
+/// It behaves like NOR flash: it starts out erased, and writes can only clear
+/// bits, so data written without erasing first comes back garbled.
+pub struct MemFlash<const SIZE: usize, const ERASE_SIZE: usize = 4096> {
+    pub data: [u8; SIZE],
+}
+
+impl<const SIZE: usize, const ERASE_SIZE: usize> MemFlash<SIZE, ERASE_SIZE> {
+    pub const fn new() -> Self {
+        Self { data: [0xFF; SIZE] }
+    }
+}
+
+impl<const SIZE: usize, const ERASE_SIZE: usize> Default for MemFlash<SIZE, ERASE_SIZE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MemFlashError(pub NorFlashErrorKind);
+
+impl NorFlashError for MemFlashError {
+    fn kind(&self) -> NorFlashErrorKind {
+        self.0
+    }
+}
+
+impl<const SIZE: usize, const ERASE_SIZE: usize> ErrorType for MemFlash<SIZE, ERASE_SIZE> {
+    type Error = MemFlashError;
+}
+
+impl<const SIZE: usize, const ERASE_SIZE: usize> ReadNorFlash for MemFlash<SIZE, ERASE_SIZE> {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        check_read(self, offset, bytes.len()).map_err(MemFlashError)?;
+        let offset = offset as usize;
+        bytes.copy_from_slice(&self.data[offset..offset + bytes.len()]);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        SIZE
+    }
+}
+
+impl<const SIZE: usize, const ERASE_SIZE: usize> NorFlash for MemFlash<SIZE, ERASE_SIZE> {
+    const WRITE_SIZE: usize = 1;
+    const ERASE_SIZE: usize = ERASE_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        check_erase(self, from, to).map_err(MemFlashError)?;
+        self.data[from as usize..to as usize].fill(0xFF);
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        check_write(self, offset, bytes.len()).map_err(MemFlashError)?;
+        let offset = offset as usize;
+        for (cell, byte) in self.data[offset..offset + bytes.len()]
+            .iter_mut()
+            .zip(bytes)
+        {
+            *cell &= byte;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn mem_flash_behaves_like_nor() {
+        let mut flash = MemFlash::<8192>::new();
+        let mut buf = [0; 4];
+
+        flash.write(10, &[0x0F, 0xF0, 0x00, 0xFF]).unwrap();
+        flash.read(10, &mut buf).unwrap();
+        assert_eq!(buf, [0x0F, 0xF0, 0x00, 0xFF]);
+
+        flash.write(10, &[0xF0; 4]).unwrap();
+        flash.read(10, &mut buf).unwrap();
+        assert_eq!(buf, [0x00, 0xF0, 0x00, 0xF0]);
+
+        flash.erase(0, 4096).unwrap();
+        flash.read(10, &mut buf).unwrap();
+        assert_eq!(buf, [0xFF; 4]);
+    }
+
+    #[test]
+    fn mem_flash_bounds() {
+        let mut flash = MemFlash::<8192>::new();
+        assert_eq!(
+            flash.erase(0, 100),
+            Err(MemFlashError(NorFlashErrorKind::NotAligned))
+        );
+        assert_eq!(
+            flash.write(8190, &[0; 4]),
+            Err(MemFlashError(NorFlashErrorKind::OutOfBounds))
+        );
+    }
+}
@@ -0,0 +1,150 @@
+//! Days in a row with at least one ticket earned.
+
+use chrono::{Days as DayCount, NaiveDate};
+
+use crate::history::{Days, DAYS};
+use crate::season::Season;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Streak {
+    /// Up to today, or to yesterday while today has nothing yet.
+    pub current: u16,
+    pub longest: u16,
+}
+
+/// The streaks in `season`, from the days in `days`. Days outside the season
+/// don't count, so a streak starts again with each one.
+pub fn streak(days: &Days, season: &Season, today: NaiveDate) -> Streak {
+    // nothing logged yet leaves `last` at `NaiveDate::MIN`
+    let oldest = days.last.checked_sub_days(DayCount::new(DAYS as u64 - 1));
+    let first = oldest.map_or(season.start, |oldest| season.start.max(oldest));
+    let last = today.min(season.end);
+
+    let mut streak = Streak::default();
+    let mut run = 0;
+    for day in first.iter_days().take_while(|day| *day <= last) {
+        if days.earned(day).is_some_and(|earned| earned > 0) {
+            run += 1;
+            streak.current = run;
+            streak.longest = streak.longest.max(run);
+        } else {
+            run = 0;
+            // today can still join the streak
+            if day < today {
+                streak.current = 0;
+            }
+        }
+    }
+    streak
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDateTime, NaiveTime};
+
+    use super::*;
+    use crate::config::Config;
+    use crate::history::{History, Sample};
+    use crate::storage::MemFlash;
+    use crate::tz::TimeZone;
+
+    fn day(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    /// `earned` on each day up to `last`.
+    fn history(last: NaiveDate, earned: &[u16]) -> Days {
+        let mut days = Days::new();
+        days.last = last;
+        let mut tickets = 0;
+        let start = DAYS - earned.len() - 1;
+        days.counts[start] = Some(0);
+        for (count, earned) in days.counts[start + 1..].iter_mut().zip(earned) {
+            tickets += earned;
+            *count = Some(tickets);
+        }
+        days
+    }
+
+    fn season() -> Season {
+        Season::from_config(&Config::new())
+    }
+
+    #[test]
+    fn current_and_longest() {
+        let days = history(day(7, 10), &[1, 2, 1, 0, 3, 1, 0, 2, 2]);
+        assert_eq!(
+            streak(&days, &season(), day(7, 10)),
+            Streak {
+                current: 2,
+                longest: 3
+            }
+        );
+    }
+
+    #[test]
+    fn today_can_still_count() {
+        let days = history(day(7, 10), &[1, 1, 1, 0]);
+        assert_eq!(streak(&days, &season(), day(7, 10)).current, 3);
+        // but not once it's over
+        assert_eq!(streak(&days, &season(), day(7, 11)).current, 0);
+    }
+
+    #[test]
+    fn season_boundaries() {
+        // the last two days before the season don't count
+        let days = history(day(6, 20), &[2, 1, 1, 1, 1]);
+        assert_eq!(
+            streak(&days, &season(), day(6, 20)),
+            Streak {
+                current: 3,
+                longest: 3
+            }
+        );
+        assert_eq!(streak(&days, &season(), day(6, 17)), Streak::default());
+
+        // after it ends the streak stops where the season did
+        let days = history(day(10, 3), &[1, 1, 1, 1]);
+        assert_eq!(
+            streak(&days, &season(), day(10, 3)),
+            Streak {
+                current: 2,
+                longest: 2
+            }
+        );
+    }
+
+    #[test]
+    fn no_history() {
+        assert_eq!(
+            streak(&Days::new(), &season(), day(7, 10)),
+            Streak::default()
+        );
+    }
+
+    #[test]
+    fn local_days() {
+        let mut history = History::open(MemFlash::<8192>::new(), 0, 2).unwrap();
+        // 01:00 UTC is the evening before in New York
+        let times = [(7, 1, 12), (7, 2, 12), (7, 4, 1), (7, 4, 15)];
+        for (tickets, (month, date, hour)) in times.into_iter().enumerate() {
+            let time = NaiveDateTime::new(
+                day(month, date),
+                NaiveTime::from_hms_opt(hour, 0, 0).unwrap(),
+            );
+            history
+                .record(Sample {
+                    time,
+                    tickets: tickets as u16 + 1,
+                })
+                .unwrap();
+        }
+
+        let utc = history.recent(day(7, 4), &TimeZone::UTC).unwrap();
+        assert_eq!(streak(&utc, &season(), day(7, 4)).current, 1);
+
+        let new_york = TimeZone::parse("EST5EDT,M3.2.0,M11.1.0").unwrap();
+        let local = history.recent(day(7, 4), &new_york).unwrap();
+        assert_eq!(streak(&local, &season(), day(7, 4)).current, 3);
+    }
+}
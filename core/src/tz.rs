@@ -0,0 +1,478 @@
+//! Timezones as POSIX TZ strings, like `EST5EDT,M3.2.0,M11.1.0`, so DST
+//! rules fit in the config. The RTC keeps UTC and this turns it into local
+//! time.
+
+use core::fmt::Write;
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime, TimeDelta};
+use heapless::String;
+
+/// Longest TZ string that can be saved.
+pub const TZ_LEN: usize = 48;
+
+/// The zones that can be picked in Settings, west to east.
+pub const ZONES: [(&str, &str); 23] = [
+    ("Honolulu", "HST10"),
+    ("Anchorage", "AKST9AKDT,M3.2.0,M11.1.0"),
+    ("Los Angeles", "PST8PDT,M3.2.0,M11.1.0"),
+    ("Phoenix", "MST7"),
+    ("Denver", "MST7MDT,M3.2.0,M11.1.0"),
+    ("Chicago", "CST6CDT,M3.2.0,M11.1.0"),
+    ("New York", "EST5EDT,M3.2.0,M11.1.0"),
+    ("Halifax", "AST4ADT,M3.2.0,M11.1.0"),
+    ("Sao Paulo", "<-03>3"),
+    ("UTC", "UTC0"),
+    ("London", "GMT0BST,M3.5.0/1,M10.5.0"),
+    ("Berlin", "CET-1CEST,M3.5.0,M10.5.0/3"),
+    ("Athens", "EET-2EEST,M3.5.0/3,M10.5.0/4"),
+    ("Nairobi", "EAT-3"),
+    ("Dubai", "<+04>-4"),
+    ("Karachi", "PKT-5"),
+    ("India", "IST-5:30"),
+    ("Dhaka", "<+06>-6"),
+    ("Bangkok", "<+07>-7"),
+    ("Singapore", "<+08>-8"),
+    ("Tokyo", "JST-9"),
+    ("Sydney", "AEST-10AEDT,M10.1.0,M4.1.0/3"),
+    ("Auckland", "NZST-12NZDT,M9.5.0,M4.1.0/3"),
+];
+
+/// When DST starts or ends, in the local time before the change.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Transition {
+    day: Day,
+    /// Seconds after midnight, can be negative or past a day.
+    time: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Day {
+    /// `Jn`, 1 to 365 without ever counting February 29th.
+    Julian(u16),
+    /// `n`, 0 to 365 counting February 29th.
+    Ordinal(u16),
+    /// `Mm.w.d`, the `week`th `weekday` of `month`, with 5 being the last.
+    Month { month: u8, week: u8, weekday: u8 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Dst {
+    /// Seconds east of UTC.
+    offset: i32,
+    start: Transition,
+    end: Transition,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeZone {
+    /// Seconds east of UTC outside of DST.
+    offset: i32,
+    dst: Option<Dst>,
+}
+
+impl TimeZone {
+    pub const UTC: TimeZone = TimeZone {
+        offset: 0,
+        dst: None,
+    };
+
+    /// Parses a POSIX TZ string, an empty one being UTC.
+    pub fn parse(tz: &str) -> Option<Self> {
+        if tz.is_empty() {
+            return Some(Self::UTC);
+        }
+        let mut p = Parser(tz.as_bytes());
+        p.name()?;
+        let offset = -p.offset()?;
+        if p.0.is_empty() {
+            return Some(Self { offset, dst: None });
+        }
+
+        p.name()?;
+        let dst_offset = match p.0.first() {
+            Some(b',') | None => offset + 3600,
+            _ => -p.offset()?,
+        };
+        // without rules, the US ones are the usual default
+        let (start, end) = match p.0.is_empty() {
+            true => (
+                Transition {
+                    day: Day::Month {
+                        month: 3,
+                        week: 2,
+                        weekday: 0,
+                    },
+                    time: 7200,
+                },
+                Transition {
+                    day: Day::Month {
+                        month: 11,
+                        week: 1,
+                        weekday: 0,
+                    },
+                    time: 7200,
+                },
+            ),
+            false => {
+                p.expect(b',')?;
+                let start = p.transition()?;
+                p.expect(b',')?;
+                let end = p.transition()?;
+                (start, end)
+            }
+        };
+        if !p.0.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            offset,
+            dst: Some(Dst {
+                offset: dst_offset,
+                start,
+                end,
+            }),
+        })
+    }
+
+    /// Seconds east of UTC at `utc`.
+    pub fn offset_at(&self, utc: NaiveDateTime) -> i32 {
+        let Some(dst) = self.dst else {
+            return self.offset;
+        };
+        let year = (utc + TimeDelta::seconds(self.offset as i64)).year();
+        let (Some(start), Some(end)) = (
+            dst.start.utc(year, self.offset),
+            dst.end.utc(year, dst.offset),
+        ) else {
+            return self.offset;
+        };
+
+        let in_dst = match start < end {
+            true => start <= utc && utc < end,
+            // the southern hemisphere, where DST goes over new year
+            false => utc >= start || utc < end,
+        };
+        match in_dst {
+            true => dst.offset,
+            false => self.offset,
+        }
+    }
+
+    pub fn to_local(&self, utc: NaiveDateTime) -> NaiveDateTime {
+        utc + TimeDelta::seconds(self.offset_at(utc) as i64)
+    }
+}
+
+impl Transition {
+    /// When it happens in `year`, for clocks `offset` seconds east of UTC.
+    fn utc(&self, year: i32, offset: i32) -> Option<NaiveDateTime> {
+        let date = match self.day {
+            Day::Julian(day) => {
+                let leap = NaiveDate::from_ymd_opt(year, 2, 29).is_some();
+                let day = day as u32 + (leap && day >= 60) as u32;
+                NaiveDate::from_yo_opt(year, day)?
+            }
+            Day::Ordinal(day) => NaiveDate::from_yo_opt(year, day as u32 + 1)?,
+            Day::Month {
+                month,
+                week,
+                weekday,
+            } => {
+                let first = NaiveDate::from_ymd_opt(year, month as u32, 1)?;
+                let since = (weekday as u32 + 7 - first.weekday().num_days_from_sunday()) % 7;
+                let mut date = first + TimeDelta::days((since + (week as u32 - 1) * 7) as i64);
+                while date.month() != month as u32 {
+                    date -= TimeDelta::days(7);
+                }
+                date
+            }
+        };
+        let local = date.and_hms_opt(0, 0, 0)? + TimeDelta::seconds(self.time as i64);
+        Some(local - TimeDelta::seconds(offset as i64))
+    }
+}
+
+struct Parser<'a>(&'a [u8]);
+
+impl Parser<'_> {
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        let (&first, rest) = self.0.split_first()?;
+        (first == byte).then(|| self.0 = rest)
+    }
+
+    /// An abbreviation, either letters or quoted like `<+0530>`.
+    fn name(&mut self) -> Option<()> {
+        let len = match self.0.first()? {
+            b'<' => self.0.iter().position(|&b| b == b'>')? + 1,
+            _ => {
+                let len = self
+                    .0
+                    .iter()
+                    .take_while(|b| b.is_ascii_alphabetic())
+                    .count();
+                if len < 3 {
+                    return None;
+                }
+                len
+            }
+        };
+        self.0 = &self.0[len..];
+        Some(())
+    }
+
+    fn number(&mut self, max: i32) -> Option<i32> {
+        let len = self.0.iter().take_while(|b| b.is_ascii_digit()).count();
+        if len == 0 || len > 3 {
+            return None;
+        }
+        let n = self.0[..len]
+            .iter()
+            .fold(0, |n, &b| n * 10 + (b - b'0') as i32);
+        self.0 = &self.0[len..];
+        (n <= max).then_some(n)
+    }
+
+    /// `[+-]hh[:mm[:ss]]` in seconds, up to `max_hours`.
+    fn time(&mut self, max_hours: i32) -> Option<i32> {
+        let sign = match self.0.first() {
+            Some(b'-') => -1,
+            Some(b'+') => 1,
+            _ => 0,
+        };
+        if sign != 0 {
+            self.0 = &self.0[1..];
+        }
+        let mut secs = self.number(max_hours)? * 3600;
+        for scale in [60, 1] {
+            if self.expect(b':').is_none() {
+                break;
+            }
+            secs += self.number(59)? * scale;
+        }
+        Some(if sign < 0 { -secs } else { secs })
+    }
+
+    /// POSIX offsets are west of UTC, the opposite of everything else.
+    fn offset(&mut self) -> Option<i32> {
+        self.time(24)
+    }
+
+    fn transition(&mut self) -> Option<Transition> {
+        let day = match self.0.first()? {
+            b'J' => {
+                self.0 = &self.0[1..];
+                let day = self.number(365)?;
+                (day >= 1).then_some(Day::Julian(day as u16))?
+            }
+            b'M' => {
+                self.0 = &self.0[1..];
+                let month = self.number(12)?;
+                self.expect(b'.')?;
+                let week = self.number(5)?;
+                self.expect(b'.')?;
+                let weekday = self.number(6)?;
+                if month < 1 || week < 1 {
+                    return None;
+                }
+                Day::Month {
+                    month: month as u8,
+                    week: week as u8,
+                    weekday: weekday as u8,
+                }
+            }
+            _ => Day::Ordinal(self.number(365)? as u16),
+        };
+        let time = match self.expect(b'/') {
+            Some(()) => self.time(167)?,
+            None => 7200,
+        };
+        Some(Transition { day, time })
+    }
+}
+
+/// A zone always `minutes` east of UTC, for configs from before DST rules.
+pub fn fixed(minutes: i16) -> String<TZ_LEN> {
+    let mut tz = String::new();
+    let sign = if minutes < 0 { '-' } else { '+' };
+    let (hours, mins) = (minutes.unsigned_abs() / 60, minutes.unsigned_abs() % 60);
+    // POSIX flips the sign
+    let posix = if minutes < 0 { "" } else { "-" };
+    let _ = write!(
+        tz,
+        "<{}{:02}{:02}>{}{}:{:02}",
+        sign, hours, mins, posix, hours, mins
+    );
+    tz
+}
+
+/// The zone's name from `ZONES`, or its UTC offset when it isn't one.
+pub fn label(tz: &str) -> String<16> {
+    let mut label = String::new();
+    match (
+        ZONES.iter().find(|(_, zone)| *zone == tz),
+        TimeZone::parse(tz),
+    ) {
+        (Some((name, _)), _) => {
+            let _ = label.push_str(name);
+        }
+        (None, Some(zone)) if zone.offset != 0 || zone.dst.is_some() => {
+            let offset = zone.offset / 60;
+            let sign = if offset < 0 { '-' } else { '+' };
+            let offset = offset.unsigned_abs();
+            let _ = write!(label, "UTC{}{}:{:02}", sign, offset / 60, offset % 60);
+        }
+        (None, Some(_)) => {
+            let _ = label.push_str("UTC");
+        }
+        (None, None) => {
+            let _ = label.push_str("Invalid");
+        }
+    }
+    label
+}
+
+/// The zone `steps` further east in `ZONES` (or west if negative). One that
+/// isn't in the list starts from the first with the same or a later offset.
+pub fn step(tz: &str, steps: i32) -> &'static str {
+    let tz = if tz.is_empty() { "UTC0" } else { tz };
+    let index = match ZONES.iter().position(|(_, zone)| *zone == tz) {
+        Some(index) => index as i32,
+        None => {
+            let offset = TimeZone::parse(tz).unwrap_or(TimeZone::UTC).offset;
+            let next = ZONES
+                .iter()
+                .position(|(_, zone)| TimeZone::parse(zone).unwrap().offset >= offset)
+                .unwrap_or(ZONES.len());
+            // the first step lands on the next zone over
+            match steps > 0 {
+                true => next as i32 - 1,
+                false => next as i32,
+            }
+        }
+    };
+    ZONES[(index + steps).clamp(0, ZONES.len() as i32 - 1) as usize].1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(hour, min, 0)
+            .unwrap()
+    }
+
+    fn zone(name: &str) -> TimeZone {
+        let (_, tz) = ZONES.iter().find(|(zone, _)| *zone == name).unwrap();
+        TimeZone::parse(tz).unwrap()
+    }
+
+    #[test]
+    fn table_parses() {
+        for (name, tz) in ZONES {
+            assert!(TimeZone::parse(tz).is_some(), "{}", name);
+            assert_eq!(label(tz), name);
+        }
+        // west to east
+        assert!(ZONES
+            .windows(2)
+            .all(|pair| zone(pair[0].0).offset <= zone(pair[1].0).offset));
+    }
+
+    #[test]
+    fn new_york() {
+        let ny = zone("New York");
+        assert_eq!(ny.offset_at(utc(2024, 1, 15, 12, 0)), -5 * 3600);
+        assert_eq!(ny.offset_at(utc(2024, 7, 4, 12, 0)), -4 * 3600);
+
+        // 2am EST on March 10th 2024 is 7am UTC
+        assert_eq!(ny.offset_at(utc(2024, 3, 10, 6, 59)), -5 * 3600);
+        assert_eq!(ny.offset_at(utc(2024, 3, 10, 7, 0)), -4 * 3600);
+        // 2am EDT on November 3rd is 6am UTC
+        assert_eq!(ny.offset_at(utc(2024, 11, 3, 5, 59)), -4 * 3600);
+        assert_eq!(ny.offset_at(utc(2024, 11, 3, 6, 0)), -5 * 3600);
+
+        assert_eq!(
+            ny.to_local(utc(2024, 11, 3, 6, 30)),
+            utc(2024, 11, 3, 1, 30)
+        );
+    }
+
+    #[test]
+    fn last_sunday() {
+        // March 2024 has five Sundays, the last is the 31st
+        let london = zone("London");
+        assert_eq!(london.offset_at(utc(2024, 3, 31, 0, 59)), 0);
+        assert_eq!(london.offset_at(utc(2024, 3, 31, 1, 0)), 3600);
+        // and October has four, the last is the 27th
+        assert_eq!(london.offset_at(utc(2024, 10, 27, 0, 59)), 3600);
+        assert_eq!(london.offset_at(utc(2024, 10, 27, 1, 0)), 0);
+    }
+
+    #[test]
+    fn southern_hemisphere() {
+        let sydney = zone("Sydney");
+        assert_eq!(sydney.offset_at(utc(2024, 1, 1, 0, 0)), 11 * 3600);
+        assert_eq!(sydney.offset_at(utc(2024, 7, 1, 0, 0)), 10 * 3600);
+        assert_eq!(sydney.offset_at(utc(2024, 12, 31, 23, 0)), 11 * 3600);
+        // 2am AEST on October 6th 2024 is 4pm UTC the day before
+        assert_eq!(sydney.offset_at(utc(2024, 10, 5, 15, 59)), 10 * 3600);
+        assert_eq!(sydney.offset_at(utc(2024, 10, 5, 16, 0)), 11 * 3600);
+    }
+
+    #[test]
+    fn parsing() {
+        let india = TimeZone::parse("IST-5:30").unwrap();
+        assert_eq!(india.offset_at(utc(2024, 6, 1, 0, 0)), 5 * 3600 + 1800);
+        assert_eq!(TimeZone::parse(""), Some(TimeZone::UTC));
+
+        // DST an hour ahead and the US rules when they're left out
+        let us = TimeZone::parse("CST6CDT").unwrap();
+        assert_eq!(us, zone("Chicago"));
+
+        let julian = TimeZone::parse("<+01>-1<+02>,J60/0,300").unwrap();
+        // J60 is March 1st even in a leap year
+        assert_eq!(julian.offset_at(utc(2024, 2, 29, 23, 0)), 7200);
+        assert_eq!(julian.offset_at(utc(2024, 2, 29, 22, 59)), 3600);
+
+        for bad in [
+            "EST",
+            "E5",
+            "EST5EDT,M3.2.0",
+            "EST5EDT,M13.1.0,M11.1.0",
+            "EST5x",
+            "<+01",
+        ] {
+            assert_eq!(TimeZone::parse(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn fixed_offsets() {
+        let tz = fixed(-4 * 60);
+        assert_eq!(tz, "<-0400>4:00");
+        assert_eq!(TimeZone::parse(&tz).unwrap().offset, -4 * 3600);
+        assert_eq!(label(&tz), "UTC-4:00");
+
+        let tz = fixed(5 * 60 + 45);
+        assert_eq!(TimeZone::parse(&tz).unwrap().offset, 5 * 3600 + 45 * 60);
+        assert_eq!(label(&tz), "UTC+5:45");
+        assert_eq!(label("nonsense"), "Invalid");
+    }
+
+    #[test]
+    fn stepping() {
+        assert_eq!(step("UTC0", 1), "GMT0BST,M3.5.0/1,M10.5.0");
+        assert_eq!(step("UTC0", -3), "EST5EDT,M3.2.0,M11.1.0");
+        assert_eq!(step("HST10", -1), "HST10");
+        assert_eq!(step("NZST-12NZDT,M9.5.0,M4.1.0/3", 5), ZONES[22].1);
+
+        // Nepal isn't in the list, so it steps to India or Dhaka
+        let nepal = fixed(5 * 60 + 45);
+        assert_eq!(step(&nepal, 1), "<+06>-6");
+        assert_eq!(step(&nepal, -1), "IST-5:30");
+    }
+}
@@ -1,6 +1,10 @@
 MEMORY {
     BOOT2 : ORIGIN = 0x10000000, LENGTH = 0x100
-    FLASH : ORIGIN = 0x10000100, LENGTH = 2048K - 0x100
+    FLASH : ORIGIN = 0x10000100, LENGTH = 2048K - 0x100 - 68K
+    /* The ticket history, see `HISTORY_OFFSET` in main.rs */
+    HISTORY : ORIGIN = 0x101EF000, LENGTH = 64K
+    /* Last sector holds the config, see `CONFIG_OFFSET` in main.rs */
+    CONFIG : ORIGIN = 0x101FF000, LENGTH = 4K
 
     /* Pick one of the two options for RAM layout     */
 
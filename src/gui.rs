@@ -1,18 +1,24 @@
-use embassy_executor::Spawner;
-use embassy_rp::rtc::DateTime;
+use core::fmt::Debug;
+
+use chrono::NaiveDateTime;
+use embassy_time::{Duration, Timer};
 use embedded_graphics::{
-    geometry::Size,
+    draw_target::DrawTarget,
+    geometry::{Point, Size},
     image::ImageRaw,
     mono_font::{mapping::StrGlyphMapping, DecorationDimensions, MonoFont, MonoTextStyle},
     pixelcolor::{Rgb565, RgbColor},
-    primitives::{PrimitiveStyle, PrimitiveStyleBuilder},
+    primitives::{Primitive, PrimitiveStyle, PrimitiveStyleBuilder},
     text::{Alignment, Baseline, TextStyle, TextStyleBuilder},
+    Drawable,
 };
 
-use crate::{
-    wifi::{RequestData, RequestType, REQUEST_TYPE},
-    Button, Display,
-};
+use heapless::Vec;
+use log::error;
+use sprig_arcade_core::api::{RequestData, RequestType};
+
+use crate::util::{Events, REQUEST_TYPE, RUN};
+use crate::{draw_rect, draw_rounded_rect, Button, EVENTS};
 
 const PICO_FONT: MonoFont = MonoFont {
     image: ImageRaw::new(include_bytes!("../assets/font.raw"), 128),
@@ -55,6 +61,8 @@ pub const BLACK_NUMBER_CHAR: MonoTextStyle<Rgb565> =
     MonoTextStyle::new(&NUMBER_FONT, Rgb565::BLACK);
 
 pub const BLACK_CHAR: MonoTextStyle<Rgb565> = MonoTextStyle::new(&PICO_FONT, Rgb565::BLACK);
+pub const GREEN_CHAR: MonoTextStyle<Rgb565> = MonoTextStyle::new(&PICO_FONT, Rgb565::new(2, 40, 8));
+pub const RED_CHAR: MonoTextStyle<Rgb565> = MonoTextStyle::new(&PICO_FONT, Rgb565::new(27, 8, 4));
 pub const CENTERED_TEXT: TextStyle = TextStyleBuilder::new()
     .baseline(Baseline::Alphabetic)
     .alignment(Alignment::Center)
@@ -80,143 +88,554 @@ pub const BLACK_FILL: PrimitiveStyle<Rgb565> = PrimitiveStyleBuilder::new()
     .fill_color(Rgb565::BLACK)
     .build();
 
-fn days_since_epoch(datetime: &DateTime) -> i32 {
-    let is_leap_year =
-        datetime.year % 4 == 0 && (datetime.year % 100 != 0 || datetime.year % 400 == 0);
-    let mut days = (datetime.year as i32 - 1) * 365;
-    days += (datetime.year as i32 - 1) / 4 - (datetime.year as i32 - 1) / 100
-        + (datetime.year as i32 - 1) / 400;
-    let mut month_days = [0, 31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
-    if is_leap_year {
-        month_days[2] = 29;
-    }
-    for i in 1..datetime.month {
-        days += month_days[i as usize];
-    }
-    days += datetime.day as i32;
-    days
+/// A bar for each value, scaled so the tallest one fits, on a track the full
+/// height of the chart. `None` leaves just the track. `line` is drawn across
+/// dashed, so bars still show through it.
+pub struct BarChart<'a> {
+    pub top_left: Point,
+    pub size: Size,
+    pub values: &'a [Option<u16>],
+    pub line: Option<f32>,
+}
+
+impl BarChart<'_> {
+    /// The value at the top of the chart.
+    pub fn max(&self) -> f32 {
+        let tallest = self.values.iter().flatten().copied().max().unwrap_or(0);
+        (tallest as f32).max(self.line.unwrap_or(0.)).max(1.)
+    }
+
+    pub fn draw<D>(&self, disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        if self.values.is_empty() {
+            return;
+        }
+        let slot = self.size.width / self.values.len() as u32;
+        let width = slot.saturating_sub(2).max(1);
+        let height = self.size.height;
+        let max = self.max();
+
+        for (i, value) in self.values.iter().enumerate() {
+            let x = self.top_left.x + (i as u32 * slot) as i32;
+            draw_rounded_rect!(
+                Point::new(x, self.top_left.y),
+                Size::new(width, height),
+                Size::new(2, 2),
+                PROGRESS_BG,
+                disp
+            );
+
+            let Some(value) = value else {
+                continue;
+            };
+            let bar = (*value as f32 / max * height as f32 + 0.5) as u32;
+            if bar > 0 {
+                draw_rounded_rect!(
+                    Point::new(x, self.top_left.y + (height - bar) as i32),
+                    Size::new(width, bar),
+                    Size::new(2, 2),
+                    PROGRESS_BLUE,
+                    disp
+                );
+            }
+        }
+
+        if let Some(line) = self.line {
+            let y = self.top_left.y + height as i32 - (line / max * height as f32) as i32;
+            for x in (0..slot * self.values.len() as u32).step_by(4) {
+                draw_rect!(
+                    Point::new(self.top_left.x + x as i32, y),
+                    Size::new(2, 1),
+                    PROGRESS_ORANGE,
+                    disp
+                );
+            }
+        }
+    }
 }
 
-fn days_between(date: &DateTime, other: &DateTime) -> i32 {
-    let days1 = days_since_epoch(date);
-    let days2 = days_since_epoch(other);
-    (days1 - days2).abs()
+/// What happens after a screen has handled a button.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Route {
+    Stay,
+    /// Opens a screen on top of this one, B goes back.
+    Push(ScreenId),
+    Pop,
 }
 
-pub enum Screens {
-    Home,
-    Session,
+/// A screen below the nav bar, see `screens!` for adding one. The hooks do
+/// nothing unless the screen needs them.
+pub trait Screen {
+    /// What `fetch_data` asks the API for while the screen is on top.
+    const REQUEST: RequestType = RequestType::Stats;
+
+    /// Called each time the screen comes to the top, from the nav bar or when
+    /// the one over it is popped. Draws what doesn't have to wait for data,
+    /// the rest is drawn once it arrives.
+    async fn init<D>(&self, _disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+    }
+
+    /// Whether the screen wants every button, including left, right and B.
+    async fn grabs_input(&self) -> bool {
+        false
+    }
+
+    async fn input<D>(&self, _btn: Button, _disp: &mut D) -> Route
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        Route::Stay
+    }
+
+    async fn long_press<D>(&self, _btn: Button, _disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+    }
+
+    async fn on_data<D>(
+        &self,
+        _disp: &mut D,
+        _data: RequestData,
+        _old_count: u16,
+        _now: NaiveDateTime,
+    ) where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+    }
+
+    /// Every `TICK` while the screen is on top.
+    async fn on_tick<D>(&self, _disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+    }
+
+    /// Called when the screen is taken off the stack, not when another one is
+    /// pushed over it.
+    async fn teardown(&self) {}
 }
 
-impl<'a> Screens {
-    pub async fn init(&self, spawner: &Spawner) {
-        session::ON_SCREEN.reset();
-        match self {
-            Screens::Home => {
-                *(REQUEST_TYPE.lock().await) = RequestType::Stats;
-                home::init().await;
+/// Lists every screen, and dispatches to them by `ScreenId`. Only the ones in
+/// `nav::NAV` get a button, the rest are pushed by other screens.
+macro_rules! screens {
+    ($($id:ident => $screen:path),* $(,)?) => {
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        pub enum ScreenId {
+            $($id),*
+        }
+
+        impl ScreenId {
+            fn request(self) -> RequestType {
+                match self {
+                    $(ScreenId::$id => <$screen as Screen>::REQUEST),*
+                }
+            }
+
+            async fn init<D>(self, disp: &mut D)
+            where
+                D: DrawTarget<Color = Rgb565>,
+                D::Error: Debug,
+            {
+                match self {
+                    $(ScreenId::$id => $screen.init(disp).await),*
+                }
+            }
+
+            async fn grabs_input(self) -> bool {
+                match self {
+                    $(ScreenId::$id => $screen.grabs_input().await),*
+                }
+            }
+
+            async fn input<D>(self, btn: Button, disp: &mut D) -> Route
+            where
+                D: DrawTarget<Color = Rgb565>,
+                D::Error: Debug,
+            {
+                match self {
+                    $(ScreenId::$id => $screen.input(btn, disp).await),*
+                }
+            }
+
+            async fn long_press<D>(self, btn: Button, disp: &mut D)
+            where
+                D: DrawTarget<Color = Rgb565>,
+                D::Error: Debug,
+            {
+                match self {
+                    $(ScreenId::$id => $screen.long_press(btn, disp).await),*
+                }
+            }
+
+            async fn on_data<D>(self, disp: &mut D, data: RequestData, old_count: u16, now: NaiveDateTime)
+            where
+                D: DrawTarget<Color = Rgb565>,
+                D::Error: Debug,
+            {
+                match self {
+                    $(ScreenId::$id => $screen.on_data(disp, data, old_count, now).await),*
+                }
+            }
+
+            async fn on_tick<D>(self, disp: &mut D)
+            where
+                D: DrawTarget<Color = Rgb565>,
+                D::Error: Debug,
+            {
+                match self {
+                    $(ScreenId::$id => $screen.on_tick(disp).await),*
+                }
             }
-            Screens::Session => {
-                *(REQUEST_TYPE.lock().await) = RequestType::Session;
-                session::init(spawner).await;
+
+            async fn teardown(self) {
+                match self {
+                    $(ScreenId::$id => $screen.teardown().await),*
+                }
             }
         }
+    };
+}
+
+screens! {
+    Home => home::Home,
+    Session => session::Session,
+    GoalPicker => session::Picker,
+    Leaderboard => leaderboard::Leaderboard,
+    Projects => projects::Projects,
+    ProjectDetail => projects::Detail,
+    Wishlist => wishlist::Wishlist,
+    Shop => shop::Shop,
+    Errors => errors::Errors,
+    ErrorDetail => errors::Detail,
+    Settings => settings::Settings,
+}
+
+/// How often the screen on top gets `on_tick`.
+pub const TICK: Duration = Duration::from_secs(1);
+
+#[embassy_executor::task]
+pub async fn tick_task() {
+    loop {
+        Timer::after(TICK).await;
+        EVENTS.send(Events::Tick).await;
+    }
+}
+
+/// Most screens open on top of each other.
+const MAX_DEPTH: usize = 4;
+
+/// The screen picked on the nav bar and any pushed over it. Only the top one
+/// is shown, and B goes back to the one below.
+pub struct Router {
+    stack: Vec<ScreenId, MAX_DEPTH>,
+}
+
+impl Router {
+    /// Starts on `root` without initialising it, it's drawn once its data
+    /// arrives.
+    pub fn new(root: ScreenId) -> Self {
+        let mut stack = Vec::new();
+        stack.push(root).unwrap();
+        Self { stack }
+    }
+
+    pub fn top(&self) -> ScreenId {
+        *self.stack.last().unwrap()
+    }
+
+    /// How many screens are open, counting the root.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Replaces the whole stack with `root`.
+    pub async fn switch<D>(&mut self, root: ScreenId, disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        while let Some(screen) = self.stack.pop() {
+            screen.teardown().await;
+        }
+        self.stack.push(root).unwrap();
+        self.show(disp).await;
+    }
+
+    pub async fn push<D>(&mut self, screen: ScreenId, disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        if self.stack.push(screen).is_err() {
+            error!("[GUI] Too many screens open to push {:?}!", screen);
+            return;
+        }
+        self.show(disp).await;
+    }
+
+    /// Goes back to the screen below, if there is one.
+    pub async fn pop<D>(&mut self, disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        if self.stack.len() < 2 {
+            return;
+        }
+        if let Some(screen) = self.stack.pop() {
+            screen.teardown().await;
+        }
+        self.show(disp).await;
+    }
+
+    async fn show<D>(&self, disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let screen = self.top();
+        *(REQUEST_TYPE.lock().await) = screen.request();
+        draw_rect!(Point::new(0, 14), Size::new(160, 114), BACKGROUND, disp);
+        screen.init(disp).await;
+        RUN.signal(true);
+    }
+
+    pub async fn grabs_input(&self) -> bool {
+        self.top().grabs_input().await
     }
 
-    pub async fn input(&self, btn: Button, disp: &mut Display<'a>) {
-        match self {
-            Screens::Home => home::input(btn, disp).await,
-            _ => {} //Screens::Session => home::input(btn, disp).await,
+    pub async fn input<D>(&mut self, btn: Button, disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let screen = self.top();
+        if btn == Button::B && self.stack.len() > 1 && !screen.grabs_input().await {
+            return self.pop(disp).await;
         }
+
+        match screen.input(btn, disp).await {
+            Route::Stay => {}
+            Route::Push(next) => self.push(next, disp).await,
+            Route::Pop => self.pop(disp).await,
+        }
+    }
+
+    pub async fn long_press<D>(&self, btn: Button, disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        self.top().long_press(btn, disp).await;
     }
 
-    pub async fn update(
+    pub async fn on_data<D>(
         &self,
-        disp: &mut Display<'a>,
+        disp: &mut D,
         data: RequestData,
         old_count: u16,
-        now: DateTime,
-    ) {
-        match self {
-            Screens::Home => home::update(disp, data, old_count, now).await,
-            Screens::Session => session::update(disp, data, now).await,
-        }
+        now: NaiveDateTime,
+    ) where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        self.top().on_data(disp, data, old_count, now).await;
+    }
+
+    pub async fn on_tick<D>(&self, disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        self.top().on_tick(disp).await;
     }
 }
 
 pub mod nav {
-    use embedded_graphics::{image::Image, pixelcolor::Rgb565, Drawable};
+    use core::fmt::Debug;
+
+    use embedded_graphics::{
+        draw_target::DrawTarget,
+        geometry::{Point, Size},
+        image::Image,
+        pixelcolor::Rgb565,
+        primitives::Primitive,
+        Drawable,
+    };
+    use sprig_arcade_core::nav::are_neighbours;
     use tinytga::Tga;
 
-    use crate::{
-        util::{ACTIVE_BTN, BTN, SELECTED_BTN},
-        Display, NavButton,
+    use super::{ScreenId, PROGRESS_BG, PROGRESS_BLUE, PROGRESS_ORANGE};
+    use crate::draw_rect;
+    use crate::util::{
+        Link, ACTIVE_BTN, BTN, ERRORS_ICON, HOME_ICON, LEADERBOARD_ICON, PROJECTS_ICON,
+        SELECTED_BTN, SESSION_ICON, SETTINGS_ICON, SHOP_ICON, WISHLIST_ICON,
     };
 
-    pub fn update_selected(
-        selected: &NavButton,
-        prev_selected: &NavButton,
-        active: &NavButton,
-        disp: &mut Display,
-    ) {
-        let btn: Tga<Rgb565> = Tga::from_slice(if prev_selected == active {
+    /// A button on the nav bar and the screen it opens.
+    pub struct NavEntry {
+        pub screen: ScreenId,
+        icon: &'static [u8],
+        /// Where the icon sits on the button.
+        icon_offset: Point,
+    }
+
+    /// The nav bar from the left, Home first.
+    pub const NAV: [NavEntry; 8] = [
+        NavEntry {
+            screen: ScreenId::Home,
+            icon: HOME_ICON,
+            icon_offset: Point::new(5, 2),
+        },
+        NavEntry {
+            screen: ScreenId::Session,
+            icon: SESSION_ICON,
+            icon_offset: Point::new(4, 1),
+        },
+        NavEntry {
+            screen: ScreenId::Leaderboard,
+            icon: LEADERBOARD_ICON,
+            icon_offset: Point::new(5, 3),
+        },
+        NavEntry {
+            screen: ScreenId::Projects,
+            icon: PROJECTS_ICON,
+            icon_offset: Point::new(5, 1),
+        },
+        // TODO: merge wishlist with shop?
+        NavEntry {
+            screen: ScreenId::Wishlist,
+            icon: WISHLIST_ICON,
+            icon_offset: Point::new(4, 2),
+        },
+        NavEntry {
+            screen: ScreenId::Shop,
+            icon: SHOP_ICON,
+            icon_offset: Point::new(4, 0),
+        },
+        // TODO: hide this by default
+        NavEntry {
+            screen: ScreenId::Errors,
+            icon: ERRORS_ICON,
+            icon_offset: Point::new(5, 1),
+        },
+        NavEntry {
+            screen: ScreenId::Settings,
+            icon: SETTINGS_ICON,
+            icon_offset: Point::new(5, 2),
+        },
+    ];
+
+    /// Where the first button is drawn, the rest follow on its right.
+    const FIRST: Point = Point::new(23, 0);
+    const SPACING: i32 = 16;
+
+    fn pos(button: usize) -> Point {
+        FIRST + Point::new(button as i32 * SPACING, 0)
+    }
+
+    /// Draws a button, `style` being one of `BTN`, `ACTIVE_BTN` or
+    /// `SELECTED_BTN`, with its icon on top.
+    fn draw_button<D>(button: usize, style: &[u8], disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let entry = &NAV[button];
+        Image::new(&Tga::<Rgb565>::from_slice(style).unwrap(), pos(button))
+            .draw(disp)
+            .unwrap();
+        Image::new(
+            &Tga::<Rgb565>::from_slice(entry.icon).unwrap(),
+            pos(button) + entry.icon_offset,
+        )
+        .draw(disp)
+        .unwrap();
+    }
+
+    /// Every button, none of them active.
+    pub fn draw_bar<D>(disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        for button in 0..NAV.len() {
+            draw_button(button, BTN, disp);
+        }
+    }
+
+    pub fn update_selected<D>(selected: usize, prev_selected: usize, active: usize, disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let btn = if prev_selected == active {
             ACTIVE_BTN
         } else {
             BTN
-        })
-        .unwrap();
-        let selected_btn: Tga<Rgb565> = Tga::from_slice(SELECTED_BTN).unwrap();
-        Image::new(&btn, prev_selected.pos()).draw(disp).unwrap();
-        Image::new(&prev_selected.icon(), prev_selected.icon_pos())
-            .draw(disp)
-            .unwrap();
+        };
+        draw_button(prev_selected, btn, disp);
 
         if active != selected {
-            Image::new(&selected_btn, selected.pos())
-                .draw(disp)
-                .unwrap();
-
-            Image::new(&selected.icon(), selected.icon_pos())
-                .draw(disp)
-                .unwrap();
+            draw_button(selected, SELECTED_BTN, disp);
         }
 
-        if selected == active || prev_selected.is_neighbour_of(active) {
-            Image::new(
-                &Tga::<Rgb565>::from_slice(ACTIVE_BTN).unwrap(),
-                active.pos(),
-            )
-            .draw(disp)
-            .unwrap();
-            Image::new(&active.icon(), active.icon_pos())
-                .draw(disp)
-                .unwrap();
+        if selected == active || are_neighbours(prev_selected, active, NAV.len()) {
+            draw_button(active, ACTIVE_BTN, disp);
         }
     }
 
-    pub fn update_active(active: &NavButton, prev_active: &NavButton, disp: &mut Display) {
-        let btn: Tga<Rgb565> = Tga::from_slice(BTN).unwrap();
-        let active_btn: Tga<Rgb565> = Tga::from_slice(ACTIVE_BTN).unwrap();
-
-        Image::new(&btn, prev_active.pos()).draw(disp).unwrap();
+    /// Signal bars left of the nav bar: blue when connected, orange while
+    /// joining and empty when the network can't be joined.
+    pub fn draw_link<D>(link: Link, disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let style = match link {
+            Link::Up => PROGRESS_BLUE,
+            Link::Joining => PROGRESS_ORANGE,
+            Link::Down => PROGRESS_BG,
+        };
 
-        Image::new(&active_btn, active.pos()).draw(disp).unwrap();
+        for bar in 0..3 {
+            let height = 3 + bar * 3;
+            draw_rect!(
+                Point::new(6 + bar as i32 * 4, 11 - height as i32),
+                Size::new(3, height),
+                style,
+                disp
+            );
+        }
+    }
 
-        Image::new(&active.icon(), active.icon_pos())
-            .draw(disp)
-            .unwrap();
-        Image::new(&prev_active.icon(), prev_active.icon_pos())
-            .draw(disp)
-            .unwrap();
+    pub fn update_active<D>(active: usize, prev_active: usize, disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        draw_button(prev_active, BTN, disp);
+        draw_button(active, ACTIVE_BTN, disp);
     }
 }
 
 pub mod home {
-    use core::cmp::max;
-    use core::fmt::Write;
-    use core::sync::atomic::Ordering;
+    use chrono::{Datelike, Days, NaiveDateTime};
+    use core::fmt::{Debug, Write};
+    use core::sync::atomic::{AtomicU8, Ordering};
     use core::{f32::consts::PI, sync::atomic::AtomicBool};
-    use embassy_rp::rtc::{DateTime, DayOfWeek};
     use embassy_time::Timer;
     use embedded_graphics::draw_target::DrawTarget;
     use embedded_graphics::{
@@ -230,56 +649,219 @@ pub mod home {
     use heapless::String;
     use log::{debug, error, info};
     use micromath::F32Ext;
+    use portable_atomic::AtomicU16;
+    use sprig_arcade_core::api::RequestData;
+    use sprig_arcade_core::forecast::{forecast, Window};
+    use sprig_arcade_core::history;
+    use sprig_arcade_core::season::{Phase, Season};
+    use sprig_arcade_core::stats::{
+        average_per_day, days_left, earned, ideal_per_day, ideal_percent, needed_per_day,
+        percent_done, tickets_left,
+    };
+    use sprig_arcade_core::streak::{streak, Streak};
     use tinytga::Tga;
 
     use super::{
-        BACKGROUND, CENTERED_TEXT, PROGRESS_BG, PROGRESS_BLUE, PROGRESS_ORANGE, STAT_ONE_CHAR,
-        STAT_THREE_CHAR,
+        BarChart, Route, Screen, BACKGROUND, BLACK_CHAR, CENTERED_TEXT, GREEN_CHAR, PROGRESS_BG,
+        PROGRESS_BLUE, PROGRESS_ORANGE, RED_CHAR, STAT_ONE_CHAR, STAT_THREE_CHAR,
     };
-    use crate::gui::{days_between, NUMBER_CHAR};
-    use crate::wifi::{RequestData, RUN};
+    use crate::gui::NUMBER_CHAR;
+    use crate::util::{clock, RUN};
     use crate::{
-        draw_rect, draw_rounded_rect, draw_tga, write_large_text, write_text, UPDATE_INTERVAL,
+        apply_update_interval, draw_rect, draw_rounded_rect, draw_tga, write_large_text, write_text,
     };
     use crate::{
         format,
-        util::{ARCADE_LOGO, PROGRESS_SELECTED, STATS_SELECTED, TICKET_LARGE, TICKET_SMALL},
-        Button, Display, TICKET_GOAL, TICKET_OFFSET,
+        util::{
+            ARCADE_LOGO, CHART_SELECTED, PROGRESS_SELECTED, STATS_SELECTED, TICKET_LARGE,
+            TICKET_SMALL,
+        },
+        Button, CONFIG, HISTORY,
     };
 
-    static SELECTED: AtomicBool = AtomicBool::new(true);
+    /// Where the side tabs are drawn.
+    const TABS: Point = Point::new(146, 39);
+    /// Days the chart shows at once, how far back it scrolls, and how many
+    /// days each press scrolls by.
+    const CHART_DAYS: u8 = 14;
+    const CHART_HISTORY: u8 = 28;
+    const CHART_STEP: u8 = 7;
+
+    /// The pages down the side tabs, from the top.
+    #[derive(Clone, Copy, PartialEq)]
+    enum Page {
+        Progress,
+        Stats,
+        Chart,
+    }
+
+    impl Page {
+        const ALL: [Page; 3] = [Page::Progress, Page::Stats, Page::Chart];
+
+        fn tab(&self) -> &'static [u8] {
+            match self {
+                Page::Progress => PROGRESS_SELECTED,
+                Page::Stats => STATS_SELECTED,
+                Page::Chart => CHART_SELECTED,
+            }
+        }
+    }
+
+    static PAGE: AtomicU8 = AtomicU8::new(0);
+    /// Days back from today the chart is scrolled.
+    static SCROLL: AtomicU8 = AtomicU8::new(0);
+    /// The streak the last time the stats were drawn, `u16::MAX` before then.
+    static SEEN_STREAK: AtomicU16 = AtomicU16::new(u16::MAX);
+    /// Which `Window::ALL` the forecast is measured over.
+    static WINDOW: AtomicU8 = AtomicU8::new(0);
+
+    fn page() -> Page {
+        Page::ALL[PAGE.load(Ordering::Relaxed) as usize]
+    }
+
+    /// Whether left and right scroll the chart instead of moving along the
+    /// nav bar.
+    pub fn on_chart() -> bool {
+        page() == Page::Chart
+    }
+
+    fn window() -> Window {
+        Window::ALL[WINDOW.load(Ordering::Relaxed) as usize]
+    }
+
+    pub(super) async fn season() -> Season {
+        Season::from_config(&*CONFIG.lock().await)
+    }
+
+    /// Outside the season there's nothing to track, so say when the next one
+    /// starts or how the last one went instead. Returns whether it did.
+    fn draw_off_season<D>(
+        disp: &mut D,
+        season: &Season,
+        ticket_count: u16,
+        now: &NaiveDateTime,
+    ) -> bool
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let (title, detail) = match season.phase(now) {
+            Phase::Running => return false,
+            Phase::Upcoming(1) => ("Next season", format!(32, "starts tomorrow!")),
+            Phase::Upcoming(days) => ("Next season", format!(32, "starts in {} days!", days)),
+            Phase::Over => (
+                "Season over!",
+                format!(32, "You earned {} tickets.", earned(ticket_count, season)),
+            ),
+        };
+
+        write_text!(title, Point::new(73, 50), CENTERED_TEXT, disp);
+        write_text!(&detail, Point::new(73, 62), CENTERED_TEXT, disp);
+        true
+    }
 
     pub async fn init() {
-        UPDATE_INTERVAL.store(5, core::sync::atomic::Ordering::Relaxed);
+        apply_update_interval(&*CONFIG.lock().await);
     }
 
-    pub async fn input(btn: Button, disp: &mut Display<'_>) {
-        match btn {
-            Button::Up => {
-                if !SELECTED.load(Ordering::Relaxed) {
-                    SELECTED.store(true, Ordering::Relaxed);
+    /// Progress, stats and the ticket chart, down the side tabs.
+    pub struct Home;
 
-                    draw_rect!(Point::new(0, 14), Size::new(146, 114), BACKGROUND, disp);
-                    draw_tga!(PROGRESS_SELECTED, Point::new(146, 47), disp);
+    impl Screen for Home {
+        async fn init<D>(&self, _disp: &mut D)
+        where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            init().await;
+        }
 
-                    RUN.signal(true);
-                }
-            }
-            Button::Down => {
-                if SELECTED.load(Ordering::Relaxed) {
-                    SELECTED.store(false, Ordering::Relaxed);
+        async fn grabs_input(&self) -> bool {
+            on_chart()
+        }
+
+        async fn input<D>(&self, btn: Button, disp: &mut D) -> Route
+        where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            input(btn, disp).await;
+            Route::Stay
+        }
 
-                    draw_rect!(Point::new(0, 14), Size::new(146, 114), BACKGROUND, disp);
-                    draw_tga!(STATS_SELECTED, Point::new(146, 47), disp);
+        async fn on_data<D>(
+            &self,
+            disp: &mut D,
+            data: RequestData,
+            old_count: u16,
+            now: NaiveDateTime,
+        ) where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            update(disp, data, old_count, now).await;
+        }
+    }
 
-                    RUN.signal(true);
+    pub async fn input<D>(btn: Button, disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        match btn {
+            Button::Up | Button::Down => {
+                let current = PAGE.load(Ordering::Relaxed);
+                let next = match btn {
+                    Button::Up => current.saturating_sub(1),
+                    _ => (current + 1).min(Page::ALL.len() as u8 - 1),
+                };
+                if next == current {
+                    return;
                 }
+                PAGE.store(next, Ordering::Relaxed);
+
+                draw_rect!(Point::new(0, 14), Size::new(146, 114), BACKGROUND, disp);
+                draw_tga!(page().tab(), TABS, disp);
+
+                match page() {
+                    // the history is already here, no need to wait for the API
+                    Page::Chart => {
+                        SCROLL.store(0, Ordering::Relaxed);
+                        if let Some(now) = clock() {
+                            update_chart(disp, now).await;
+                        }
+                    }
+                    _ => RUN.signal(true),
+                }
+            }
+            Button::Left | Button::Right if on_chart() => {
+                let scroll = SCROLL.load(Ordering::Relaxed);
+                let next = match btn {
+                    Button::Left => (scroll + CHART_STEP).min(CHART_HISTORY - CHART_DAYS),
+                    _ => scroll.saturating_sub(CHART_STEP),
+                };
+                if next != scroll {
+                    SCROLL.store(next, Ordering::Relaxed);
+                    if let Some(now) = clock() {
+                        update_chart(disp, now).await;
+                    }
+                }
+            }
+            Button::A if page() == Page::Stats => {
+                let next = window().next();
+                let index = Window::ALL.iter().position(|w| *w == next).unwrap_or(0);
+                WINDOW.store(index as u8, Ordering::Relaxed);
+                RUN.signal(true);
             }
             _ => (),
         }
     }
 
-    pub async fn update(disp: &mut Display<'_>, data: RequestData, old_count: u16, now: DateTime) {
+    pub async fn update<D>(disp: &mut D, data: RequestData, old_count: u16, now: NaiveDateTime)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
         let tickets;
         match data {
             RequestData::Stats(ticket_count) => {
@@ -290,29 +872,41 @@ pub mod home {
                 return;
             }
         }
-        match SELECTED.load(Ordering::Relaxed) {
-            false => {
+        match page() {
+            Page::Progress => {
+                debug!("[GUI] Updating progress bar...");
+                update_progress(disp, tickets, old_count, now).await;
+            }
+            Page::Stats => {
                 debug!("[GUI] Updating stats...");
                 update_stats(disp, tickets, now).await;
             }
-            true => {
-                debug!("[GUI] Updating progress bar...");
-                update_progress(disp, tickets, old_count, now).await;
+            Page::Chart => {
+                debug!("[GUI] Updating chart...");
+                update_chart(disp, now).await;
             }
         }
     }
 
-    async fn update_progress(
-        disp: &mut Display<'_>,
+    pub async fn update_progress<D>(
+        disp: &mut D,
         ticket_count: u16,
         old_count: u16,
-        now: DateTime,
-    ) {
+        now: NaiveDateTime,
+    ) where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
         draw_tga!(ARCADE_LOGO, Point::new(30, 98), disp);
-        draw_tga!(PROGRESS_SELECTED, Point::new(146, 47), disp);
+        draw_tga!(PROGRESS_SELECTED, TABS, disp);
+
+        let season = season().await;
+        if draw_off_season(disp, &season, ticket_count, &now) {
+            return;
+        }
 
         let mut count = String::<4>::new();
-        write!(count, "{} ", ticket_count - TICKET_OFFSET).unwrap();
+        write!(count, "{} ", earned(ticket_count, &season)).unwrap();
         debug!("[GUI] {:?}", count);
         write_text!(
             custom,
@@ -329,34 +923,9 @@ pub mod home {
             disp
         );
 
-        let end = DateTime {
-            year: 2024,
-            month: 9,
-            day: 31,
-            hour: 23,
-            minute: 59,
-            second: 59,
-            day_of_week: DayOfWeek::Saturday,
-        };
-        let start = DateTime {
-            year: 2024,
-            month: 6,
-            day: 18,
-            hour: 0,
-            minute: 0,
-            second: 0,
-            day_of_week: DayOfWeek::Tuesday,
-        };
-
-        let passed_days = days_between(&now, &start);
-        let ideal_percent = (passed_days as f32 + 1.0) / (days_between(&end, &start) as f32 - 1.0);
+        let ideal_percent = ideal_percent(&season, &now);
 
-        let old = old_count
-            - if TICKET_OFFSET > old_count {
-                old_count
-            } else {
-                TICKET_OFFSET
-            };
+        let old = earned(old_count, &season);
         draw_rounded_rect!(
             Point::new(20, 62),
             Size::new(6, 6),
@@ -379,7 +948,7 @@ pub mod home {
             disp
         );
 
-        let per = (ticket_count - TICKET_OFFSET) as f32 / TICKET_GOAL as f32;
+        let per = percent_done(ticket_count, &season);
 
         // TODO: max out percentage to 100%
         let complete = format!(11, "{}% there!", (per * 100.).round());
@@ -388,17 +957,14 @@ pub mod home {
             28,
             "Should be {}% ({}  ) done!",
             (ideal_percent * 100.).round(),
-            (ideal_percent * TICKET_GOAL as f32).round()
+            (ideal_percent * season.goal as f32).round()
         );
 
         let left = format!(
             32,
             "{}% left ({}  )!",
             ((1. - per) * 100.).round() as u16,
-            max(
-                TICKET_GOAL as i16 - ticket_count as i16 + TICKET_OFFSET as i16,
-                0
-            ),
+            tickets_left(ticket_count, &season),
         );
 
         write_text!(&complete, Point::new(28, 62), disp);
@@ -419,8 +985,8 @@ pub mod home {
             disp
         );
 
-        let prev = old as f32 / TICKET_GOAL as f32;
-        let change = (ticket_count - TICKET_OFFSET - old) as f32 / TICKET_GOAL as f32;
+        let prev = old as f32 / season.goal as f32;
+        let change = (earned(ticket_count, &season) as f32 - old as f32) / season.goal as f32;
 
         static DRAWN: AtomicBool = AtomicBool::new(false);
         let mut data = [Rgb565::new(31, 60, 27); 120 * 6];
@@ -484,7 +1050,11 @@ pub mod home {
         DRAWN.store(true, core::sync::atomic::Ordering::Relaxed);
     }
 
-    async fn update_stats(disp: &mut Display<'_>, ticket_count: u16, now: DateTime) {
+    pub async fn update_stats<D>(disp: &mut D, ticket_count: u16, now: NaiveDateTime)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
         macro_rules! round_format {
             ($num:expr) => {{
                 let num = $num;
@@ -497,28 +1067,12 @@ pub mod home {
             }};
         }
 
-        let end = DateTime {
-            year: 2024,
-            month: 9,
-            day: 31,
-            hour: 23,
-            minute: 59,
-            second: 59,
-            day_of_week: DayOfWeek::Saturday,
-        };
-        let start = DateTime {
-            year: 2024,
-            month: 6,
-            day: 18,
-            hour: 0,
-            minute: 0,
-            second: 0,
-            day_of_week: DayOfWeek::Tuesday,
-        };
+        let season = season().await;
+        if draw_off_season(disp, &season, ticket_count, &now) {
+            return;
+        }
 
-        let hrs = round_format!(
-            (ticket_count - TICKET_OFFSET) as f32 / (days_between(&now, &start) as f32 + 1.)
-        );
+        let hrs = round_format!(average_per_day(ticket_count, &season, &now));
         write_text!(custom, &hrs, Point::new(23, 29), STAT_ONE_CHAR, disp);
         write_text!(
             "hrs/day on average.",
@@ -526,7 +1080,7 @@ pub mod home {
             disp
         );
 
-        let ideal = round_format!(TICKET_GOAL as f32 / days_between(&end, &start) as f32);
+        let ideal = round_format!(ideal_per_day(&season));
         write_text!(custom, &ideal, Point::new(23, 45), NUMBER_CHAR, disp);
         write_text!(
             "ideal daily tickets.",
@@ -534,7 +1088,7 @@ pub mod home {
             disp
         );
 
-        let days_left = format!(2, "{}", days_between(&end, &now) - 1);
+        let days_left = format!(4, "{}", days_left(&season, &now));
         write_text!(
             custom,
             &days_left,
@@ -548,9 +1102,7 @@ pub mod home {
             disp
         );
 
-        let on_track = round_format!(
-            (TICKET_GOAL - ticket_count + TICKET_OFFSET) as f32 / days_between(&end, &now) as f32
-        );
+        let on_track = round_format!(needed_per_day(ticket_count, &season, &now));
 
         write_text!(custom, &on_track, Point::new(23, 77), STAT_ONE_CHAR, disp);
         write_text!(
@@ -558,116 +1110,570 @@ pub mod home {
             Point::new(26 + (on_track.len() * 8) as i32, 79),
             disp
         );
+
+        let days = *HISTORY.lock().await;
+        draw_forecast(disp, &days, &season, ticket_count, now);
+
+        let streak = streak(&days, &season, now.date());
+        // the first one drawn after turning on isn't news
+        let seen = SEEN_STREAK.swap(streak.current, Ordering::Relaxed);
+        if seen != u16::MAX && streak.current > seen {
+            celebrate(disp, &streak).await;
+        } else {
+            draw_streak(disp, &streak);
+        }
+    }
+
+    fn draw_streak<D>(disp: &mut D, streak: &Streak)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        draw_rect!(Point::new(0, 90), Size::new(146, 16), BACKGROUND, disp);
+
+        let current = format!(3, "{}", streak.current);
+        write_text!(custom, &current, Point::new(23, 93), NUMBER_CHAR, disp);
+        let best = format!(24, "day streak, best {}.", streak.longest);
+        write_text!(&best, Point::new(26 + (current.len() * 8) as i32, 95), disp);
+    }
+
+    /// When the goal is reached at the pace over the chosen window, green if
+    /// it's before the season ends, and how many tickets that makes by then.
+    fn draw_forecast<D>(
+        disp: &mut D,
+        days: &history::Days,
+        season: &Season,
+        ticket_count: u16,
+        now: NaiveDateTime,
+    ) where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        draw_rect!(Point::new(0, 106), Size::new(146, 22), BACKGROUND, disp);
+
+        let window = window();
+        let Some(forecast) = forecast(days, season, window, ticket_count, now.date()) else {
+            let text = format!(40, "No {} pace yet. A: change pace", window.label());
+            write_text!(&text, Point::new(73, 108), CENTERED_TEXT, disp);
+            return;
+        };
+
+        let (line, style) = match forecast.finish {
+            Some(finish) => (
+                format!(
+                    40,
+                    "Done by {}/{} at your {} pace.",
+                    finish.month(),
+                    finish.day(),
+                    window.label()
+                ),
+                if forecast.on_time(season) {
+                    GREEN_CHAR
+                } else {
+                    RED_CHAR
+                },
+            ),
+            None => (
+                format!(40, "Never done at your {} pace.", window.label()),
+                RED_CHAR,
+            ),
+        };
+        write_text!(
+            custom,
+            &line,
+            Point::new(73, 108),
+            style,
+            CENTERED_TEXT,
+            disp
+        );
+
+        let end = format!(
+            40,
+            "{} tickets by {}/{}. A: change pace",
+            forecast.at_end,
+            season.end.month(),
+            season.end.day()
+        );
+        write_text!(
+            custom,
+            &end,
+            Point::new(73, 118),
+            BLACK_CHAR,
+            CENTERED_TEXT,
+            disp
+        );
+    }
+
+    /// Sparks fly out of the streak when it grows.
+    async fn celebrate<D>(disp: &mut D, streak: &Streak)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        const SPARKS: [(i32, i32); 6] = [(-3, -1), (-2, 1), (-1, -1), (1, 1), (2, -1), (3, 1)];
+
+        let center = Point::new(23 + format!(3, "{}", streak.current).len() as i32 * 4, 98);
+        for frame in 1..=12 {
+            draw_streak(disp, streak);
+            for (i, (dx, dy)) in SPARKS.iter().enumerate() {
+                let style = match (i + frame as usize) % 2 {
+                    0 => PROGRESS_ORANGE,
+                    _ => PROGRESS_BLUE,
+                };
+                let spark = center + Point::new(dx * frame, dy * frame / 2);
+                draw_rect!(spark, Size::new(2, 2), style, disp);
+            }
+            Timer::after_millis(40).await;
+        }
+        draw_streak(disp, streak);
+    }
+
+    /// Tickets earned each day, `CHART_DAYS` at a time, with the ideal daily
+    /// tickets across them.
+    pub async fn update_chart<D>(disp: &mut D, now: NaiveDateTime)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let season = season().await;
+        let days = *HISTORY.lock().await;
+
+        let last = now.date() - Days::new(SCROLL.load(Ordering::Relaxed) as u64);
+        let first = last - Days::new(CHART_DAYS as u64 - 1);
+        let mut earned = [None; CHART_DAYS as usize];
+        for (i, tickets) in earned.iter_mut().enumerate() {
+            *tickets = days.earned(first + Days::new(i as u64));
+        }
+
+        draw_rect!(Point::new(0, 14), Size::new(146, 114), BACKGROUND, disp);
+        write_text!("Tickets per day", Point::new(73, 26), CENTERED_TEXT, disp);
+
+        let chart = BarChart {
+            top_left: Point::new(14, 34),
+            size: Size::new(126, 60),
+            values: &earned,
+            line: Some(ideal_per_day(&season)),
+        };
+        chart.draw(disp);
+
+        let max = format!(3, "{}", chart.max() as u16);
+        write_text!(&max, Point::new(2, 39), disp);
+        write_text!("0", Point::new(2, 94), disp);
+
+        // under the first and last bars
+        let from = format!(5, "{}/{}", first.month(), first.day());
+        let to = format!(5, "{}/{}", last.month(), last.day());
+        write_text!(&from, Point::new(18, 103), CENTERED_TEXT, disp);
+        write_text!(&to, Point::new(135, 103), CENTERED_TEXT, disp);
+
+        for x in [34, 38, 42] {
+            draw_rect!(Point::new(x, 113), Size::new(2, 1), PROGRESS_ORANGE, disp);
+        }
+        write_text!("ideal daily tickets", Point::new(48, 116), disp);
     }
 }
 
 pub mod session {
-    use core::{str::FromStr, sync::atomic::AtomicBool};
+    use core::{
+        fmt::Debug,
+        str::FromStr,
+        sync::atomic::{AtomicBool, AtomicU8, Ordering},
+    };
 
-    use embassy_executor::Spawner;
-    use embassy_rp::rtc::DateTime;
-    use embassy_sync::{blocking_mutex::raw::ThreadModeRawMutex, signal::Signal};
-    use embassy_time::Timer;
+    use chrono::NaiveDateTime;
+    use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
     use embedded_graphics::{
+        draw_target::DrawTarget,
         geometry::Point,
         image::Image,
+        pixelcolor::Rgb565,
         prelude::{Primitive, Size},
         Drawable,
     };
-    use heapless::String;
+    use heapless::{String, Vec};
     use log::error;
+    use sprig_arcade_core::api::{RequestData, RequestType, SessionAction, MAX_GOALS};
+    use sprig_arcade_core::stats::earned;
     use tinytga::Tga;
 
     use crate::{
-        draw_rect, draw_rounded_rect, draw_tga, format,
-        gui::{BLACK_FILL, CENTERED_TEXT},
-        util::{Events, PROGRESS_BAR},
-        wifi::RequestData,
-        write_large_text, write_text, Display, EVENTS, TICKETS, TICKET_OFFSET, UPDATE_INTERVAL,
+        apply_update_interval, draw_rect, draw_rounded_rect, draw_tga, format,
+        gui::{home::season, BLACK_FILL, CENTERED_TEXT, PROGRESS_BG},
+        util::{PROGRESS_BAR, RUN, SESSION_ACTION},
+        write_large_text, write_text, Button, CONFIG, TICKETS, UPDATE_INTERVAL,
     };
 
-    use super::BACKGROUND;
+    use super::{Route, Screen, ScreenId, BACKGROUND};
 
-    pub static ON_SCREEN: Signal<ThreadModeRawMutex, bool> = Signal::new();
     pub static FLASH: AtomicBool = AtomicBool::new(true);
     pub static TRIGGERED: AtomicBool = AtomicBool::new(true);
 
-    pub async fn init(spawner: &Spawner) {
-        UPDATE_INTERVAL.store(1, core::sync::atomic::Ordering::Relaxed);
-        ON_SCREEN.signal(true);
-        spawner.spawn(flash_task()).unwrap();
+    // What is on screen, changed straight away by the controls and put right
+    // by the next poll if the request didn't go through.
+    static ELAPSED: AtomicU8 = AtomicU8::new(0);
+    static PAUSED: AtomicBool = AtomicBool::new(false);
+    static COMPLETED: AtomicBool = AtomicBool::new(true);
+    /// The goal on screen, `None` until the first poll.
+    static GOAL: Mutex<CriticalSectionRawMutex, Option<String<32>>> = Mutex::new(None);
+    /// Whether the colon is hidden, it blinks each tick.
+    static BLINK: AtomicBool = AtomicBool::new(false);
+
+    const PICKER_ROWS: usize = 6;
+
+    /// Goals to start a session with, `loaded` once they have been fetched.
+    struct Goals {
+        goals: Vec<String<32>, MAX_GOALS>,
+        selected: usize,
+        loaded: bool,
     }
 
-    #[embassy_executor::task]
-    pub async fn flash_task() {
-        let mut flashing = false;
-        while ON_SCREEN.signaled() {
-            EVENTS.send(Events::FlashSessionScreen(flashing)).await;
-            flashing = !flashing;
-            Timer::after_secs(1).await;
+    impl Goals {
+        const fn new() -> Self {
+            Self {
+                goals: Vec::new(),
+                selected: 0,
+                loaded: false,
+            }
         }
     }
 
-    pub async fn flash(flash: bool, disp: &mut Display<'_>) {
-        if FLASH.load(core::sync::atomic::Ordering::Relaxed) {
-            if flash {
-                draw_rect!(Point::new(73, 40), Size::new(8, 10), BACKGROUND, disp);
-            } else {
-                write_large_text!(":", Point::new(73, 40), disp);
-            }
-        }
+    static GOALS: Mutex<CriticalSectionRawMutex, Goals> = Mutex::new(Goals::new());
+
+    pub async fn init() {
+        UPDATE_INTERVAL.store(1, Ordering::Relaxed);
     }
 
-    pub async fn update(disp: &mut Display<'_>, data: RequestData, now: DateTime) {
-        let (elapsed, goal, paused) = match data {
-            RequestData::Session(elapsed, goal, paused) => (elapsed, goal, paused),
-            _ => {
-                error!("[GUI] [Session] Recieved incorrect data!");
-                return;
+    /// The session running now, or the last one.
+    pub struct Session;
+
+    impl Screen for Session {
+        const REQUEST: RequestType = RequestType::Session;
+
+        async fn init<D>(&self, disp: &mut D)
+        where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            init().await;
+
+            // back from the picker, the next poll is up to a second away
+            if let Some(goal) = GOAL.lock().await.as_ref() {
+                draw(
+                    disp,
+                    ELAPSED.load(Ordering::Relaxed),
+                    goal,
+                    PAUSED.load(Ordering::Relaxed),
+                    COMPLETED.load(Ordering::Relaxed),
+                )
+                .await;
             }
-        };
+        }
 
-        draw_tga!(PROGRESS_BAR, Point::new(20, 53), disp);
+        async fn input<D>(&self, btn: Button, disp: &mut D) -> Route
+        where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            input(btn, disp).await
+        }
 
-        let display = match elapsed {
-            0 => String::<4>::from_str("1:00").unwrap(),
-            60 => String::<4>::from_str("DONE").unwrap(),
-            _ => format!(4, "0:{:02}", 60 - elapsed),
+        async fn long_press<D>(&self, btn: Button, disp: &mut D)
+        where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            long_press(btn, disp).await;
+        }
+
+        async fn on_data<D>(
+            &self,
+            disp: &mut D,
+            data: RequestData,
+            _old_count: u16,
+            now: NaiveDateTime,
+        ) where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            update(disp, data, now).await;
+        }
+
+        async fn on_tick<D>(&self, disp: &mut D)
+        where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            flash(disp);
+        }
+
+        async fn teardown(&self) {
+            *GOAL.lock().await = None;
+            // polling every second is only for the timer
+            apply_update_interval(&*CONFIG.lock().await);
+        }
+    }
+
+    /// Blinks the colon of the timer while `FLASH` is set.
+    pub fn flash<D>(disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let hidden = BLINK.load(Ordering::Relaxed);
+        BLINK.store(!hidden, Ordering::Relaxed);
+        if FLASH.load(Ordering::Relaxed) {
+            if hidden {
+                draw_rect!(Point::new(73, 40), Size::new(8, 10), BACKGROUND, disp);
+            } else {
+                write_large_text!(":", Point::new(73, 40), disp);
+            }
+        }
+    }
+
+    /// The goals to start a session with, pushed over the session.
+    pub struct Picker;
+
+    impl Screen for Picker {
+        const REQUEST: RequestType = RequestType::Goals;
+
+        async fn init<D>(&self, disp: &mut D)
+        where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            draw_picker(&*GOALS.lock().await, disp);
+        }
+
+        async fn input<D>(&self, btn: Button, disp: &mut D) -> Route
+        where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            picker_input(btn, disp).await
+        }
+
+        async fn on_data<D>(
+            &self,
+            disp: &mut D,
+            data: RequestData,
+            _old_count: u16,
+            _now: NaiveDateTime,
+        ) where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            let goals = match data {
+                RequestData::Goals(goals) => goals,
+                // a poll from before the picker was opened
+                RequestData::Session(..) => return,
+                _ => {
+                    error!("[GUI] [Session] Recieved incorrect data!");
+                    return;
+                }
+            };
+
+            let mut picker = GOALS.lock().await;
+            picker.goals = goals
+                .iter()
+                .map(|goal| String::from_str(goal).unwrap_or_default())
+                .collect();
+            picker.selected = 0;
+            picker.loaded = true;
+            draw_picker(&picker, disp);
+        }
+
+        async fn teardown(&self) {
+            *GOALS.lock().await = Goals::new();
+        }
+    }
+
+    /// A pauses or resumes, or picks a goal to start a session with when
+    /// there isn't one.
+    pub async fn input<D>(btn: Button, disp: &mut D) -> Route
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        if btn != Button::A {
+            return Route::Stay;
+        }
+
+        if COMPLETED.load(Ordering::Relaxed) {
+            return Route::Push(ScreenId::GoalPicker);
+        }
+
+        let paused = !PAUSED.load(Ordering::Relaxed);
+        PAUSED.store(paused, Ordering::Relaxed);
+        send(SessionAction::Pause).await;
+        draw_state(disp, ELAPSED.load(Ordering::Relaxed), paused, false);
+        Route::Stay
+    }
+
+    /// Up and down pick a goal and A starts a session with it.
+    async fn picker_input<D>(btn: Button, disp: &mut D) -> Route
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let mut picker = GOALS.lock().await;
+        match btn {
+            Button::Up => picker.selected = picker.selected.saturating_sub(1),
+            Button::Down => {
+                picker.selected = (picker.selected + 1).min(picker.goals.len().saturating_sub(1))
+            }
+            Button::A => {
+                let Some(goal) = picker.goals.get(picker.selected).cloned() else {
+                    return Route::Stay;
+                };
+                drop(picker);
+                start(goal).await;
+                return Route::Pop;
+            }
+            _ => return Route::Stay,
+        }
+
+        draw_picker(&picker, disp);
+        Route::Stay
+    }
+
+    /// Holding B cancels the session.
+    pub async fn long_press<D>(btn: Button, disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        if btn != Button::B || COMPLETED.load(Ordering::Relaxed) {
+            return;
+        }
+
+        COMPLETED.store(true, Ordering::Relaxed);
+        send(SessionAction::Cancel).await;
+        draw_state(
+            disp,
+            ELAPSED.load(Ordering::Relaxed),
+            PAUSED.load(Ordering::Relaxed),
+            true,
+        );
+    }
+
+    async fn send(action: SessionAction) {
+        let mut pending = SESSION_ACTION.lock().await;
+        *pending = match (pending.take(), action) {
+            // pausing and resuming before the first one went out
+            (Some(SessionAction::Pause), SessionAction::Pause) => None,
+            (_, action) => Some(action),
         };
+        RUN.signal(true);
+    }
 
-        if elapsed == 60 {
-            FLASH.store(false, core::sync::atomic::Ordering::Relaxed);
-            if TRIGGERED.load(core::sync::atomic::Ordering::Relaxed) {
-                TICKETS.store(
-                    TICKETS.load(core::sync::atomic::Ordering::Relaxed) + 1,
-                    core::sync::atomic::Ordering::Relaxed,
+    /// Drawn from `GOAL` once the picker is popped, before the next poll.
+    async fn start(goal: String<32>) {
+        ELAPSED.store(0, Ordering::Relaxed);
+        PAUSED.store(false, Ordering::Relaxed);
+        COMPLETED.store(false, Ordering::Relaxed);
+        send(SessionAction::Start(goal.clone())).await;
+        *GOAL.lock().await = Some(goal);
+    }
+
+    fn draw_picker<D>(picker: &Goals, disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        draw_rect!(Point::new(0, 14), Size::new(160, 114), BACKGROUND, disp);
+        write_text!(
+            "What are you working on?",
+            Point::new(80, 20),
+            CENTERED_TEXT,
+            disp
+        );
+
+        if !picker.loaded {
+            write_text!("Loading goals...", Point::new(80, 60), CENTERED_TEXT, disp);
+        } else if picker.goals.is_empty() {
+            write_text!("No goals found", Point::new(80, 60), CENTERED_TEXT, disp);
+        }
+
+        let first = picker.selected.saturating_sub(PICKER_ROWS - 1);
+        let rows = picker
+            .goals
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(PICKER_ROWS);
+        for (row, (index, goal)) in rows.enumerate() {
+            let y = 30 + row as i32 * 14;
+            if index == picker.selected {
+                draw_rounded_rect!(
+                    Point::new(8, y),
+                    Size::new(144, 12),
+                    Size::new(2, 2),
+                    PROGRESS_BG,
+                    disp
                 );
-                TRIGGERED.store(false, core::sync::atomic::Ordering::Relaxed);
+            }
+            write_text!(goal, Point::new(12, y + 3), disp);
+        }
+
+        write_text!(
+            "A: start, B: back",
+            Point::new(80, 119),
+            CENTERED_TEXT,
+            disp
+        );
+    }
+
+    pub async fn update<D>(disp: &mut D, data: RequestData, now: NaiveDateTime)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let (elapsed, goal, paused, completed) = match data {
+            RequestData::Session(elapsed, goal, paused, completed) => {
+                (elapsed, goal, paused, completed)
+            }
+            // a fetch from before the picker was closed
+            RequestData::Goals(_) => return,
+            _ => {
+                error!("[GUI] [Session] Recieved incorrect data!");
+                return;
+            }
+        };
+
+        ELAPSED.store(elapsed, Ordering::Relaxed);
+        PAUSED.store(paused, Ordering::Relaxed);
+        COMPLETED.store(completed, Ordering::Relaxed);
+
+        if elapsed == 60 {
+            if TRIGGERED.load(Ordering::Relaxed) {
+                TICKETS.store(TICKETS.load(Ordering::Relaxed) + 1, Ordering::Relaxed);
+                TRIGGERED.store(false, Ordering::Relaxed);
             }
         } else {
-            TRIGGERED.store(true, core::sync::atomic::Ordering::Relaxed);
+            TRIGGERED.store(true, Ordering::Relaxed);
         }
 
-        draw_rect!(Point::new(64, 40), Size::new(32, 10), BACKGROUND, disp);
-        write_large_text!(&display, Point::new(80, 40), CENTERED_TEXT, disp);
+        draw(disp, elapsed, goal, paused, completed).await;
+        *GOAL.lock().await = Some(String::from_str(goal).unwrap_or_default());
+    }
+
+    async fn draw<D>(disp: &mut D, elapsed: u8, goal: &str, paused: bool, completed: bool)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        draw_tga!(PROGRESS_BAR, Point::new(20, 53), disp);
+
+        draw_state(disp, elapsed, paused, completed);
 
         write_text!("Ticket No. ", Point::new(2, 118), disp);
         let tickets = format!(
             3,
             "{}",
-            1 + TICKETS.load(core::sync::atomic::Ordering::Relaxed) - TICKET_OFFSET
+            1 + earned(TICKETS.load(Ordering::Relaxed), &season().await)
         );
         write_large_text!(&tickets, Point::new(46, 114), disp);
 
-        draw_rect!(Point::new(134, 118), Size::new(32, 8), BACKGROUND, disp);
-        if paused {
-            write_text!("Paused", Point::new(134, 118), disp);
-        } else if elapsed < 60 {
-            write_text!("Ongoing", Point::new(130, 118), disp);
-        } else {
-            write_text!("Finished", Point::new(126, 118), disp);
-        }
-
         draw_rounded_rect!(
             Point::new(20, 53),
             Size::new((120. * (elapsed as f32 / 60.)) as u32, 6),
@@ -676,6 +1682,1636 @@ pub mod session {
             disp
         );
 
+        draw_rect!(Point::new(0, 62), Size::new(160, 6), BACKGROUND, disp);
         write_text!(goal, Point::new(80, 62), CENTERED_TEXT, disp);
     }
+
+    /// The time left, status and controls, which change without a poll.
+    fn draw_state<D>(disp: &mut D, elapsed: u8, paused: bool, completed: bool)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let display = match elapsed {
+            0 => String::<4>::from_str("1:00").unwrap(),
+            60 => String::<4>::from_str("DONE").unwrap(),
+            _ => format!(4, "0:{:02}", 60 - elapsed),
+        };
+
+        let running = !paused && !completed && elapsed < 60;
+        FLASH.store(running, Ordering::Relaxed);
+
+        draw_rect!(Point::new(64, 40), Size::new(32, 10), BACKGROUND, disp);
+        write_large_text!(&display, Point::new(80, 40), CENTERED_TEXT, disp);
+
+        draw_rect!(Point::new(120, 118), Size::new(40, 8), BACKGROUND, disp);
+        if elapsed >= 60 {
+            write_text!("Finished", Point::new(126, 118), disp);
+        } else if completed {
+            write_text!("Cancelled", Point::new(122, 118), disp);
+        } else if paused {
+            write_text!("Paused", Point::new(134, 118), disp);
+        } else {
+            write_text!("Ongoing", Point::new(130, 118), disp);
+        }
+
+        let hint = if completed || elapsed >= 60 {
+            "A: start a new session"
+        } else if paused {
+            "A: resume, hold B: cancel"
+        } else {
+            "A: pause, hold B: cancel"
+        };
+        draw_rect!(Point::new(0, 90), Size::new(160, 6), BACKGROUND, disp);
+        write_text!(hint, Point::new(80, 90), CENTERED_TEXT, disp);
+    }
+}
+
+pub mod leaderboard {
+    use core::fmt::Debug;
+    use core::sync::atomic::{AtomicBool, AtomicU16, AtomicU8, Ordering};
+
+    use chrono::NaiveDateTime;
+
+    use embedded_graphics::{
+        draw_target::DrawTarget,
+        geometry::{Point, Size},
+        image::Image,
+        pixelcolor::Rgb565,
+        primitives::Primitive,
+        Drawable,
+    };
+    use heapless::String;
+    use log::error;
+    use sprig_arcade_core::api::{
+        LeaderboardData, RequestData, RequestType, LEADERBOARD_PAGE, LEADERBOARD_PAGES,
+    };
+    use tinytga::Tga;
+
+    use super::{Route, Screen, BACKGROUND, CENTERED_TEXT, PROGRESS_ORANGE};
+    use crate::util::{REQUEST_TYPE, RUN, TICKET_SMALL};
+    use crate::{
+        apply_update_interval, draw_rect, draw_rounded_rect, draw_tga, format, write_text, Button,
+        CONFIG,
+    };
+
+    const ROW_HEIGHT: i32 = 13;
+    const TOP: i32 = 27;
+    /// Longest name that fits between the rank and the ticket count.
+    const NAME_LEN: usize = 22;
+
+    static PAGE: AtomicU8 = AtomicU8::new(0);
+    /// Whether the last page was full, so there might be another one.
+    static MORE: AtomicBool = AtomicBool::new(false);
+    /// The user's rank, 0 until the first page has loaded.
+    static RANK: AtomicU16 = AtomicU16::new(0);
+
+    pub async fn init() {
+        PAGE.store(0, Ordering::Relaxed);
+        MORE.store(false, Ordering::Relaxed);
+        RANK.store(0, Ordering::Relaxed);
+        apply_update_interval(&*CONFIG.lock().await);
+    }
+
+    /// The top of the leaderboard and where you are on it.
+    pub struct Leaderboard;
+
+    impl Screen for Leaderboard {
+        const REQUEST: RequestType = RequestType::Leaderboard(0);
+
+        async fn init<D>(&self, disp: &mut D)
+        where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            init().await;
+            draw_loading(disp, 0);
+        }
+
+        async fn input<D>(&self, btn: Button, disp: &mut D) -> Route
+        where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            input(btn, disp).await;
+            Route::Stay
+        }
+
+        async fn on_data<D>(
+            &self,
+            disp: &mut D,
+            data: RequestData,
+            _old_count: u16,
+            _now: NaiveDateTime,
+        ) where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            update(disp, data).await;
+        }
+    }
+
+    /// Up and down turn the page, A jumps to the page the user is on.
+    pub async fn input<D>(btn: Button, disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let page = PAGE.load(Ordering::Relaxed);
+        let next = match btn {
+            Button::Up if page > 0 => page - 1,
+            Button::Down if MORE.load(Ordering::Relaxed) && page + 1 < LEADERBOARD_PAGES => {
+                page + 1
+            }
+            Button::A => match RANK.load(Ordering::Relaxed) {
+                0 => return,
+                rank => {
+                    ((rank - 1) / LEADERBOARD_PAGE as u16).min(LEADERBOARD_PAGES as u16 - 1) as u8
+                }
+            },
+            _ => return,
+        };
+        if next == page {
+            return;
+        }
+
+        PAGE.store(next, Ordering::Relaxed);
+        *(REQUEST_TYPE.lock().await) = RequestType::Leaderboard(next);
+        draw_loading(disp, next);
+        RUN.signal(true);
+    }
+
+    /// Clears the list while a page is being fetched.
+    pub fn draw_loading<D>(disp: &mut D, page: u8)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        draw_rect!(Point::new(0, 14), Size::new(160, 114), BACKGROUND, disp);
+        draw_header(disp, page);
+        write_text!("Loading...", Point::new(80, 60), CENTERED_TEXT, disp);
+    }
+
+    fn draw_header<D>(disp: &mut D, page: u8)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        write_text!("Leaderboard", Point::new(8, 17), disp);
+        let label = format!(8, "Page {}", page + 1);
+        write_text!(&label, Point::new(152 - (label.len() * 4) as i32, 17), disp);
+    }
+
+    pub async fn update<D>(disp: &mut D, data: RequestData)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let RequestData::Leaderboard(page, data) = data else {
+            error!("[GUI] [Leaderboard] Recieved incorrect data!");
+            return;
+        };
+
+        // a page that was turned away from before it arrived
+        if page != PAGE.load(Ordering::Relaxed) {
+            return;
+        }
+
+        RANK.store(data.rank, Ordering::Relaxed);
+        MORE.store(data.users.len() == LEADERBOARD_PAGE, Ordering::Relaxed);
+
+        draw(disp, page, &data);
+    }
+
+    fn draw<D>(disp: &mut D, page: u8, data: &LeaderboardData)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        draw_rect!(Point::new(0, 14), Size::new(160, 114), BACKGROUND, disp);
+        draw_header(disp, page);
+
+        if data.users.is_empty() {
+            write_text!("Nobody here yet", Point::new(80, 60), CENTERED_TEXT, disp);
+        }
+
+        let ticket = Tga::from_slice(TICKET_SMALL).unwrap();
+        let first = page as usize * LEADERBOARD_PAGE + 1;
+        for (row, user) in data.users.iter().enumerate() {
+            let y = TOP + row as i32 * ROW_HEIGHT;
+            let rank = first + row;
+
+            if rank == data.rank as usize {
+                draw_rounded_rect!(
+                    Point::new(4, y),
+                    Size::new(152, 11),
+                    Size::new(2, 2),
+                    PROGRESS_ORANGE,
+                    disp
+                );
+            }
+
+            let name: String<NAME_LEN> = user.name.chars().take(NAME_LEN).collect();
+            let sessions = format!(5, "{}", user.sessions);
+
+            write_text!(&format!(4, "{}", rank), Point::new(8, y + 3), disp);
+            write_text!(&name, Point::new(24, y + 3), disp);
+            write_text!(
+                &sessions,
+                Point::new(142 - (sessions.len() * 4) as i32, y + 3),
+                disp
+            );
+            draw_tga!(tga, ticket, Point::new(144, y + 2), disp);
+        }
+
+        let you = format!(32, "You are #{} with {}", data.rank, data.sessions);
+        write_text!(&you, Point::new(8, 107), disp);
+        draw_tga!(
+            tga,
+            ticket,
+            Point::new(10 + (you.len() * 4) as i32, 106),
+            disp
+        );
+
+        write_text!(
+            "Up/Down: page, A: find me",
+            Point::new(80, 119),
+            CENTERED_TEXT,
+            disp
+        );
+    }
+}
+
+pub mod projects {
+    use core::fmt::Debug;
+    use core::str::FromStr;
+    use core::sync::atomic::{AtomicU8, Ordering};
+
+    use chrono::NaiveDateTime;
+
+    use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
+    use embedded_graphics::{
+        draw_target::DrawTarget,
+        geometry::{Point, Size},
+        pixelcolor::Rgb565,
+        primitives::Primitive,
+        Drawable,
+    };
+    use heapless::{String, Vec};
+    use log::error;
+    use sprig_arcade_core::api::{RequestData, RequestType, MAX_PROJECTS};
+
+    use super::{
+        Route, Screen, ScreenId, BACKGROUND, BLACK_FILL, CENTERED_TEXT, PROGRESS_BG, PROGRESS_BLUE,
+    };
+    use crate::{
+        apply_update_interval, draw_rect, draw_rounded_rect, format, write_large_text, write_text,
+        Button, CONFIG,
+    };
+
+    const ROW_HEIGHT: i32 = 19;
+    const VISIBLE_ROWS: usize = 5;
+    const TOP: i32 = 17;
+
+    static SELECTED: AtomicU8 = AtomicU8::new(0);
+    static SCROLL: AtomicU8 = AtomicU8::new(0);
+
+    struct Project {
+        name: String<32>,
+        minutes: u32,
+        sessions: u32,
+    }
+
+    /// The last projects fetched, `None` until the first fetch arrives.
+    static PROJECTS: Mutex<CriticalSectionRawMutex, Option<Vec<Project, MAX_PROJECTS>>> =
+        Mutex::new(None);
+
+    pub async fn init() {
+        SELECTED.store(0, Ordering::Relaxed);
+        SCROLL.store(0, Ordering::Relaxed);
+        *PROJECTS.lock().await = None;
+    }
+
+    /// Hours and sessions on each project.
+    pub struct Projects;
+
+    impl Screen for Projects {
+        const REQUEST: RequestType = RequestType::Projects;
+
+        async fn init<D>(&self, disp: &mut D)
+        where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            apply_update_interval(&*CONFIG.lock().await);
+            draw(disp).await;
+        }
+
+        async fn input<D>(&self, btn: Button, disp: &mut D) -> Route
+        where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            input(btn, disp).await
+        }
+
+        async fn on_data<D>(
+            &self,
+            disp: &mut D,
+            data: RequestData,
+            _old_count: u16,
+            _now: NaiveDateTime,
+        ) where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            update(data).await;
+            draw(disp).await;
+        }
+
+        async fn teardown(&self) {
+            init().await;
+        }
+    }
+
+    /// The selected project, pushed over the list.
+    pub struct Detail;
+
+    impl Screen for Detail {
+        const REQUEST: RequestType = RequestType::Projects;
+
+        async fn init<D>(&self, disp: &mut D)
+        where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            draw_selected(disp).await;
+        }
+
+        async fn on_data<D>(
+            &self,
+            disp: &mut D,
+            data: RequestData,
+            _old_count: u16,
+            _now: NaiveDateTime,
+        ) where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            update(data).await;
+            draw_selected(disp).await;
+        }
+    }
+
+    /// Up and down pick a project and A opens it.
+    pub async fn input<D>(btn: Button, disp: &mut D) -> Route
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let len = match PROJECTS.lock().await.as_ref() {
+            Some(projects) if !projects.is_empty() => projects.len(),
+            _ => return Route::Stay,
+        };
+        let selected = SELECTED.load(Ordering::Relaxed) as usize;
+
+        match btn {
+            Button::Up => select(selected.saturating_sub(1)),
+            Button::Down => select((selected + 1).min(len - 1)),
+            Button::A => return Route::Push(ScreenId::ProjectDetail),
+            _ => return Route::Stay,
+        }
+
+        draw(disp).await;
+        Route::Stay
+    }
+
+    fn select(index: usize) {
+        SELECTED.store(index as u8, Ordering::Relaxed);
+
+        let scroll = SCROLL.load(Ordering::Relaxed) as usize;
+        if index < scroll {
+            SCROLL.store(index as u8, Ordering::Relaxed);
+        } else if index >= scroll + VISIBLE_ROWS {
+            SCROLL.store((index + 1 - VISIBLE_ROWS) as u8, Ordering::Relaxed);
+        }
+    }
+
+    pub async fn update(data: RequestData) {
+        let RequestData::Projects(data) = data else {
+            error!("[GUI] [Projects] Recieved incorrect data!");
+            return;
+        };
+
+        let projects: Vec<Project, MAX_PROJECTS> = data
+            .iter()
+            .map(|project| Project {
+                name: String::from_str(project.name).unwrap_or_default(),
+                minutes: project.minutes,
+                sessions: project.sessions,
+            })
+            .collect();
+
+        // the list can shrink between fetches
+        if SELECTED.load(Ordering::Relaxed) as usize >= projects.len() {
+            SCROLL.store(0, Ordering::Relaxed);
+            select(projects.len().saturating_sub(1));
+        }
+
+        *PROJECTS.lock().await = Some(projects);
+    }
+
+    pub async fn draw<D>(disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let projects = PROJECTS.lock().await;
+        if let Some(projects) = clear(disp, projects.as_deref()) {
+            draw_list(disp, projects, SELECTED.load(Ordering::Relaxed) as usize);
+        }
+    }
+
+    pub async fn draw_selected<D>(disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let projects = PROJECTS.lock().await;
+        if let Some(projects) = clear(disp, projects.as_deref()) {
+            let total = projects.iter().map(|project| project.minutes).sum();
+            let selected = SELECTED.load(Ordering::Relaxed) as usize;
+            draw_detail(disp, &projects[selected], total);
+        }
+    }
+
+    /// Clears the screen, and hands back the projects if there are any to draw.
+    fn clear<'a, D>(disp: &mut D, projects: Option<&'a [Project]>) -> Option<&'a [Project]>
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        draw_rect!(Point::new(0, 14), Size::new(160, 114), BACKGROUND, disp);
+
+        match projects {
+            None => {
+                write_text!(
+                    "Loading projects...",
+                    Point::new(80, 60),
+                    CENTERED_TEXT,
+                    disp
+                );
+            }
+            Some([]) => {
+                write_text!("No projects yet", Point::new(80, 60), CENTERED_TEXT, disp);
+            }
+            Some(projects) => return Some(projects),
+        }
+        None
+    }
+
+    fn draw_list<D>(disp: &mut D, projects: &[Project], selected: usize)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let scroll = SCROLL.load(Ordering::Relaxed) as usize;
+        let rows = projects.iter().enumerate().skip(scroll).take(VISIBLE_ROWS);
+        for (row, (index, project)) in rows.enumerate() {
+            let y = TOP + row as i32 * ROW_HEIGHT;
+
+            if index == selected {
+                draw_rounded_rect!(
+                    Point::new(4, y),
+                    Size::new(146, 15),
+                    Size::new(2, 2),
+                    PROGRESS_BG,
+                    disp
+                );
+            }
+
+            let details = format!(
+                32,
+                "{}h {:02}m, {} sessions",
+                project.minutes / 60,
+                project.minutes % 60,
+                project.sessions
+            );
+            write_text!(&project.name, Point::new(8, y + 2), disp);
+            write_text!(&details, Point::new(8, y + 9), disp);
+        }
+
+        // scroll bar
+        if projects.len() > VISIBLE_ROWS {
+            let track = VISIBLE_ROWS as i32 * ROW_HEIGHT - 4;
+            let count = projects.len() as i32;
+            draw_rect!(
+                Point::new(154, TOP),
+                Size::new(2, track as u32),
+                PROGRESS_BG,
+                disp
+            );
+            draw_rect!(
+                Point::new(154, TOP + track * scroll as i32 / count),
+                Size::new(2, (track * VISIBLE_ROWS as i32 / count) as u32),
+                PROGRESS_BLUE,
+                disp
+            );
+        }
+
+        write_text!(
+            "Up/Down: choose, A: open",
+            Point::new(80, 119),
+            CENTERED_TEXT,
+            disp
+        );
+    }
+
+    fn draw_detail<D>(disp: &mut D, project: &Project, total: u32)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        write_text!(&project.name, Point::new(80, 20), CENTERED_TEXT, disp);
+
+        let time = format!(8, "{}:{:02}", project.minutes / 60, project.minutes % 60);
+        write_large_text!(&time, Point::new(80, 32), CENTERED_TEXT, disp);
+        write_text!("hours logged", Point::new(80, 45), CENTERED_TEXT, disp);
+
+        // how much of all the time logged went into this project
+        let share = match total {
+            0 => 0.,
+            total => project.minutes as f32 / total as f32,
+        };
+        draw_rounded_rect!(
+            Point::new(20, 58),
+            Size::new(120, 6),
+            Size::new(2, 2),
+            PROGRESS_BG,
+            disp
+        );
+        draw_rounded_rect!(
+            Point::new(20, 58),
+            Size::new((120. * share) as u32, 6),
+            Size::new(2, 2),
+            BLACK_FILL,
+            disp
+        );
+        let percent = format!(24, "{}% of your hours", (share * 100. + 0.5) as u32);
+        write_text!(&percent, Point::new(80, 68), CENTERED_TEXT, disp);
+
+        let sessions = format!(24, "{} sessions", project.sessions);
+        write_text!(&sessions, Point::new(80, 84), CENTERED_TEXT, disp);
+        if let Some(average) = project.minutes.checked_div(project.sessions) {
+            let average = format!(32, "{} min per session", average);
+            write_text!(&average, Point::new(80, 93), CENTERED_TEXT, disp);
+        }
+
+        write_text!("B: back", Point::new(80, 119), CENTERED_TEXT, disp);
+    }
+}
+
+pub mod shop {
+    use core::fmt::Debug;
+    use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+    use chrono::NaiveDateTime;
+
+    use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
+    use embedded_graphics::{
+        draw_target::DrawTarget,
+        geometry::{Point, Size},
+        image::Image,
+        pixelcolor::Rgb565,
+        primitives::Primitive,
+        Drawable,
+    };
+    use heapless::{String, Vec};
+    use log::error;
+    use sprig_arcade_core::api::{RequestData, RequestType, SHOP_PAGE};
+    use sprig_arcade_core::errors::{ErrorKind, Source};
+    use sprig_arcade_core::shop::{toggle, update_prices, wishlist_name, wishlisted};
+    use tinytga::Tga;
+
+    use super::{Route, Screen, BACKGROUND, CENTERED_TEXT, PROGRESS_BG};
+    use crate::util::{Events, REQUEST_TYPE, RUN, TICKET_SMALL};
+    use crate::{
+        apply_update_interval, draw_rect, draw_rounded_rect, draw_tga, format, report, write_text,
+        Button, CONFIG, EVENTS,
+    };
+
+    const ROW_HEIGHT: i32 = 13;
+    const TOP: i32 = 27;
+    /// Longest name that fits between the star and the price.
+    const NAME_LEN: usize = 24;
+
+    static PAGE: AtomicU8 = AtomicU8::new(0);
+    static SELECTED: AtomicU8 = AtomicU8::new(0);
+    /// Whether the last page was full, so there might be another one.
+    static MORE: AtomicBool = AtomicBool::new(false);
+
+    struct Item {
+        name: String<32>,
+        tickets: u16,
+    }
+
+    /// The page on screen, `None` while it is being fetched.
+    static ITEMS: Mutex<CriticalSectionRawMutex, Option<Vec<Item, SHOP_PAGE>>> = Mutex::new(None);
+
+    pub async fn init() {
+        PAGE.store(0, Ordering::Relaxed);
+        SELECTED.store(0, Ordering::Relaxed);
+        MORE.store(false, Ordering::Relaxed);
+        *ITEMS.lock().await = None;
+        apply_update_interval(&*CONFIG.lock().await);
+    }
+
+    /// What the shop has, a page at a time.
+    pub struct Shop;
+
+    impl Screen for Shop {
+        const REQUEST: RequestType = RequestType::Shop(0);
+
+        async fn init<D>(&self, disp: &mut D)
+        where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            init().await;
+            draw(disp).await;
+        }
+
+        async fn input<D>(&self, btn: Button, disp: &mut D) -> Route
+        where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            input(btn, disp).await;
+            Route::Stay
+        }
+
+        async fn on_data<D>(
+            &self,
+            disp: &mut D,
+            data: RequestData,
+            _old_count: u16,
+            _now: NaiveDateTime,
+        ) where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            update(disp, data).await;
+        }
+    }
+
+    /// Up and down pick an item, turning the page at either end, and A stars
+    /// or unstars it.
+    pub async fn input<D>(btn: Button, disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let page = PAGE.load(Ordering::Relaxed);
+        let selected = SELECTED.load(Ordering::Relaxed) as usize;
+        let guard = ITEMS.lock().await;
+        let Some(items) = guard.as_ref() else {
+            return;
+        };
+
+        match btn {
+            Button::Up if selected > 0 => SELECTED.store(selected as u8 - 1, Ordering::Relaxed),
+            Button::Up if page > 0 => {
+                drop(guard);
+                return turn(page - 1, SHOP_PAGE - 1, disp).await;
+            }
+            Button::Down if selected + 1 < items.len() => {
+                SELECTED.store(selected as u8 + 1, Ordering::Relaxed)
+            }
+            Button::Down if MORE.load(Ordering::Relaxed) => {
+                drop(guard);
+                return turn(page + 1, 0, disp).await;
+            }
+            Button::A => {
+                let Some(item) = items.get(selected) else {
+                    return;
+                };
+                let mut config = CONFIG.lock().await;
+                if toggle(&mut config.wishlist, &item.name, item.tickets).is_err() {
+                    report!(Source::Gui, ErrorKind::Unexpected, "The wishlist is full");
+                    return;
+                }
+                drop(config);
+                if EVENTS.try_send(Events::SaveConfig).is_err() {
+                    report!(
+                        Source::Gui,
+                        ErrorKind::Unexpected,
+                        "Event queue is full, wishlist wasn't saved"
+                    );
+                }
+            }
+            _ => return,
+        }
+
+        drop(guard);
+        draw(disp).await;
+    }
+
+    async fn turn<D>(page: u8, selected: usize, disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        PAGE.store(page, Ordering::Relaxed);
+        SELECTED.store(selected as u8, Ordering::Relaxed);
+        *ITEMS.lock().await = None;
+        *(REQUEST_TYPE.lock().await) = RequestType::Shop(page);
+        draw(disp).await;
+        RUN.signal(true);
+    }
+
+    pub async fn update<D>(disp: &mut D, data: RequestData)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let RequestData::Shop(page, data) = data else {
+            error!("[GUI] [Shop] Recieved incorrect data!");
+            return;
+        };
+
+        // a page that was turned away from before it arrived
+        if page != PAGE.load(Ordering::Relaxed) {
+            return;
+        }
+
+        // keep the wishlist's prices up to date
+        let mut config = CONFIG.lock().await;
+        let prices = data.iter().map(|item| (item.name, item.tickets));
+        if update_prices(&mut config.wishlist, prices)
+            && EVENTS.try_send(Events::SaveConfig).is_err()
+        {
+            report!(
+                Source::Gui,
+                ErrorKind::Unexpected,
+                "Event queue is full, wishlist wasn't saved"
+            );
+        }
+        drop(config);
+
+        let items: Vec<Item, SHOP_PAGE> = data
+            .iter()
+            .map(|item| Item {
+                name: wishlist_name(item.name),
+                tickets: item.tickets,
+            })
+            .collect();
+
+        let selected = SELECTED.load(Ordering::Relaxed) as usize;
+        SELECTED.store(
+            selected.min(items.len().saturating_sub(1)) as u8,
+            Ordering::Relaxed,
+        );
+        MORE.store(items.len() == SHOP_PAGE, Ordering::Relaxed);
+        *ITEMS.lock().await = Some(items);
+
+        draw(disp).await;
+    }
+
+    pub async fn draw<D>(disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let page = PAGE.load(Ordering::Relaxed);
+        let selected = SELECTED.load(Ordering::Relaxed) as usize;
+
+        draw_rect!(Point::new(0, 14), Size::new(160, 114), BACKGROUND, disp);
+        write_text!("Shop", Point::new(8, 17), disp);
+        let label = format!(8, "Page {}", page + 1);
+        write_text!(&label, Point::new(152 - (label.len() * 4) as i32, 17), disp);
+
+        let items = ITEMS.lock().await;
+        let Some(items) = items.as_ref() else {
+            write_text!("Loading...", Point::new(80, 60), CENTERED_TEXT, disp);
+            return;
+        };
+        if items.is_empty() {
+            write_text!("Nothing for sale", Point::new(80, 60), CENTERED_TEXT, disp);
+            return;
+        }
+
+        let config = CONFIG.lock().await;
+        let ticket = Tga::from_slice(TICKET_SMALL).unwrap();
+        for (row, item) in items.iter().enumerate() {
+            let y = TOP + row as i32 * ROW_HEIGHT;
+
+            if row == selected {
+                draw_rounded_rect!(
+                    Point::new(4, y),
+                    Size::new(152, 11),
+                    Size::new(2, 2),
+                    PROGRESS_BG,
+                    disp
+                );
+            }
+            if wishlisted(&config.wishlist, &item.name) {
+                write_text!("*", Point::new(8, y + 3), disp);
+            }
+
+            let name: String<NAME_LEN> = item.name.chars().take(NAME_LEN).collect();
+            let price = format!(5, "{}", item.tickets);
+            write_text!(&name, Point::new(16, y + 3), disp);
+            write_text!(
+                &price,
+                Point::new(142 - (price.len() * 4) as i32, y + 3),
+                disp
+            );
+            draw_tga!(tga, ticket, Point::new(144, y + 2), disp);
+        }
+
+        let hint = match items.get(selected) {
+            Some(item) if wishlisted(&config.wishlist, &item.name) => "A: remove from wishlist",
+            _ => "A: add to wishlist",
+        };
+        write_text!(hint, Point::new(80, 119), CENTERED_TEXT, disp);
+    }
+}
+
+pub mod wishlist {
+    use core::fmt::Debug;
+    use core::sync::atomic::{AtomicU8, Ordering};
+
+    use chrono::NaiveDateTime;
+    use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
+    use embedded_graphics::{
+        draw_target::DrawTarget,
+        geometry::{Point, Size},
+        pixelcolor::Rgb565,
+        primitives::Primitive,
+        Drawable,
+    };
+    use heapless::String;
+    use log::error;
+    use sprig_arcade_core::api::RequestData;
+    use sprig_arcade_core::errors::{ErrorKind, Source};
+    use sprig_arcade_core::stats::{average_per_day, days_until};
+
+    use super::{
+        home::season, Route, Screen, BACKGROUND, CENTERED_TEXT, PROGRESS_BG, PROGRESS_BLUE,
+    };
+    use crate::util::Events;
+    use crate::{
+        apply_update_interval, draw_rect, draw_rounded_rect, format, report, write_text, Button,
+        CONFIG, EVENTS, TICKETS,
+    };
+
+    const ROW_HEIGHT: i32 = 24;
+    const VISIBLE_ROWS: usize = 4;
+    const TOP: i32 = 17;
+    const BAR_WIDTH: u32 = 92;
+
+    static SELECTED: AtomicU8 = AtomicU8::new(0);
+    static SCROLL: AtomicU8 = AtomicU8::new(0);
+    /// When the ticket count was last fetched, the ETAs are left out until then.
+    static NOW: Mutex<CriticalSectionRawMutex, Option<NaiveDateTime>> = Mutex::new(None);
+
+    pub async fn init() {
+        SELECTED.store(0, Ordering::Relaxed);
+        SCROLL.store(0, Ordering::Relaxed);
+        apply_update_interval(&*CONFIG.lock().await);
+    }
+
+    /// The items being saved up for.
+    pub struct Wishlist;
+
+    impl Screen for Wishlist {
+        async fn init<D>(&self, disp: &mut D)
+        where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            init().await;
+            draw(disp).await;
+        }
+
+        async fn input<D>(&self, btn: Button, disp: &mut D) -> Route
+        where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            input(btn, disp).await;
+            Route::Stay
+        }
+
+        async fn on_data<D>(
+            &self,
+            disp: &mut D,
+            data: RequestData,
+            _old_count: u16,
+            now: NaiveDateTime,
+        ) where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            update(disp, data, now).await;
+        }
+    }
+
+    /// Up and down pick an item and A takes it off the wishlist.
+    pub async fn input<D>(btn: Button, disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let selected = SELECTED.load(Ordering::Relaxed) as usize;
+        let mut config = CONFIG.lock().await;
+        let len = config.wishlist.len();
+        if len == 0 {
+            return;
+        }
+
+        match btn {
+            Button::Up => select(selected.saturating_sub(1)),
+            Button::Down => select((selected + 1).min(len - 1)),
+            Button::A => {
+                config.wishlist.remove(selected);
+                if EVENTS.try_send(Events::SaveConfig).is_err() {
+                    report!(
+                        Source::Gui,
+                        ErrorKind::Unexpected,
+                        "Event queue is full, wishlist wasn't saved"
+                    );
+                }
+                SCROLL.store(0, Ordering::Relaxed);
+                select(selected.min(len.saturating_sub(2)));
+            }
+            _ => return,
+        }
+
+        drop(config);
+        draw(disp).await;
+    }
+
+    fn select(index: usize) {
+        SELECTED.store(index as u8, Ordering::Relaxed);
+
+        let scroll = SCROLL.load(Ordering::Relaxed) as usize;
+        if index < scroll {
+            SCROLL.store(index as u8, Ordering::Relaxed);
+        } else if index >= scroll + VISIBLE_ROWS {
+            SCROLL.store((index + 1 - VISIBLE_ROWS) as u8, Ordering::Relaxed);
+        }
+    }
+
+    /// Only needs the ticket count, which `App` has already stored.
+    pub async fn update<D>(disp: &mut D, data: RequestData, now: NaiveDateTime)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let RequestData::Stats(_) = data else {
+            error!("[GUI] [Wishlist] Recieved incorrect data!");
+            return;
+        };
+
+        *NOW.lock().await = Some(now);
+        draw(disp).await;
+    }
+
+    pub async fn draw<D>(disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let selected = SELECTED.load(Ordering::Relaxed) as usize;
+        let scroll = SCROLL.load(Ordering::Relaxed) as usize;
+        let tickets = TICKETS.load(Ordering::Relaxed);
+
+        // the same average as the Home screen's stats
+        let season = season().await;
+        let per_day = NOW
+            .lock()
+            .await
+            .map(|now| average_per_day(tickets, &season, &now));
+
+        draw_rect!(Point::new(0, 14), Size::new(160, 114), BACKGROUND, disp);
+
+        let config = CONFIG.lock().await;
+        if config.wishlist.is_empty() {
+            write_text!(
+                "Your wishlist is empty",
+                Point::new(80, 56),
+                CENTERED_TEXT,
+                disp
+            );
+            write_text!(
+                "Star items in the Shop",
+                Point::new(80, 66),
+                CENTERED_TEXT,
+                disp
+            );
+            return;
+        }
+
+        let rows = config
+            .wishlist
+            .iter()
+            .enumerate()
+            .skip(scroll)
+            .take(VISIBLE_ROWS);
+        for (row, (index, item)) in rows.enumerate() {
+            let y = TOP + row as i32 * ROW_HEIGHT;
+
+            let track = if index == selected {
+                draw_rounded_rect!(
+                    Point::new(4, y),
+                    Size::new(146, 20),
+                    Size::new(2, 2),
+                    PROGRESS_BG,
+                    disp
+                );
+                BACKGROUND
+            } else {
+                PROGRESS_BG
+            };
+
+            let count = format!(12, "{}/{}", tickets.min(item.tickets), item.tickets);
+            write_text!(&item.name, Point::new(8, y + 3), disp);
+            write_text!(
+                &count,
+                Point::new(146 - (count.len() * 4) as i32, y + 3),
+                disp
+            );
+
+            let progress = match item.tickets {
+                0 => 1.,
+                price => (tickets as f32 / price as f32).min(1.),
+            };
+            draw_rounded_rect!(
+                Point::new(8, y + 12),
+                Size::new(BAR_WIDTH, 5),
+                Size::new(2, 2),
+                track,
+                disp
+            );
+            draw_rounded_rect!(
+                Point::new(8, y + 12),
+                Size::new((BAR_WIDTH as f32 * progress) as u32, 5),
+                Size::new(2, 2),
+                PROGRESS_BLUE,
+                disp
+            );
+
+            let eta = match per_day.map(|per_day| days_until(item.tickets, tickets, per_day)) {
+                None => format!(12, ""),
+                Some(Some(0)) => format!(12, "Affordable!"),
+                Some(Some(1)) => format!(12, "1 day"),
+                Some(Some(days)) => format!(12, "{} days", days),
+                Some(None) => format!(12, "No ETA"),
+            };
+            write_text!(&eta, Point::new(146 - (eta.len() * 4) as i32, y + 12), disp);
+        }
+
+        // scroll bar
+        let count = config.wishlist.len() as i32;
+        if count > VISIBLE_ROWS as i32 {
+            let track = VISIBLE_ROWS as i32 * ROW_HEIGHT - 4;
+            draw_rect!(
+                Point::new(154, TOP),
+                Size::new(2, track as u32),
+                PROGRESS_BG,
+                disp
+            );
+            draw_rect!(
+                Point::new(154, TOP + track * scroll as i32 / count),
+                Size::new(2, (track * VISIBLE_ROWS as i32 / count) as u32),
+                PROGRESS_BLUE,
+                disp
+            );
+        }
+
+        write_text!(
+            "A: remove from wishlist",
+            Point::new(80, 119),
+            CENTERED_TEXT,
+            disp
+        );
+    }
+}
+
+pub mod errors {
+    use core::fmt::Debug;
+    use core::sync::atomic::{AtomicU8, Ordering};
+
+    use chrono::{Datelike, NaiveDateTime, Timelike};
+    use embedded_graphics::{
+        draw_target::DrawTarget,
+        geometry::{Point, Size},
+        pixelcolor::Rgb565,
+        primitives::Primitive,
+        Drawable,
+    };
+    use heapless::String;
+    use sprig_arcade_core::api::RequestData;
+    use sprig_arcade_core::errors::ErrorRecord;
+
+    use super::{Route, Screen, ScreenId, BACKGROUND, CENTERED_TEXT, PROGRESS_BG, PROGRESS_BLUE};
+    use crate::util::ERRORS;
+    use crate::{draw_rect, draw_rounded_rect, format, write_text, Button};
+
+    const ROW_HEIGHT: i32 = 13;
+    const VISIBLE_ROWS: usize = 7;
+    const TOP: i32 = 17;
+    /// Characters of the message that fit in a row of the list.
+    const ROW_LEN: usize = 21;
+    /// Characters that fit across the detail view.
+    const LINE_LEN: usize = 36;
+
+    static SELECTED: AtomicU8 = AtomicU8::new(0);
+    static SCROLL: AtomicU8 = AtomicU8::new(0);
+
+    pub fn init() {
+        SELECTED.store(0, Ordering::Relaxed);
+        SCROLL.store(0, Ordering::Relaxed);
+    }
+
+    /// Every error since the last clear.
+    pub struct Errors;
+
+    impl Screen for Errors {
+        async fn init<D>(&self, disp: &mut D)
+        where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            draw(disp);
+        }
+
+        async fn input<D>(&self, btn: Button, disp: &mut D) -> Route
+        where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            input(btn, disp)
+        }
+
+        async fn long_press<D>(&self, btn: Button, disp: &mut D)
+        where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            long_press(btn, disp);
+        }
+
+        async fn on_data<D>(
+            &self,
+            disp: &mut D,
+            _data: RequestData,
+            _old_count: u16,
+            _now: NaiveDateTime,
+        ) where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            draw(disp);
+        }
+
+        async fn teardown(&self) {
+            init();
+        }
+    }
+
+    /// The selected error in full, pushed over the list.
+    pub struct Detail;
+
+    impl Screen for Detail {
+        async fn init<D>(&self, disp: &mut D)
+        where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            draw_selected(disp);
+        }
+
+        async fn on_data<D>(
+            &self,
+            disp: &mut D,
+            _data: RequestData,
+            _old_count: u16,
+            _now: NaiveDateTime,
+        ) where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            draw_selected(disp);
+        }
+    }
+
+    /// Up and down pick an error and A opens it.
+    pub fn input<D>(btn: Button, disp: &mut D) -> Route
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let len = ERRORS.lock(|errors| errors.borrow().len());
+        if len == 0 {
+            return Route::Stay;
+        }
+        let selected = SELECTED.load(Ordering::Relaxed) as usize;
+
+        match btn {
+            Button::Up => select(selected.saturating_sub(1)),
+            Button::Down => select((selected + 1).min(len - 1)),
+            Button::A => return Route::Push(ScreenId::ErrorDetail),
+            _ => return Route::Stay,
+        }
+
+        draw(disp);
+        Route::Stay
+    }
+
+    /// Holding B clears every error.
+    pub fn long_press<D>(btn: Button, disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        if btn != Button::B {
+            return;
+        }
+
+        ERRORS.lock(|errors| errors.borrow_mut().clear());
+        init();
+        draw(disp);
+    }
+
+    fn select(index: usize) {
+        SELECTED.store(index as u8, Ordering::Relaxed);
+
+        let scroll = SCROLL.load(Ordering::Relaxed) as usize;
+        if index < scroll {
+            SCROLL.store(index as u8, Ordering::Relaxed);
+        } else if index >= scroll + VISIBLE_ROWS {
+            SCROLL.store((index + 1 - VISIBLE_ROWS) as u8, Ordering::Relaxed);
+        }
+    }
+
+    /// Also called after every fetch, as errors can arrive at any time.
+    pub fn draw<D>(disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        draw_rect!(Point::new(0, 14), Size::new(160, 114), BACKGROUND, disp);
+
+        ERRORS.lock(|errors| {
+            let errors = errors.borrow();
+            if errors.is_empty() {
+                write_text!("No errors", Point::new(80, 60), CENTERED_TEXT, disp);
+                return;
+            }
+
+            let selected = clamp_selected(errors.len());
+
+            let scroll = SCROLL.load(Ordering::Relaxed) as usize;
+            let rows = errors.iter().enumerate().skip(scroll).take(VISIBLE_ROWS);
+            for (row, (index, record)) in rows.enumerate() {
+                let y = TOP + row as i32 * ROW_HEIGHT;
+
+                if index == selected {
+                    draw_rounded_rect!(
+                        Point::new(4, y),
+                        Size::new(146, 11),
+                        Size::new(2, 2),
+                        PROGRESS_BG,
+                        disp
+                    );
+                }
+
+                let time = match record.time {
+                    Some(time) => format!(5, "{:02}:{:02}", time.hour(), time.minute()),
+                    None => format!(5, "--:--"),
+                };
+                let message: String<ROW_LEN> = record.message.chars().take(ROW_LEN).collect();
+                write_text!(&time, Point::new(8, y + 3), disp);
+                write_text!(record.source.label(), Point::new(32, y + 3), disp);
+                write_text!(&message, Point::new(60, y + 3), disp);
+            }
+
+            // scroll bar
+            let count = errors.len() as i32;
+            if count > VISIBLE_ROWS as i32 {
+                let track = VISIBLE_ROWS as i32 * ROW_HEIGHT - 2;
+                draw_rect!(
+                    Point::new(154, TOP),
+                    Size::new(2, track as u32),
+                    PROGRESS_BG,
+                    disp
+                );
+                draw_rect!(
+                    Point::new(154, TOP + track * scroll as i32 / count),
+                    Size::new(2, (track * VISIBLE_ROWS as i32 / count) as u32),
+                    PROGRESS_BLUE,
+                    disp
+                );
+            }
+
+            write_text!(
+                "A: details, hold B: clear",
+                Point::new(80, 119),
+                CENTERED_TEXT,
+                disp
+            );
+        });
+    }
+
+    /// New errors push the selection down the list.
+    fn clamp_selected(len: usize) -> usize {
+        let selected = (SELECTED.load(Ordering::Relaxed) as usize).min(len - 1);
+        SELECTED.store(selected as u8, Ordering::Relaxed);
+        selected
+    }
+
+    fn draw_selected<D>(disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        draw_rect!(Point::new(0, 14), Size::new(160, 114), BACKGROUND, disp);
+
+        ERRORS.lock(|errors| {
+            let errors = errors.borrow();
+            if errors.is_empty() {
+                write_text!("No errors", Point::new(80, 60), CENTERED_TEXT, disp);
+                return;
+            }
+            if let Some(record) = errors.get(clamp_selected(errors.len())) {
+                draw_detail(disp, record);
+            }
+        });
+    }
+
+    fn draw_detail<D>(disp: &mut D, record: &ErrorRecord)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let title = format!(24, "{} - {}", record.source.label(), record.kind.label());
+        write_text!(&title, Point::new(80, 20), CENTERED_TEXT, disp);
+
+        match record.time {
+            Some(time) => {
+                let time = format_time(&time);
+                write_text!(&time, Point::new(80, 30), CENTERED_TEXT, disp);
+            }
+            None => {
+                write_text!(
+                    "Before the clock was set",
+                    Point::new(80, 30),
+                    CENTERED_TEXT,
+                    disp
+                );
+            }
+        }
+
+        // wrap at spaces where possible
+        let mut rest = record.message.as_str();
+        let mut y = 46;
+        while !rest.is_empty() {
+            let end = match rest.char_indices().nth(LINE_LEN) {
+                None => rest.len(),
+                Some((end, _)) => rest[..end].rfind(' ').map_or(end, |space| space + 1),
+            };
+            write_text!(rest[..end].trim_end(), Point::new(8, y), disp);
+            rest = &rest[end..];
+            y += 9;
+        }
+
+        write_text!("B: back", Point::new(80, 119), CENTERED_TEXT, disp);
+    }
+
+    fn format_time(time: &NaiveDateTime) -> String<19> {
+        format!(
+            19,
+            "{}-{:02}-{:02} {:02}:{:02}:{:02}",
+            time.year(),
+            time.month(),
+            time.day(),
+            time.hour(),
+            time.minute(),
+            time.second()
+        )
+    }
+}
+
+pub mod settings {
+    use core::fmt::Debug;
+    use core::sync::atomic::{AtomicU8, Ordering};
+
+    use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
+    use embedded_graphics::{
+        draw_target::DrawTarget,
+        geometry::{Point, Size},
+        pixelcolor::Rgb565,
+        primitives::Primitive,
+        Drawable,
+    };
+    use sprig_arcade_core::config::Config;
+    use sprig_arcade_core::errors::{ErrorKind, Source};
+    use sprig_arcade_core::settings::{DateField, Setting};
+
+    use super::{
+        Route, Screen, BACKGROUND, BLACK_FILL, CENTERED_TEXT, PROGRESS_BG, PROGRESS_BLUE,
+        PROGRESS_ORANGE,
+    };
+    use crate::util::Events;
+    use crate::{draw_rect, draw_rounded_rect, report, write_text, Button, CONFIG, EVENTS};
+
+    const ROW_HEIGHT: i32 = 19;
+    const VISIBLE_ROWS: usize = 5;
+    const TOP: i32 = 17;
+
+    static SELECTED: AtomicU8 = AtomicU8::new(0);
+    static SCROLL: AtomicU8 = AtomicU8::new(0);
+
+    /// A copy of the config being edited, only written back when A is pressed.
+    struct Edit {
+        draft: Config,
+        field: DateField,
+    }
+
+    static EDIT: Mutex<CriticalSectionRawMutex, Option<Edit>> = Mutex::new(None);
+
+    pub async fn init() {
+        *EDIT.lock().await = None;
+    }
+
+    /// Everything kept in `CONFIG`.
+    pub struct Settings;
+
+    impl Screen for Settings {
+        async fn init<D>(&self, disp: &mut D)
+        where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            init().await;
+            draw(disp).await;
+        }
+
+        async fn grabs_input(&self) -> bool {
+            editing().await
+        }
+
+        async fn input<D>(&self, btn: Button, disp: &mut D) -> Route
+        where
+            D: DrawTarget<Color = Rgb565>,
+            D::Error: Debug,
+        {
+            input(btn, disp).await;
+            Route::Stay
+        }
+    }
+
+    pub async fn editing() -> bool {
+        EDIT.lock().await.is_some()
+    }
+
+    pub async fn input<D>(btn: Button, disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let selected = SELECTED.load(Ordering::Relaxed) as usize;
+        let setting = Setting::ALL[selected];
+
+        {
+            let mut edit = EDIT.lock().await;
+            match (edit.as_mut(), btn) {
+                (None, Button::Up) => select(selected.saturating_sub(1)),
+                (None, Button::Down) => select((selected + 1).min(Setting::ALL.len() - 1)),
+                (None, Button::A) => {
+                    *edit = Some(Edit {
+                        draft: CONFIG.lock().await.clone(),
+                        field: DateField::Year,
+                    });
+                }
+                (Some(e), Button::Up) => setting.adjust(&mut e.draft, 1, e.field),
+                (Some(e), Button::Down) => setting.adjust(&mut e.draft, -1, e.field),
+                (Some(e), Button::Left) => e.field = e.field.left(),
+                (Some(e), Button::Right) => e.field = e.field.right(),
+                (Some(_), Button::A) => {
+                    if let Some(e) = edit.take() {
+                        *CONFIG.lock().await = e.draft;
+                        if EVENTS.try_send(Events::SaveConfig).is_err() {
+                            report!(
+                                Source::Gui,
+                                ErrorKind::Unexpected,
+                                "Event queue is full, config wasn't saved"
+                            );
+                        }
+                    }
+                }
+                (Some(_), Button::B) => *edit = None,
+                _ => return,
+            }
+        }
+
+        draw(disp).await;
+    }
+
+    fn select(index: usize) {
+        SELECTED.store(index as u8, Ordering::Relaxed);
+
+        let scroll = SCROLL.load(Ordering::Relaxed) as usize;
+        if index < scroll {
+            SCROLL.store(index as u8, Ordering::Relaxed);
+        } else if index >= scroll + VISIBLE_ROWS {
+            SCROLL.store((index + 1 - VISIBLE_ROWS) as u8, Ordering::Relaxed);
+        }
+    }
+
+    pub async fn draw<D>(disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let selected = SELECTED.load(Ordering::Relaxed) as usize;
+        let scroll = SCROLL.load(Ordering::Relaxed) as usize;
+        let edit = EDIT.lock().await;
+        let config = CONFIG.lock().await;
+
+        draw_rect!(Point::new(0, 14), Size::new(160, 114), BACKGROUND, disp);
+
+        let rows = Setting::ALL
+            .iter()
+            .enumerate()
+            .skip(scroll)
+            .take(VISIBLE_ROWS);
+        for (row, (index, setting)) in rows.enumerate() {
+            let y = TOP + row as i32 * ROW_HEIGHT;
+
+            if index == selected {
+                let style = match *edit {
+                    Some(_) => PROGRESS_ORANGE,
+                    None => PROGRESS_BG,
+                };
+                draw_rounded_rect!(
+                    Point::new(4, y),
+                    Size::new(146, 15),
+                    Size::new(2, 2),
+                    style,
+                    disp
+                );
+            }
+
+            let value = match edit.as_ref() {
+                Some(e) if index == selected => setting.value(&e.draft),
+                _ => setting.value(&config),
+            };
+            let value_x = 146 - (value.len() * 4) as i32;
+
+            write_text!(setting.label(), Point::new(8, y + 5), disp);
+            write_text!(&value, Point::new(value_x, y + 5), disp);
+
+            // underline the part of the date being changed
+            if let (Some(e), Setting::StartDate | Setting::EndDate) = (edit.as_ref(), setting) {
+                if index == selected {
+                    let (start, len) = match e.field {
+                        DateField::Year => (0, 4),
+                        DateField::Month => (5, 2),
+                        DateField::Day => (8, 2),
+                    };
+                    draw_rect!(
+                        Point::new(value_x + start * 4, y + 12),
+                        Size::new(len * 4, 1),
+                        BLACK_FILL,
+                        disp
+                    );
+                }
+            }
+        }
+
+        // scroll bar
+        let track = VISIBLE_ROWS as i32 * ROW_HEIGHT - 4;
+        let count = Setting::ALL.len() as i32;
+        draw_rect!(
+            Point::new(154, TOP),
+            Size::new(2, track as u32),
+            PROGRESS_BG,
+            disp
+        );
+        draw_rect!(
+            Point::new(154, TOP + track * scroll as i32 / count),
+            Size::new(2, (track * VISIBLE_ROWS as i32 / count) as u32),
+            PROGRESS_BLUE,
+            disp
+        );
+
+        let hint = match *edit {
+            Some(_) => "Up/Down: change, A: save, B: cancel",
+            None => "Up/Down: choose, A: edit",
+        };
+        write_text!(hint, Point::new(80, 119), CENTERED_TEXT, disp);
+    }
 }
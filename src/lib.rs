@@ -0,0 +1,158 @@
+#![no_std]
+#![allow(async_fn_in_trait)]
+
+#[cfg(all(feature = "firmware", feature = "simulator"))]
+compile_error!("`firmware` and `simulator` can't be enabled together, use `cargo simulator`");
+
+use core::fmt::Debug;
+use core::sync::atomic::{AtomicU16, Ordering};
+
+use chrono::NaiveDateTime;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::Channel;
+use embassy_sync::mutex::Mutex;
+use embassy_time::Instant;
+use embedded_graphics::draw_target::DrawTarget;
+use embedded_graphics::pixelcolor::Rgb565;
+use gui::nav::{draw_bar, draw_link, update_active, update_selected, NAV};
+use gui::{Router, ScreenId};
+use portable_atomic::AtomicU8;
+use sprig_arcade_core::api::RequestData;
+use sprig_arcade_core::config::Config;
+use sprig_arcade_core::history::Days;
+use sprig_arcade_core::nav;
+use util::{Button, Events, Link, LONG_PRESS};
+
+pub mod gui;
+pub mod util;
+
+pub static TICKETS: AtomicU16 = AtomicU16::new(0);
+
+pub static EVENTS: Channel<CriticalSectionRawMutex, Events, 4> = Channel::new();
+
+pub static UPDATE_INTERVAL: AtomicU8 = AtomicU8::new(5);
+
+/// Fetches every `update_interval` minutes of `config`, for the screens that
+/// don't need to be any quicker.
+pub fn apply_update_interval(config: &Config) {
+    UPDATE_INTERVAL.store(config.update_interval, Ordering::Relaxed);
+}
+
+/// Loaded from flash at boot, see `CONFIG_OFFSET` in main.rs.
+pub static CONFIG: Mutex<CriticalSectionRawMutex, Config> = Mutex::new(Config::new());
+
+/// The last days of the ticket history, read back from flash each time the
+/// tickets are logged.
+pub static HISTORY: Mutex<CriticalSectionRawMutex, Days> = Mutex::new(Days::new());
+
+/// The nav bar and the screens below it, shared by the firmware and the simulator.
+pub struct App {
+    /// The button of the screen at the bottom of the stack.
+    active: usize,
+    selected: usize,
+    router: Router,
+    /// The button being held down, since when, and the screen it was pressed on.
+    pressed: Option<(Button, Instant, ScreenId, usize)>,
+}
+
+impl App {
+    /// Draws the nav bar with Home active.
+    pub fn new<D>(disp: &mut D) -> Self
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        draw_bar(disp);
+        update_active(0, 0, disp);
+
+        // only created once the network is up
+        draw_link(Link::Up, disp);
+
+        Self {
+            active: 0,
+            selected: 0,
+            router: Router::new(NAV[0].screen),
+            pressed: None,
+        }
+    }
+
+    pub async fn button_pressed<D>(&mut self, button: Button, disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        self.pressed = Some((
+            button,
+            Instant::now(),
+            self.router.top(),
+            self.router.depth(),
+        ));
+
+        if self.router.grabs_input().await {
+            return self.router.input(button, disp).await;
+        }
+
+        match button {
+            Button::Left | Button::Right => {
+                let next = match button {
+                    Button::Left => nav::left(self.selected, NAV.len()),
+                    _ => nav::right(self.selected, NAV.len()),
+                };
+                update_selected(next, self.selected, self.active, disp);
+                self.selected = next;
+            }
+            Button::A if self.active != self.selected => {
+                update_active(self.selected, self.active, disp);
+                self.active = self.selected;
+                self.router.switch(NAV[self.active].screen, disp).await;
+            }
+            btn => self.router.input(btn, disp).await,
+        }
+    }
+
+    pub async fn button_released<D>(&mut self, button: Button, disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let Some((pressed, at, screen, depth)) = self.pressed.take() else {
+            return;
+        };
+        // the press already went back or switched screens, the long press was
+        // meant for the one that's gone
+        if screen != self.router.top() || depth != self.router.depth() {
+            return;
+        }
+        if pressed == button && at.elapsed() >= LONG_PRESS {
+            self.router.long_press(button, disp).await;
+        }
+    }
+
+    pub fn link_changed<D>(&mut self, link: Link, disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        draw_link(link, disp);
+    }
+
+    pub async fn data_update<D>(&mut self, data: RequestData, now: NaiveDateTime, disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        let old = TICKETS.load(Ordering::Relaxed);
+        if let RequestData::Stats(tickets) = data {
+            TICKETS.store(tickets, Ordering::Relaxed);
+        }
+        self.router.on_data(disp, data, old, now).await;
+    }
+
+    pub async fn tick<D>(&mut self, disp: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+        D::Error: Debug,
+    {
+        self.router.on_tick(disp).await;
+    }
+}
@@ -3,43 +3,42 @@
 #![allow(async_fn_in_trait)]
 
 use core::cell::RefCell;
-use core::sync::atomic::{AtomicU16, Ordering};
 
-use chrono::{DateTime, Datelike, FixedOffset, Timelike, Weekday};
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike, Weekday};
 use defmt::*;
+use embassy_embedded_hal::flash::partition::BlockingPartition;
 use embassy_embedded_hal::shared_bus::blocking::spi::SpiDeviceWithConfig;
 use embassy_executor::Spawner;
 use embassy_futures::select::{select3, select4};
 use embassy_rp::bind_interrupts;
-use embassy_rp::gpio::{AnyPin, Input, Level, Output};
-use embassy_rp::peripherals::{self, PIO0, USB};
+use embassy_rp::flash::{self, Flash};
+use embassy_rp::gpio::{AnyPin, Input, Level, Output, Pull};
+use embassy_rp::peripherals::{self, PIO0, RTC, USB};
 use embassy_rp::pio::InterruptHandler;
+use embassy_rp::pwm::{self, Pwm};
 use embassy_rp::rtc::{DayOfWeek, Rtc};
 use embassy_rp::spi::{self, Spi};
 use embassy_rp::spi::{Blocking, Phase, Polarity};
 use embassy_rp::usb::{self, Driver};
-use embassy_sync::blocking_mutex::raw::{CriticalSectionRawMutex, ThreadModeRawMutex};
+use embassy_rp::watchdog::Watchdog;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
 use embassy_sync::blocking_mutex::Mutex as BlockingMutex;
-use embassy_sync::channel::Channel;
-use embassy_sync::mutex::Mutex;
 use embassy_time::{Delay, Timer};
 use embedded_graphics::draw_target::DrawTarget;
-use embedded_graphics::geometry::{Point, Size};
-use embedded_graphics::image::Image;
 use embedded_graphics::pixelcolor::Rgb565;
-use embedded_graphics::primitives::{Primitive, Rectangle};
-use embedded_graphics::Drawable;
-use gui::nav::{update_active, update_selected};
-use gui::{session, Screens, BACKGROUND};
 use log::info;
-use portable_atomic::AtomicU8;
-use st7735_lcd::{Orientation, ST7735};
-use tinytga::Tga;
-use util::{
-    Button, Events, BTN, BUTTONS, ERRORS_ICON, HOME_ICON, LEADERBOARD_ICON, PROJECTS_ICON,
-    SELECTED_BTN, SESSION_ICON, SHOP_ICON, WISHLIST_ICON,
+use sprig_arcade::gui::tick_task;
+use sprig_arcade::util::{self, Button, Events};
+use sprig_arcade::{apply_update_interval, report, App, CONFIG, EVENTS, HISTORY};
+use sprig_arcade_core::api::RequestData;
+use sprig_arcade_core::config::{
+    Config, ConfigError, ConfigStore, Network, Security, MAX_NETWORKS,
 };
-use wifi::RequestData;
+use sprig_arcade_core::errors::{ErrorKind, Source};
+use sprig_arcade_core::history::{History, Sample};
+use sprig_arcade_core::networks::remember;
+use sprig_arcade_core::tz::TimeZone;
+use st7735_lcd::{Orientation, ST7735};
 use {defmt_rtt as _, panic_probe as _};
 
 bind_interrupts!(struct Irqs {
@@ -47,16 +46,16 @@ bind_interrupts!(struct Irqs {
     PIO0_IRQ_0 => InterruptHandler<PIO0>;
 });
 
-mod gui;
-mod util;
 mod wifi;
 
-// TODO: move everything to settings
-pub const TICKET_GOAL: u16 = 160;
-pub const TICKET_OFFSET: u16 = 14;
-pub static TICKETS: AtomicU16 = AtomicU16::new(0);
-pub const END_DATE: Mutex<CriticalSectionRawMutex, Option<DateTime<FixedOffset>>> =
-    Mutex::new(None);
+const FLASH_SIZE: usize = 2 * 1024 * 1024;
+/// Start of the last flash sector, which memory.x keeps out of the program.
+const CONFIG_OFFSET: u32 = 0x1F_F000;
+/// The 16 sectors before the config, for the ticket history.
+const HISTORY_OFFSET: u32 = 0x1E_F000;
+const HISTORY_SECTORS: u32 = 16;
+/// PWM counter wrap for the backlight, 125MHz / 10000 is a flicker free 12.5kHz.
+const BACKLIGHT_TOP: u16 = 10_000;
 
 type Display<'a> = ST7735<
     SpiDeviceWithConfig<
@@ -69,10 +68,6 @@ type Display<'a> = ST7735<
     Output<'a, peripherals::PIN_26>,
 >;
 
-static EVENTS: Channel<ThreadModeRawMutex, Events, 4> = Channel::new();
-
-pub static UPDATE_INTERVAL: AtomicU8 = AtomicU8::new(5);
-
 #[embassy_executor::task]
 async fn logger_task(driver: Driver<'static, USB>) {
     embassy_usb_logger::run!(1024, log::LevelFilter::Debug, driver);
@@ -134,134 +129,12 @@ async fn input_task(
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum NavButton {
-    None,
-    Home,
-    Session,
-    Leaderboard, // TODO: remove (or hide by default w/o implementation)
-    Projects,
-    Wishlist, // TODO: merge wishlist with shop and make a settings page?
-    Shop,
-    // TODO: hide this by default
-    Errors,
-}
-
-impl NavButton {
-    pub fn is_neighbour_of(&self, neighbour: &NavButton) -> bool {
-        match self {
-            NavButton::Home => match neighbour {
-                NavButton::Session => true,
-                NavButton::Errors => true,
-                _ => false,
-            },
-            NavButton::Session => match neighbour {
-                NavButton::Home => true,
-                NavButton::Leaderboard => true,
-                _ => false,
-            },
-            NavButton::Leaderboard => match neighbour {
-                NavButton::Session => true,
-                NavButton::Projects => true,
-                _ => false,
-            },
-            NavButton::Projects => match neighbour {
-                NavButton::Leaderboard => true,
-                NavButton::Wishlist => true,
-                _ => false,
-            },
-            NavButton::Wishlist => match neighbour {
-                NavButton::Projects => true,
-                NavButton::Shop => true,
-                _ => false,
-            },
-            NavButton::Shop => match neighbour {
-                NavButton::Wishlist => true,
-                NavButton::Errors => true,
-                _ => false,
-            },
-            NavButton::Errors => match neighbour {
-                NavButton::Shop => true,
-                NavButton::Home => true,
-                _ => false,
-            },
-            NavButton::None => false,
-        }
-    }
-
-    pub fn pos(&self) -> Point {
-        match self {
-            NavButton::Home => Point::new(23, 0),
-            NavButton::Session => Point::new(39, 0),
-            NavButton::Leaderboard => Point::new(55, 0),
-            NavButton::Projects => Point::new(71, 0),
-            NavButton::Wishlist => Point::new(87, 0),
-            NavButton::Shop => Point::new(103, 0),
-            NavButton::Errors => Point::new(119, 0),
-            NavButton::None => Point::new(0, 0),
-        }
-    }
-
-    pub fn icon(&self) -> Tga<Rgb565> {
-        Tga::from_slice(match self {
-            NavButton::Home => HOME_ICON,
-            NavButton::Session => SESSION_ICON,
-            NavButton::Leaderboard => LEADERBOARD_ICON,
-            NavButton::Projects => PROJECTS_ICON,
-            NavButton::Wishlist => WISHLIST_ICON,
-            NavButton::Shop => SHOP_ICON,
-            NavButton::Errors => ERRORS_ICON,
-            NavButton::None => HOME_ICON,
-        })
-        .unwrap()
-    }
-
-    pub fn icon_pos(&self) -> Point {
-        match self {
-            NavButton::Home => Point::new(28, 2),
-            NavButton::Session => Point::new(43, 1),
-            NavButton::Leaderboard => Point::new(60, 3),
-            NavButton::Projects => Point::new(76, 1),
-            NavButton::Wishlist => Point::new(91, 2),
-            NavButton::Shop => Point::new(107, 0),
-            NavButton::Errors => Point::new(124, 1),
-            NavButton::None => Point::new(0, 0),
-        }
-    }
-
-    pub fn right(&self) -> Self {
-        match self {
-            NavButton::Home => NavButton::Session,
-            NavButton::Session => NavButton::Leaderboard,
-            NavButton::Leaderboard => NavButton::Projects,
-            NavButton::Projects => NavButton::Wishlist,
-            NavButton::Wishlist => NavButton::Shop,
-            NavButton::Shop => NavButton::Errors,
-            NavButton::Errors => NavButton::Home,
-            NavButton::None => NavButton::Home,
-        }
-    }
-
-    pub fn left(&self) -> Self {
-        match self {
-            NavButton::Home => NavButton::Errors,
-            NavButton::Session => NavButton::Home,
-            NavButton::Leaderboard => NavButton::Session,
-            NavButton::Projects => NavButton::Leaderboard,
-            NavButton::Wishlist => NavButton::Projects,
-            NavButton::Shop => NavButton::Wishlist,
-            NavButton::Errors => NavButton::Shop,
-            NavButton::None => NavButton::Home,
-        }
-    }
-}
-
 #[embassy_executor::main]
 async fn main(spawner: Spawner) -> ! {
     let p = embassy_rp::init(Default::default());
     let driver = Driver::new(p.USB, Irqs);
     spawner.spawn(logger_task(driver)).unwrap();
-    let mut bl = Output::new(p.PIN_17, Level::Low);
+    let mut bl = Pwm::new_output_b(p.PWM_CH0, p.PIN_17, backlight(0));
 
     info!("Launched Arcade Sprig!");
     Timer::after_nanos(20000).await;
@@ -299,7 +172,87 @@ async fn main(spawner: Spawner) -> ! {
 
     // BOILERPLATE MARK
 
-    bl.set_high();
+    let flash: BlockingMutex<CriticalSectionRawMutex, _> = BlockingMutex::new(RefCell::new(
+        Flash::<_, flash::Blocking, FLASH_SIZE>::new_blocking(p.FLASH),
+    ));
+    let config_partition = BlockingPartition::new(&flash, CONFIG_OFFSET, flash::ERASE_SIZE as u32);
+    let mut config_store = ConfigStore::new(config_partition, 0);
+    let config = match config_store.load() {
+        Ok(config) => config,
+        Err(err) => {
+            warn!(
+                "[Config] Failed to load config ({}), using the built in one",
+                Debug2Format(&err)
+            );
+            // a fresh Sprig has nothing saved yet, anything else is worth a look
+            if !matches!(err, ConfigError::Missing) {
+                report!(
+                    Source::Config,
+                    ErrorKind::Storage,
+                    "Failed to load config: {:?}",
+                    err
+                );
+            }
+            let config = env_config();
+            if let Err(err) = config_store.save(&config) {
+                report!(
+                    Source::Config,
+                    ErrorKind::Storage,
+                    "Failed to save config: {:?}",
+                    err
+                );
+            }
+            config
+        }
+    };
+
+    // holding A and B while turning on sets up Wi-Fi and the API again
+    let a = Input::new(AnyPin::from(p.PIN_14), Pull::Up);
+    let b = Input::new(AnyPin::from(p.PIN_15), Pull::Up);
+    Timer::after_millis(1).await;
+    if (a.is_low() && b.is_low()) || config.networks.is_empty() || config.api_token.is_empty() {
+        info!("[Setup] Starting setup mode");
+        *CONFIG.lock().await = config;
+        let config = wifi::provision(
+            &spawner, p.PIN_23, p.PIN_25, p.PIO0, p.PIN_24, p.PIN_29, p.DMA_CH0, &mut disp,
+        )
+        .await;
+        if let Err(err) = config_store.save(&config) {
+            report!(
+                Source::Config,
+                ErrorKind::Storage,
+                "Failed to save config: {:?}",
+                err
+            );
+        }
+        restart(Watchdog::new(p.WATCHDOG));
+    }
+
+    let history_partition = BlockingPartition::new(
+        &flash,
+        HISTORY_OFFSET,
+        HISTORY_SECTORS * flash::ERASE_SIZE as u32,
+    );
+    let mut history = match History::open(history_partition, 0, HISTORY_SECTORS) {
+        Ok(history) => Some(history),
+        Err(err) => {
+            report!(
+                Source::History,
+                ErrorKind::Storage,
+                "Failed to open the ticket history: {:?}",
+                err
+            );
+            None
+        }
+    };
+
+    set_timezone(&config);
+    // Home isn't initialised until its first data, so nothing else applies it
+    apply_update_interval(&config);
+    let brightness = config.backlight;
+    *CONFIG.lock().await = config;
+
+    bl.set_config(&backlight(brightness));
     let wifi = wifi::setup(
         &spawner, p.PIN_23, p.PIN_25, p.PIO0, p.PIN_24, p.PIN_29, p.DMA_CH0, &mut disp,
     )
@@ -307,71 +260,30 @@ async fn main(spawner: Spawner) -> ! {
 
     spawner
         .spawn(input_task(
-            Input::new(AnyPin::from(p.PIN_5), embassy_rp::gpio::Pull::Up),
-            Input::new(AnyPin::from(p.PIN_7), embassy_rp::gpio::Pull::Up),
-            Input::new(AnyPin::from(p.PIN_6), embassy_rp::gpio::Pull::Up),
-            Input::new(AnyPin::from(p.PIN_8), embassy_rp::gpio::Pull::Up),
-            Input::new(AnyPin::from(p.PIN_14), embassy_rp::gpio::Pull::Up),
-            Input::new(AnyPin::from(p.PIN_15), embassy_rp::gpio::Pull::Up),
+            Input::new(AnyPin::from(p.PIN_5), Pull::Up),
+            Input::new(AnyPin::from(p.PIN_7), Pull::Up),
+            Input::new(AnyPin::from(p.PIN_6), Pull::Up),
+            Input::new(AnyPin::from(p.PIN_8), Pull::Up),
+            a,
+            b,
         ))
         .unwrap();
 
-    let buttons: Tga<Rgb565> = Tga::from_slice(BUTTONS).unwrap();
-    let selected_btn: Tga<Rgb565> = Tga::from_slice(SELECTED_BTN).unwrap();
-
-    Image::new(&buttons, Point::new(23, 0))
-        .draw(&mut disp)
-        .unwrap();
-
-    let mut active = NavButton::Home;
-    let mut selected = NavButton::Home;
-
-    Image::new(&selected_btn, selected.pos())
-        .draw(&mut disp)
-        .unwrap();
-
-    Image::new(&selected.icon(), selected.icon_pos())
-        .draw(&mut disp)
-        .unwrap();
-
-    update_active(&selected, &active, &mut disp);
+    let mut app = App::new(&mut disp);
 
-    let mut screen = Screens::Home;
-
-    wifi::configure_rtc(wifi).await;
+    let synced = wifi::configure_rtc(wifi).await;
+    spawner.spawn(wifi::time_task(wifi, synced)).unwrap();
 
     spawner.spawn(wifi::fetch_data(wifi)).unwrap();
     spawner.spawn(wifi::wifi_trigger()).unwrap();
+    spawner.spawn(tick_task()).unwrap();
 
     let mut rtc = Rtc::new(p.RTC);
 
     loop {
         match EVENTS.receive().await {
-            Events::ButtonPressed(button) => match button {
-                Button::Left | Button::Right => {
-                    selected = move_nav(&selected, &active, &button, &mut disp).await;
-                }
-                Button::A => {
-                    if active != selected {
-                        active = select_btn(&selected, &active, &mut disp).await;
-                        Rectangle::new(Point::new(0, 14), Size::new(160, 114))
-                            .into_styled(BACKGROUND)
-                            .draw(&mut disp)
-                            .unwrap();
-                        screen = match active {
-                            NavButton::Home => Screens::Home,
-                            NavButton::Session => Screens::Session,
-                            _ => Screens::Home,
-                        };
-                        screen.init(&spawner).await;
-                        wifi::RUN.signal(true);
-                    }
-                }
-                btn => screen.input(btn, &mut disp).await,
-            },
-            Events::ButtonReleased(button) => {
-                // TODO: do something here?
-            }
+            Events::ButtonPressed(button) => app.button_pressed(button, &mut disp).await,
+            Events::ButtonReleased(button) => app.button_released(button, &mut disp).await,
             Events::DataUpdate(data) => {
                 if !rtc.is_running() {
                     error!(
@@ -380,77 +292,159 @@ async fn main(spawner: Spawner) -> ! {
                     EVENTS.send(Events::DataUpdate(data)).await;
                     continue;
                 }
-                match data {
-                    RequestData::Stats(tickets) => {
-                        let old = TICKETS.load(Ordering::Relaxed);
-                        TICKETS.store(tickets, Ordering::Relaxed);
-
-                        screen
-                            .update(&mut disp, data, old, rtc.now().unwrap())
-                            .await;
-                    }
-                    _ => {
-                        screen
-                            .update(
-                                &mut disp,
-                                data,
-                                TICKETS.load(Ordering::Relaxed),
-                                rtc.now().unwrap(),
-                            )
-                            .await;
+                if let (RequestData::Stats(tickets), Some(history)) = (&data, &mut history) {
+                    let sample = Sample {
+                        time: now(&rtc),
+                        tickets: *tickets,
+                    };
+                    let today = util::local(sample.time).date();
+                    let days = history
+                        .record(sample)
+                        .and_then(|_| history.recent(today, &util::timezone()));
+                    match days {
+                        Ok(days) => *HISTORY.lock().await = days,
+                        Err(err) => report!(
+                            Source::History,
+                            ErrorKind::Storage,
+                            "Failed to log tickets: {:?}",
+                            err
+                        ),
                     }
                 }
+                app.data_update(data, util::local(now(&rtc)), &mut disp)
+                    .await;
             }
-            Events::RtcUpdate(date) => {
-                let day_of_week = match date.weekday() {
-                    Weekday::Mon => DayOfWeek::Monday,
-                    Weekday::Tue => DayOfWeek::Tuesday,
-                    Weekday::Wed => DayOfWeek::Wednesday,
-                    Weekday::Thu => DayOfWeek::Thursday,
-                    Weekday::Fri => DayOfWeek::Friday,
-                    Weekday::Sat => DayOfWeek::Saturday,
-                    Weekday::Sun => DayOfWeek::Sunday,
-                };
-
-                let now = embassy_rp::rtc::DateTime {
-                    year: date.year() as u16,
-                    month: date.month() as u8,
-                    day: date.day() as u8,
-                    day_of_week,
-                    hour: date.hour() as u8,
-                    minute: date.minute() as u8,
-                    second: date.second() as u8,
-                };
-
-                rtc.set_datetime(now).unwrap();
-            }
-            Events::FlashSessionScreen(text) => {
-                session::flash(text, &mut disp).await;
+            Events::RtcUpdate(date) => set_rtc(&mut rtc, date.naive_utc()),
+            Events::Tick => app.tick(&mut disp).await,
+            Events::LinkChanged(link) => app.link_changed(link, &mut disp),
+            Events::SaveConfig => {
+                let config = CONFIG.lock().await;
+                if let Err(err) = config_store.save(&config) {
+                    report!(
+                        Source::Config,
+                        ErrorKind::Storage,
+                        "Failed to save config: {:?}",
+                        err
+                    );
+                }
+
+                bl.set_config(&backlight(config.backlight));
+                set_timezone(&config);
+                apply_update_interval(&config);
             }
-            _ => {}
         }
     }
 }
 
-async fn move_nav(
-    selected: &NavButton,
-    active: &NavButton,
-    button: &Button,
-    disp: &mut Display<'_>,
-) -> NavButton {
-    let prev_selected = selected.clone();
-    let next = match button {
-        Button::Left => selected.left(),
-        Button::Right => selected.right(),
-        _ => return selected.clone(),
+/// The RTC is kept in UTC, `util::local` turns it into local time.
+fn set_rtc(rtc: &mut Rtc<'_, RTC>, date: NaiveDateTime) {
+    let day_of_week = match date.weekday() {
+        Weekday::Mon => DayOfWeek::Monday,
+        Weekday::Tue => DayOfWeek::Tuesday,
+        Weekday::Wed => DayOfWeek::Wednesday,
+        Weekday::Thu => DayOfWeek::Thursday,
+        Weekday::Fri => DayOfWeek::Friday,
+        Weekday::Sat => DayOfWeek::Saturday,
+        Weekday::Sun => DayOfWeek::Sunday,
     };
-    update_selected(&next, &prev_selected, active, disp);
 
-    next
+    let now = embassy_rp::rtc::DateTime {
+        year: date.year() as u16,
+        month: date.month() as u8,
+        day: date.day() as u8,
+        day_of_week,
+        hour: date.hour() as u8,
+        minute: date.minute() as u8,
+        second: date.second() as u8,
+    };
+
+    rtc.set_datetime(now).unwrap();
+    util::set_clock(date);
+}
+
+/// Reboots, so a new config is loaded from scratch.
+fn restart(mut watchdog: Watchdog) -> ! {
+    watchdog.trigger_reset();
+    loop {
+        core::hint::spin_loop();
+    }
 }
 
-async fn select_btn(selected: &NavButton, active: &NavButton, disp: &mut Display<'_>) -> NavButton {
-    update_active(selected, active, disp);
+fn set_timezone(config: &Config) {
+    let zone = TimeZone::parse(&config.timezone).unwrap_or_else(|| {
+        report!(
+            Source::Config,
+            ErrorKind::Unexpected,
+            "Bad timezone {}, using UTC",
+            config.timezone
+        );
+        TimeZone::UTC
+    });
+    util::set_timezone(zone);
+}
+
+/// Backlight brightness as a percentage.
+fn backlight(percent: u8) -> pwm::Config {
+    let mut config = pwm::Config::default();
+    config.top = BACKLIGHT_TOP;
+    config.compare_b = BACKLIGHT_TOP / 100 * percent.min(100) as u16;
+    config
+}
+
+/// The config set in `.cargo/config.toml`, only used on first boot.
+fn env_config() -> Config {
+    let mut config = Config::new();
+    // more can be added as WIFI_NETWORK_2 and so on, the first is joined first
+    let networks = [
+        (option_env!("WIFI_NETWORK"), option_env!("WIFI_PASSWD")),
+        (option_env!("WIFI_NETWORK_2"), option_env!("WIFI_PASSWD_2")),
+        (option_env!("WIFI_NETWORK_3"), option_env!("WIFI_PASSWD_3")),
+        (option_env!("WIFI_NETWORK_4"), option_env!("WIFI_PASSWD_4")),
+    ];
+    for (i, (ssid, passwd)) in networks.into_iter().enumerate() {
+        let (Some(ssid), passwd) = (ssid, passwd.unwrap_or("")) else {
+            continue;
+        };
+        let network = Network {
+            ssid: ssid.try_into().unwrap(),
+            passwd: passwd.try_into().unwrap(),
+            security: if passwd.is_empty() {
+                Security::Open
+            } else {
+                Security::Wpa2
+            },
+            priority: (MAX_NETWORKS - i) as u8,
+        };
+        remember(&mut config.networks, network).unwrap();
+    }
+    // a comma separated list, the defaults are used without one
+    for server in option_env!("NTP_SERVERS").unwrap_or("").split(',') {
+        let server = server.trim();
+        if !server.is_empty() {
+            let _ = config.ntp_servers.push(server.try_into().unwrap());
+        }
+    }
+    // a POSIX TZ string, like the ones in `tz::ZONES`
+    config
+        .timezone
+        .push_str(option_env!("TIMEZONE").unwrap_or(""))
+        .unwrap();
+    config
+        .slack_id
+        .push_str(option_env!("SLACK_ID").unwrap_or(""))
+        .unwrap();
+    config
+        .api_token
+        .push_str(option_env!("API_TOKEN").unwrap_or(""))
+        .unwrap();
+    config
+}
 
-    selected.clone()
+/// The RTC's time, in UTC.
+fn now(rtc: &Rtc<'_, RTC>) -> NaiveDateTime {
+    let now = rtc.now().unwrap();
+    NaiveDate::from_ymd_opt(now.year as i32, now.month as u32, now.day as u32)
+        .unwrap()
+        .and_hms_opt(now.hour as u32, now.minute as u32, now.second as u32)
+        .unwrap()
 }
@@ -0,0 +1,321 @@
+//! Runs the UI on a Linux host with canned API data, see `cargo simulator`.
+//!
+//! Every frame is drawn to the terminal. Buttons are read from stdin, one per
+//! character: `w`/`a`/`s`/`d` for the d-pad, `j` for A, `k` for B and `l` to
+//! hold B. Pass a path as the first argument to also save each frame as a PNG.
+
+use std::io::{BufRead, Write};
+use std::thread;
+
+use chrono::{NaiveDate, NaiveDateTime, TimeDelta};
+use embassy_executor::Spawner;
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel};
+use embassy_time::{Duration, Instant, Timer};
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{OriginDimensions, Point, Size},
+    pixelcolor::{Rgb565, Rgb888, RgbColor},
+};
+use embedded_graphics_simulator::{OutputSettingsBuilder, SimulatorDisplay};
+use sprig_arcade::{
+    apply_update_interval,
+    gui::tick_task,
+    report,
+    util::{self, Button, Events, LONG_PRESS, REQUEST_TYPE, RUN, SESSION_ACTION},
+    App, CONFIG, EVENTS, HISTORY, UPDATE_INTERVAL,
+};
+use sprig_arcade_core::api::{
+    LeaderboardData, LeaderboardUser, ProjectData, RequestData, RequestType, SessionAction,
+    ShopItem, LEADERBOARD_PAGE, SHOP_PAGE,
+};
+use sprig_arcade_core::config::ConfigStore;
+use sprig_arcade_core::errors::{ErrorKind, Source};
+use sprig_arcade_core::history::{History, Sample};
+use sprig_arcade_core::storage::MemFlash;
+use sprig_arcade_core::tz::TimeZone;
+
+const TICKETS: u16 = 87;
+/// Tickets earned on each of the days before the simulator starts, oldest first.
+const EARNED: [u16; 28] = [
+    2, 0, 3, 4, 1, 0, 0, 5, 3, 2, 4, 6, 2, 0, 1, 3, 5, 4, 2, 0, 0, 6, 4, 3, 2, 5, 1, 3,
+];
+const GOALS: [&str; 3] = ["Simulating the Sprig", "No Goal", "Sprig Arcade"];
+/// Name, minutes and sessions.
+const PROJECTS: [(&str, u32, u32); 6] = [
+    ("Sprig Arcade", 2460, 41),
+    ("Simulating the Sprig", 1320, 22),
+    ("Hack Hour bot", 780, 13),
+    ("No Goal", 300, 6),
+    ("Pixel art", 240, 4),
+    ("Dotfiles", 120, 2),
+];
+const SHOP: [(&str, u16); 9] = [
+    ("Sprig", 20),
+    ("Pinecil", 60),
+    ("Raspberry Pi Pico W", 15),
+    ("Flipper Zero", 300),
+    ("Framework Laptop 13", 1000),
+    ("Hack Club stickers", 5),
+    ("Mechanical keyboard", 120),
+    ("Soldering kit", 45),
+    ("Steam Deck", 900),
+];
+/// The simulated user is 9th, on the second page.
+const LEADERBOARD: [(&str, u32); 16] = [
+    ("Orpheus", 212),
+    ("Heidi", 180),
+    ("Tanuki", 166),
+    ("Zara", 151),
+    ("Malted Milk", 140),
+    ("Basil", 133),
+    ("Quinn", 120),
+    ("Juniper", 101),
+    ("Sprig Simulator", TICKETS as u32),
+    ("Ada", 80),
+    ("Ferris", 74),
+    ("Kestrel", 61),
+    ("Nova", 55),
+    ("Pixel", 43),
+    ("Rowan", 30),
+    ("Wren", 12),
+];
+
+/// Buttons, and whether they are held long enough to count as a long press.
+static INPUT: Channel<CriticalSectionRawMutex, (Button, bool), 8> = Channel::new();
+
+/// The simulated RTC starts in the middle of the 2024 season, in UTC.
+fn now() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2024, 8, 1)
+        .unwrap()
+        .and_hms_opt(12, 0, 0)
+        .unwrap()
+        + TimeDelta::seconds(Instant::now().as_secs() as i64)
+}
+
+#[embassy_executor::task]
+async fn input_task() {
+    loop {
+        let (button, hold) = INPUT.receive().await;
+        EVENTS.send(Events::ButtonPressed(button)).await;
+        if hold {
+            // a little longer, as the press is timed from when the app sees it
+            Timer::after(LONG_PRESS + Duration::from_millis(100)).await;
+        }
+        EVENTS.send(Events::ButtonReleased(button)).await;
+    }
+}
+
+/// Stands in for `wifi::wifi_trigger`, with seconds instead of minutes.
+#[embassy_executor::task]
+async fn fetch_trigger() {
+    loop {
+        Timer::after_secs(UPDATE_INTERVAL.load(core::sync::atomic::Ordering::Relaxed) as u64).await;
+        RUN.signal(true);
+    }
+}
+
+/// Stands in for `wifi::fetch_data`, every session fetch moves a running
+/// session a minute along.
+#[embassy_executor::task]
+async fn fetch_data() {
+    let (mut elapsed, mut goal, mut paused, mut completed) = (0, GOALS[0], false, false);
+    loop {
+        let action = SESSION_ACTION.lock().await.take();
+        match &action {
+            Some(SessionAction::Start(work)) => {
+                (elapsed, paused, completed) = (0, false, false);
+                goal = GOALS
+                    .iter()
+                    .find(|g| **g == work.as_str())
+                    .unwrap_or(&GOALS[0]);
+            }
+            Some(SessionAction::Pause) => paused = !paused,
+            Some(SessionAction::Cancel) => completed = true,
+            None => {}
+        }
+
+        let typ = *(REQUEST_TYPE.lock().await);
+        let data = match typ {
+            RequestType::Stats => RequestData::Stats(TICKETS),
+            RequestType::Session => {
+                if !paused && !completed && action.is_none() {
+                    elapsed += 1;
+                    completed = elapsed == 60;
+                }
+                RequestData::Session(elapsed, goal, paused, completed)
+            }
+            RequestType::Goals => RequestData::Goals(GOALS.into_iter().collect()),
+            RequestType::Projects => RequestData::Projects(
+                PROJECTS
+                    .iter()
+                    .map(|&(name, minutes, sessions)| ProjectData {
+                        name,
+                        minutes,
+                        sessions,
+                    })
+                    .collect(),
+            ),
+            RequestType::Shop(page) => RequestData::Shop(
+                page,
+                SHOP.chunks(SHOP_PAGE)
+                    .nth(page as usize)
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|&(name, tickets)| ShopItem { name, tickets })
+                    .collect(),
+            ),
+            RequestType::Leaderboard(page) => RequestData::Leaderboard(
+                page,
+                LeaderboardData {
+                    rank: 9,
+                    sessions: TICKETS as u32,
+                    users: LEADERBOARD
+                        .chunks(LEADERBOARD_PAGE)
+                        .nth(page as usize)
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|&(name, sessions)| LeaderboardUser { name, sessions })
+                        .collect(),
+                },
+            ),
+        };
+
+        EVENTS.send(Events::DataUpdate(data)).await;
+        RUN.reset();
+        RUN.wait().await;
+    }
+}
+
+fn read_input() {
+    for line in std::io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        for c in line.chars() {
+            let button = match c.to_ascii_lowercase() {
+                'w' => (Button::Up, false),
+                'a' => (Button::Left, false),
+                's' => (Button::Down, false),
+                'd' => (Button::Right, false),
+                'j' => (Button::A, false),
+                'k' => (Button::B, false),
+                'l' => (Button::B, true),
+                _ => continue,
+            };
+            embassy_futures::block_on(INPUT.send(button));
+        }
+    }
+    std::process::exit(0);
+}
+
+/// Draws two rows of pixels per line of text using half blocks.
+fn present(disp: &SimulatorDisplay<Rgb565>, png: Option<&str>) {
+    let size = disp.size();
+    let mut frame = String::from("\x1b[H");
+    for y in (0..size.height as i32).step_by(2) {
+        for x in 0..size.width as i32 {
+            let top = Rgb888::from(disp.get_pixel(Point::new(x, y)));
+            let bottom = Rgb888::from(disp.get_pixel(Point::new(x, y + 1)));
+            frame += &format!(
+                "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m▀",
+                top.r(),
+                top.g(),
+                top.b(),
+                bottom.r(),
+                bottom.g(),
+                bottom.b()
+            );
+        }
+        frame += "\x1b[0m\n";
+    }
+    frame += "w/a/s/d: d-pad, j: A, k: B, l: hold B > ";
+
+    let mut stdout = std::io::stdout().lock();
+    stdout.write_all(frame.as_bytes()).unwrap();
+    stdout.flush().unwrap();
+
+    if let Some(path) = png {
+        let settings = OutputSettingsBuilder::new().scale(1).build();
+        if let Err(err) = disp.to_rgb_output_image(&settings).save_png(path) {
+            eprintln!("Failed to save frame to {path}: {err}");
+        }
+    }
+}
+
+#[embassy_executor::main]
+async fn main(spawner: Spawner) {
+    let png = std::env::args().nth(1);
+
+    let mut disp = SimulatorDisplay::new(Size::new(160, 128));
+    disp.clear(Rgb565::new(31, 60, 27)).unwrap();
+
+    // settings only last until the simulator exits
+    let mut config_store = ConfigStore::new(MemFlash::<4096>::new(), 0);
+    let mut history = History::open(MemFlash::<{ 4 * 4096 }>::new(), 0, 4).unwrap();
+    // a few weeks of made up history, ending with today's tickets
+    let start = now() - TimeDelta::days(EARNED.len() as i64);
+    let mut tickets = TICKETS - EARNED.iter().sum::<u16>();
+    for (day, earned) in [0].iter().chain(&EARNED).enumerate() {
+        tickets += earned;
+        let time = start + TimeDelta::days(day as i64 - 1);
+        history.record(Sample { time, tickets }).unwrap();
+    }
+
+    // the simulated clock is always set
+    util::set_clock(now());
+
+    print!("\x1b[2J");
+    let mut app = App::new(&mut disp);
+    present(&disp, png.as_deref());
+
+    thread::spawn(read_input);
+    spawner.spawn(input_task()).unwrap();
+    spawner.spawn(fetch_data()).unwrap();
+    spawner.spawn(fetch_trigger()).unwrap();
+    spawner.spawn(tick_task()).unwrap();
+
+    loop {
+        match EVENTS.receive().await {
+            Events::ButtonPressed(button) => app.button_pressed(button, &mut disp).await,
+            Events::ButtonReleased(button) => app.button_released(button, &mut disp).await,
+            Events::DataUpdate(data) => {
+                if let RequestData::Stats(tickets) = data {
+                    let sample = Sample {
+                        time: now(),
+                        tickets,
+                    };
+                    let today = util::local(sample.time).date();
+                    let days = history
+                        .record(sample)
+                        .and_then(|_| history.recent(today, &util::timezone()));
+                    match days {
+                        Ok(days) => *HISTORY.lock().await = days,
+                        Err(err) => report!(
+                            Source::History,
+                            ErrorKind::Storage,
+                            "Failed to log tickets: {err:?}"
+                        ),
+                    }
+                }
+                app.data_update(data, util::local(now()), &mut disp).await
+            }
+            Events::RtcUpdate(_) => continue,
+            Events::LinkChanged(link) => app.link_changed(link, &mut disp),
+            Events::Tick => app.tick(&mut disp).await,
+            Events::SaveConfig => {
+                let config = CONFIG.lock().await;
+                if let Err(err) = config_store.save(&config) {
+                    report!(
+                        Source::Config,
+                        ErrorKind::Storage,
+                        "Failed to save config: {err:?}"
+                    );
+                }
+                util::set_timezone(TimeZone::parse(&config.timezone).unwrap_or(TimeZone::UTC));
+                apply_update_interval(&config);
+                continue;
+            }
+        }
+        present(&disp, png.as_deref());
+    }
+}
@@ -1,10 +1,19 @@
-use chrono::{DateTime, FixedOffset};
-use heapless::String;
+use core::cell::{Cell, RefCell};
+use core::fmt;
 
-use crate::wifi::RequestData;
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
+use embassy_sync::{
+    blocking_mutex::{raw::CriticalSectionRawMutex, Mutex as BlockingMutex},
+    mutex::Mutex,
+    signal::Signal,
+};
+use embassy_time::{Duration, Instant};
+use heapless::String;
+use sprig_arcade_core::api::{RequestData, RequestType, SessionAction};
+use sprig_arcade_core::errors::{ErrorKind, ErrorLog, ErrorRecord, Source, MAX_ERRORS};
+use sprig_arcade_core::tz::TimeZone;
 
 pub const ARCADE_LOGO: &'static [u8; 2347] = include_bytes!("../assets/arcade.tga");
-pub const BUTTONS: &'static [u8; 1942] = include_bytes!("../assets/buttons.tga");
 pub const BTN: &'static [u8; 204] = include_bytes!("../assets/btn.tga");
 pub const SELECTED_BTN: &'static [u8; 204] = include_bytes!("../assets/selected_btn.tga");
 pub const ACTIVE_BTN: &'static [u8; 204] = include_bytes!("../assets/active_btn.tga");
@@ -17,16 +26,17 @@ pub const PROJECTS_ICON: &'static [u8; 182] = include_bytes!("../assets/buttons/
 pub const WISHLIST_ICON: &'static [u8; 218] = include_bytes!("../assets/buttons/wishlist.tga");
 pub const SHOP_ICON: &'static [u8; 204] = include_bytes!("../assets/buttons/shop.tga");
 pub const ERRORS_ICON: &'static [u8; 212] = include_bytes!("../assets/buttons/errors.tga");
+pub const SETTINGS_ICON: &[u8; 192] = include_bytes!("../assets/buttons/settings.tga");
 
 pub const TICKET_LARGE: &'static [u8; 471] = include_bytes!("../assets/ticket_large.tga");
 pub const TICKET_SMALL: &'static [u8; 187] = include_bytes!("../assets/ticket_small.tga");
 
 pub const PROGRESS_BAR: &'static [u8; 1033] = include_bytes!("../assets/session/progress.tga");
 
-// TODO: if there is more side bars then move to similar system as top nav
-pub const PROGRESS_SELECTED: &'static [u8; 710] =
-    include_bytes!("../assets/home/progress_selected.tga");
-pub const STATS_SELECTED: &'static [u8; 710] = include_bytes!("../assets/home/stats_selected.tga");
+/// The side tabs on Home, with one page's tab selected each, see `home::Page`.
+pub const PROGRESS_SELECTED: &[u8; 942] = include_bytes!("../assets/home/progress_selected.tga");
+pub const STATS_SELECTED: &[u8; 946] = include_bytes!("../assets/home/stats_selected.tga");
+pub const CHART_SELECTED: &[u8; 946] = include_bytes!("../assets/home/chart_selected.tga");
 
 // TODO: replace legacy code with this
 #[macro_export]
@@ -70,7 +80,7 @@ macro_rules! draw_tga {
 #[macro_export]
 macro_rules! write_text {
     ($text:expr, $point:expr, $disp:expr) => {
-        embedded_graphics::text::Text::new($text, $point, crate::gui::BLACK_CHAR)
+        embedded_graphics::text::Text::new($text, $point, $crate::gui::BLACK_CHAR)
             .draw($disp)
             .unwrap();
     };
@@ -78,7 +88,7 @@ macro_rules! write_text {
         embedded_graphics::text::Text::with_text_style(
             $text,
             $point,
-            crate::gui::BLACK_CHAR,
+            $crate::gui::BLACK_CHAR,
             $style,
         )
         .draw($disp)
@@ -99,7 +109,7 @@ macro_rules! write_text {
 #[macro_export]
 macro_rules! write_large_text {
     ($text:expr, $point:expr, $disp:expr) => {
-        embedded_graphics::text::Text::new($text, $point, crate::gui::BLACK_NUMBER_CHAR)
+        embedded_graphics::text::Text::new($text, $point, $crate::gui::BLACK_NUMBER_CHAR)
             .draw($disp)
             .unwrap();
     };
@@ -107,7 +117,7 @@ macro_rules! write_large_text {
         embedded_graphics::text::Text::with_text_style(
             $text,
             $point,
-            crate::gui::BLACK_NUMBER_CHAR,
+            $crate::gui::BLACK_NUMBER_CHAR,
             $style,
         )
         .draw($disp)
@@ -138,6 +148,15 @@ macro_rules! draw_rounded_rect {
     };
 }
 
+/// Logs an error and keeps it for the Errors screen, with `format!` style
+/// arguments after the source and kind.
+#[macro_export]
+macro_rules! report {
+    ($source:expr, $kind:expr, $($arg:tt)*) => {
+        $crate::util::report($source, $kind, core::format_args!($($arg)*))
+    };
+}
+
 // TODO: replace .unwrap() with this
 #[macro_export]
 macro_rules! unwrap {
@@ -152,6 +171,9 @@ macro_rules! unwrap {
     };
 }
 
+/// How long B has to be held to cancel a session.
+pub const LONG_PRESS: Duration = Duration::from_millis(800);
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Button {
     Up,
@@ -162,10 +184,77 @@ pub enum Button {
     B,
 }
 
+/// The state of the Wi-Fi connection, shown in the corner of the nav bar.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Link {
+    /// Joining the network failed, waiting to try again.
+    Down,
+    /// Joining the network or waiting for DHCP.
+    Joining,
+    Up,
+}
+
 pub enum Events {
     ButtonPressed(Button),
     ButtonReleased(Button),
     DataUpdate(RequestData),
-    RtcUpdate(DateTime<FixedOffset>),
-    FlashSessionScreen(bool),
+    RtcUpdate(DateTime<Utc>),
+    /// Sent every `gui::TICK`, for the screen on top.
+    Tick,
+    /// `CONFIG` was changed on the Settings screen and should be saved.
+    SaveConfig,
+    LinkChanged(Link),
+}
+
+pub static RUN: Signal<CriticalSectionRawMutex, bool> = Signal::new();
+
+pub static REQUEST_TYPE: Mutex<CriticalSectionRawMutex, RequestType> =
+    Mutex::new(RequestType::Stats);
+
+/// Sent by `fetch_data` before its next poll.
+pub static SESSION_ACTION: Mutex<CriticalSectionRawMutex, Option<SessionAction>> = Mutex::new(None);
+
+/// Errors for the Errors screen, pushed to by `report!`.
+pub static ERRORS: BlockingMutex<CriticalSectionRawMutex, RefCell<ErrorLog<MAX_ERRORS>>> =
+    BlockingMutex::new(RefCell::new(ErrorLog::new()));
+
+/// The RTC's time, in UTC, at an `Instant`, so any task can tell the time.
+static CLOCK: BlockingMutex<CriticalSectionRawMutex, Cell<Option<(NaiveDateTime, Instant)>>> =
+    BlockingMutex::new(Cell::new(None));
+
+/// The timezone from `CONFIG`.
+static ZONE: BlockingMutex<CriticalSectionRawMutex, Cell<TimeZone>> =
+    BlockingMutex::new(Cell::new(TimeZone::UTC));
+
+/// Call whenever the RTC is set or read, with its UTC time.
+pub fn set_clock(now: NaiveDateTime) {
+    CLOCK.lock(|clock| clock.set(Some((now, Instant::now()))));
+}
+
+/// Local time, or `None` if the RTC hasn't been set yet.
+pub fn clock() -> Option<NaiveDateTime> {
+    let (time, at) = CLOCK.lock(|clock| clock.get())?;
+    Some(local(
+        time + TimeDelta::milliseconds(at.elapsed().as_millis() as i64),
+    ))
+}
+
+/// Call whenever the timezone in `CONFIG` changes.
+pub fn set_timezone(zone: TimeZone) {
+    ZONE.lock(|cell| cell.set(zone));
+}
+
+pub fn timezone() -> TimeZone {
+    ZONE.lock(|zone| zone.get())
+}
+
+/// `utc` on the clocks where the Sprig is.
+pub fn local(utc: NaiveDateTime) -> NaiveDateTime {
+    timezone().to_local(utc)
+}
+
+pub fn report(source: Source, kind: ErrorKind, args: fmt::Arguments) {
+    let record = ErrorRecord::new(clock(), source, kind, args);
+    log::error!("[{}] {}", source.label(), record.message);
+    ERRORS.lock(|errors| errors.borrow_mut().push(record));
 }
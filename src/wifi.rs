@@ -1,17 +1,22 @@
 use core::{
+    fmt::{Debug, Write},
     ptr::addr_of_mut,
     str::{from_utf8, FromStr},
-    sync::atomic::AtomicBool,
 };
 
-use chrono::{DateTime, FixedOffset, TimeZone};
-use cyw43::State;
+use chrono::{DateTime, FixedOffset, Utc};
+use cyw43::{Control, State};
 use cyw43_pio::PioSpi;
 use embassy_executor::Spawner;
+use embassy_futures::select::select;
 use embassy_net::{
-    dns::DnsSocket,
-    tcp::client::{TcpClient, TcpClientState},
-    Config, Stack, StackResources,
+    dns::{DnsQueryType, DnsSocket},
+    tcp::{
+        client::{TcpClient, TcpClientState},
+        TcpSocket,
+    },
+    udp::{PacketMetadata, UdpSocket},
+    Config, ConfigV4, Ipv4Address, Ipv4Cidr, Stack, StackResources, StaticConfigV4,
 };
 use embassy_rp::{
     clocks::RoscRng,
@@ -20,12 +25,8 @@ use embassy_rp::{
     pio::Pio,
     Peripherals,
 };
-use embassy_sync::{
-    blocking_mutex::raw::{CriticalSectionRawMutex, NoopRawMutex, ThreadModeRawMutex},
-    mutex::{Mutex, MutexGuard},
-    signal::Signal,
-};
-use embassy_time::Timer;
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, mutex::Mutex};
+use embassy_time::{with_timeout, Duration, Timer};
 use embedded_graphics::draw_target::DrawTarget;
 use embedded_graphics::{
     geometry::{Point, Size},
@@ -37,55 +38,39 @@ use embedded_graphics::{
     primitives::{PrimitiveStyle, PrimitiveStyleBuilder, Rectangle, RoundedRectangle},
     Drawable,
 };
-use heapless::String;
-use log::{debug, error, info};
+use heapless::{String, Vec};
+use log::{debug, info};
 use rand::RngCore;
 use reqwless::{
     client::{HttpClient, TlsConfig, TlsVerify},
-    request::RequestBuilder,
+    headers::ContentType,
+    request::{Method, RequestBuilder},
+    response::{HeaderIterator, StatusCode},
     Error,
 };
 use serde::Deserialize;
 use static_cell::StaticCell;
 
-use crate::{
+use sprig_arcade::{
     gui::{BLACK_CHAR, CENTERED_TEXT},
-    Irqs, EVENTS, TICKETS, UPDATE_INTERVAL,
+    report,
+    util::{Events, Link, REQUEST_TYPE, RUN, SESSION_ACTION},
+    CONFIG, EVENTS, TICKETS, UPDATE_INTERVAL,
 };
 
-pub static RUN: Signal<ThreadModeRawMutex, bool> = Signal::new();
-
-#[derive(Deserialize, Debug)]
-pub struct SessionResponse {
-    ok: bool,
-    data: Option<SessionData>,
-    error: Option<&'static str>,
-}
-
-#[derive(Deserialize, Debug)]
-pub struct SessionData {
-    elapsed: u8,
-    goal: &'static str,
-    paused: bool,
-}
-
-#[derive(Deserialize, Debug)]
-pub struct StatsResponse {
-    ok: bool,
-    data: Option<StatsData>,
-    error: Option<&'static str>,
-}
-
-#[derive(Deserialize, Debug, Clone)]
-pub struct StatsData {
-    pub sessions: u32,
-    pub total: u32,
-}
+use sprig_arcade_core::api::{
+    ActionResponse, FetchError, GoalsResponse, LeaderboardResponse, ProjectsResponse, RequestData,
+    RequestType, Response, SessionAction, SessionResponse, ShopResponse, StatsResponse, TimeData,
+    LEADERBOARD_PAGE, MAX_GOALS, MAX_PROJECTS, SHOP_PAGE,
+};
+use sprig_arcade_core::backoff::{retry_after, Backoff, Retry};
+use sprig_arcade_core::config::Security;
+use sprig_arcade_core::errors::{ErrorKind, Source};
+use sprig_arcade_core::networks::join_order;
+use sprig_arcade_core::provision::{self, Handled};
+use sprig_arcade_core::sntp;
 
-#[derive(Deserialize, Debug)]
-pub struct TimeData {
-    pub datetime: &'static str,
-}
+use crate::Irqs;
 
 #[embassy_executor::task]
 async fn wifi_task(
@@ -103,24 +88,39 @@ async fn net_task(stack: &'static Stack<cyw43::NetDriver<'static>>) -> ! {
     stack.run().await
 }
 
-#[derive(Clone, Copy, Debug)]
-pub enum RequestType {
-    Stats,
-    Session,
-}
+/// Boots the CYW43, which is shared by `setup` and `provision`.
+async fn start_radio(
+    spawner: &Spawner,
+    pwr_pin: PIN_23,
+    cs_pin: PIN_25,
+    pio_ch: PIO0,
+    dio: PIN_24,
+    clk: PIN_29,
+    dma_ch: DMA_CH0,
+) -> (cyw43::NetDriver<'static>, Control<'static>) {
+    let fw = include_bytes!("../firmware/43439A0.bin");
+    let clm = include_bytes!("../firmware/43439A0_clm.bin");
 
-#[derive(Debug)]
-pub enum RequestData {
-    // Ticket count
-    Stats(u16),
-    // Elapsed time, goal, paused
-    Session(u8, &'static str, bool),
-    None,
-}
+    let pwr = Output::new(pwr_pin, Level::Low);
+    let cs = Output::new(cs_pin, Level::High);
+    let mut pio = Pio::new(pio_ch, Irqs);
+    let spi = PioSpi::new(&mut pio.common, pio.sm0, pio.irq0, cs, dio, clk, dma_ch);
 
-pub static REQUEST_TYPE: Mutex<ThreadModeRawMutex, RequestType> = Mutex::new(RequestType::Stats);
+    static STATE: StaticCell<State> = StaticCell::new();
+    let state = STATE.init(State::new());
+
+    let (net_device, mut control, runner) = cyw43::new(state, pwr, spi, fw).await;
+    spawner.spawn(wifi_task(runner)).unwrap();
 
-pub async fn setup(
+    control.init(clm).await;
+    control
+        .set_power_management(cyw43::PowerManagementMode::PowerSave)
+        .await;
+
+    (net_device, control)
+}
+
+pub async fn setup<D>(
     spawner: &Spawner,
     pwr_pin: PIN_23,
     cs_pin: PIN_25,
@@ -128,8 +128,12 @@ pub async fn setup(
     dio: PIN_24,
     clk: PIN_29,
     dma_ch: DMA_CH0,
-    display: &mut crate::Display<'_>,
-) -> &'static Stack<cyw43::NetDriver<'static>> {
+    display: &mut D,
+) -> &'static Stack<cyw43::NetDriver<'static>>
+where
+    D: DrawTarget<Color = Rgb565>,
+    D::Error: Debug,
+{
     Text::with_text_style("Loading...", Point::new(80, 40), BLACK_CHAR, CENTERED_TEXT)
         .draw(display)
         .unwrap();
@@ -160,24 +164,8 @@ pub async fn setup(
 
     let mut rng = RoscRng;
 
-    let fw = include_bytes!("../firmware/43439A0.bin");
-    let clm = include_bytes!("../firmware/43439A0_clm.bin");
-
-    let pwr = Output::new(pwr_pin, Level::Low);
-    let cs = Output::new(cs_pin, Level::High);
-    let mut pio = Pio::new(pio_ch, Irqs);
-    let spi = PioSpi::new(&mut pio.common, pio.sm0, pio.irq0, cs, dio, clk, dma_ch);
-
-    static STATE: StaticCell<State> = StaticCell::new();
-    let state = STATE.init(State::new());
-
-    let (net_device, mut control, runner) = cyw43::new(state, pwr, spi, fw).await;
-    spawner.spawn(wifi_task(runner)).unwrap();
-
-    control.init(clm).await;
-    control
-        .set_power_management(cyw43::PowerManagementMode::PowerSave)
-        .await;
+    let (net_device, mut control) =
+        start_radio(spawner, pwr_pin, cs_pin, pio_ch, dio, clk, dma_ch).await;
 
     RoundedRectangle::with_equal_corners(
         Rectangle::new(Point::new(20, 49), Size::new(20, 6)),
@@ -192,11 +180,11 @@ pub async fn setup(
     let seed = rng.next_u64();
 
     static STACK: StaticCell<Stack<cyw43::NetDriver<'static>>> = StaticCell::new();
-    static RESOURCES: StaticCell<StackResources<5>> = StaticCell::new();
+    static RESOURCES: StaticCell<StackResources<6>> = StaticCell::new();
     let stack = &*STACK.init(Stack::new(
         net_device,
         config,
-        RESOURCES.init(StackResources::<5>::new()),
+        RESOURCES.init(StackResources::<6>::new()),
         seed,
     ));
 
@@ -210,24 +198,18 @@ pub async fn setup(
     .draw(display)
     .unwrap();
 
-    info!("[Wifi] Joining network");
-
-    loop {
-        //match control.join_open(WIFI_NETWORK).await { // for open networks
-        match control
-            .join_wpa2(env!("WIFI_NETWORK"), env!("WIFI_PASSWD"))
-            .await
-        {
-            Ok(_) => break,
-            Err(err) => {
-                info!(
-                    "[Wifi] Failed to join with status {}, trying again",
-                    err.status
-                );
-                Timer::after_nanos(20000).await;
-            }
+    let ssid = loop {
+        match join(&mut control).await {
+            Some(ssid) => break ssid,
+            None => Timer::after_secs(2).await,
         }
-    }
+    };
+
+    let mut joined = String::<40>::new();
+    write!(joined, "Joined {}", ssid).unwrap();
+    Text::with_text_style(&joined, Point::new(80, 70), BLACK_CHAR, CENTERED_TEXT)
+        .draw(display)
+        .unwrap();
 
     RoundedRectangle::with_equal_corners(
         Rectangle::new(Point::new(20, 49), Size::new(50, 6)),
@@ -304,9 +286,302 @@ pub async fn setup(
 
     info!("[Wifi] Up and running");
 
+    spawner.spawn(supervisor_task(control, stack)).unwrap();
+
     stack
 }
 
+/// How often the link is checked once it's up.
+const LINK_CHECK_SECS: u64 = 2;
+/// How long to wait for a DHCP lease before joining again.
+const DHCP_TIMEOUT_SECS: u64 = 15;
+
+/// Watches the connection, and joins the network again when it drops.
+#[embassy_executor::task]
+async fn supervisor_task(
+    mut control: Control<'static>,
+    stack: &'static Stack<cyw43::NetDriver<'static>>,
+) -> ! {
+    let mut backoff = Backoff::new();
+    let mut link = Link::Up;
+
+    loop {
+        if stack.is_link_up() && stack.is_config_up() {
+            if link != Link::Up {
+                info!("[Wifi] Reconnected");
+            }
+            publish(&mut link, Link::Up).await;
+            backoff.success();
+            Timer::after_secs(LINK_CHECK_SECS).await;
+            continue;
+        }
+
+        if link == Link::Up {
+            report!(Source::Wifi, ErrorKind::Connection, "Lost the connection");
+        }
+        publish(&mut link, Link::Joining).await;
+
+        if !stack.is_link_up() {
+            if join(&mut control).await.is_none() {
+                let wait = backoff.rejoin(RoscRng.next_u32());
+                info!("[Wifi] No known network joined, trying again in {}ms", wait);
+                publish(&mut link, Link::Down).await;
+                Timer::after_millis(wait as u64).await;
+                continue;
+            }
+
+            // the old lease might not be valid anymore
+            stack.set_config_v4(ConfigV4::Dhcp(Default::default()));
+        }
+
+        info!("[Wifi] Waiting for DHCP");
+        let lease = with_timeout(
+            Duration::from_secs(DHCP_TIMEOUT_SECS),
+            stack.wait_config_up(),
+        )
+        .await;
+        if lease.is_err() {
+            report!(
+                Source::Wifi,
+                ErrorKind::Connection,
+                "No DHCP lease, rejoining"
+            );
+            control.leave().await;
+            publish(&mut link, Link::Down).await;
+            Timer::after_millis(backoff.rejoin(RoscRng.next_u32()) as u64).await;
+        }
+    }
+}
+
+/// Different SSIDs kept from a scan.
+const MAX_SCAN: usize = 16;
+
+/// Scans for the known networks and joins the best one, returning its SSID.
+async fn join(control: &mut Control<'static>) -> Option<String<32>> {
+    let networks = CONFIG.lock().await.networks.clone();
+
+    info!("[Wifi] Scanning");
+    let mut in_range = Vec::<String<32>, MAX_SCAN>::new();
+    let mut scanner = control.scan().await;
+    while let Some(bss) = scanner.next().await {
+        let Some(ssid) = bss.ssid.get(..bss.ssid_len as usize) else {
+            continue;
+        };
+        let Ok(ssid) = from_utf8(ssid) else {
+            continue;
+        };
+        if !ssid.is_empty() && !in_range.iter().any(|seen| seen == ssid) {
+            let _ = in_range.push(String::try_from(ssid).unwrap());
+        }
+    }
+    drop(scanner);
+
+    for i in join_order(&networks, &in_range) {
+        let network = &networks[i];
+        info!("[Wifi] Joining {}", network.ssid);
+        let joined = match network.security {
+            Security::Open => control.join_open(&network.ssid).await,
+            // cyw43 can only join WPA3 networks that also allow WPA2
+            Security::Wpa2 | Security::Wpa3 => {
+                control.join_wpa2(&network.ssid, &network.passwd).await
+            }
+        };
+        match joined {
+            Ok(()) => {
+                info!("[Wifi] Joined {}", network.ssid);
+                return Some(network.ssid.clone());
+            }
+            Err(err) => info!(
+                "[Wifi] Failed to join {} with status {}",
+                network.ssid, err.status
+            ),
+        }
+    }
+
+    None
+}
+
+async fn publish(link: &mut Link, new: Link) {
+    if *link != new {
+        *link = new;
+        EVENTS.send(Events::LinkChanged(new)).await;
+    }
+}
+
+/// The open network the Sprig makes while provisioning.
+const SETUP_SSID: &str = "Sprig Arcade Setup";
+const SETUP_CHANNEL: u8 = 6;
+
+/// Starts an open access point and serves the setup form until it's filled
+/// in, returning the updated config. The caller saves it and restarts.
+#[allow(clippy::too_many_arguments)]
+pub async fn provision<D>(
+    spawner: &Spawner,
+    pwr_pin: PIN_23,
+    cs_pin: PIN_25,
+    pio_ch: PIO0,
+    dio: PIN_24,
+    clk: PIN_29,
+    dma_ch: DMA_CH0,
+    display: &mut D,
+) -> sprig_arcade_core::config::Config
+where
+    D: DrawTarget<Color = Rgb565>,
+    D::Error: Debug,
+{
+    Text::with_text_style("Setup mode", Point::new(80, 30), BLACK_CHAR, CENTERED_TEXT)
+        .draw(display)
+        .unwrap();
+
+    let (net_device, mut control) =
+        start_radio(spawner, pwr_pin, cs_pin, pio_ch, dio, clk, dma_ch).await;
+    control.start_ap_open(SETUP_SSID, SETUP_CHANNEL).await;
+
+    let [a, b, c, d] = provision::ADDRESS;
+    let address = Ipv4Address::new(a, b, c, d);
+    let config = Config::ipv4_static(StaticConfigV4 {
+        address: Ipv4Cidr::new(address, 24),
+        gateway: Some(address),
+        dns_servers: Vec::new(),
+    });
+
+    static STACK: StaticCell<Stack<cyw43::NetDriver<'static>>> = StaticCell::new();
+    static RESOURCES: StaticCell<StackResources<5>> = StaticCell::new();
+    let stack = &*STACK.init(Stack::new(
+        net_device,
+        config,
+        RESOURCES.init(StackResources::<5>::new()),
+        RoscRng.next_u64(),
+    ));
+
+    spawner.spawn(net_task(stack)).unwrap();
+    spawner.spawn(dhcp_task(stack)).unwrap();
+    spawner.spawn(dns_task(stack)).unwrap();
+
+    for (i, line) in [
+        "Join the Wi-Fi network",
+        SETUP_SSID,
+        "then open 192.168.4.1",
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        Text::with_text_style(
+            line,
+            Point::new(80, 55 + i as i32 * 12),
+            BLACK_CHAR,
+            CENTERED_TEXT,
+        )
+        .draw(display)
+        .unwrap();
+    }
+
+    let mut rx_buffer = [0; 1024];
+    let mut tx_buffer = [0; 2048];
+    loop {
+        let mut socket = TcpSocket::new(stack, &mut rx_buffer, &mut tx_buffer);
+        socket.set_timeout(Some(Duration::from_secs(10)));
+        if let Err(err) = socket.accept(80).await {
+            debug!("[Setup] Failed to accept a connection: {:?}", err);
+            continue;
+        }
+
+        let mut config = CONFIG.lock().await.clone();
+        let handled = provision::serve(&mut socket, &mut config).await;
+        let _ = socket.flush().await;
+        socket.close();
+        // give the phone a moment to read the reply
+        Timer::after_millis(100).await;
+
+        match handled {
+            Ok(Handled::Saved) => {
+                info!("[Setup] Saved the new config");
+                Rectangle::new(Point::new(0, 20), Size::new(160, 90))
+                    .into_styled(PrimitiveStyle::with_fill(Rgb565::new(31, 60, 27)))
+                    .draw(display)
+                    .unwrap();
+                Text::with_text_style(
+                    "Saved, restarting...",
+                    Point::new(80, 60),
+                    BLACK_CHAR,
+                    CENTERED_TEXT,
+                )
+                .draw(display)
+                .unwrap();
+                // let the saved page finish sending before the network goes away
+                Timer::after_secs(1).await;
+                return config;
+            }
+            Ok(handled) => debug!("[Setup] {:?}", handled),
+            Err(err) => debug!("[Setup] Connection failed: {:?}", err),
+        }
+    }
+}
+
+/// Hands out addresses to phones joining the setup network.
+#[embassy_executor::task]
+async fn dhcp_task(stack: &'static Stack<cyw43::NetDriver<'static>>) -> ! {
+    let mut rx_meta = [PacketMetadata::EMPTY; 4];
+    let mut rx_buffer = [0; 1024];
+    let mut tx_meta = [PacketMetadata::EMPTY; 4];
+    let mut tx_buffer = [0; 1024];
+    let mut socket = UdpSocket::new(
+        stack,
+        &mut rx_meta,
+        &mut rx_buffer,
+        &mut tx_meta,
+        &mut tx_buffer,
+    );
+    socket.bind(67).unwrap();
+
+    let mut request = [0; 576];
+    let mut reply = [0; 300];
+    loop {
+        let Ok((len, _)) = socket.recv_from(&mut request).await else {
+            continue;
+        };
+        if let Some(len) = provision::dhcp_reply(&request[..len], &mut reply) {
+            // the phone has no address yet, so it can only hear a broadcast
+            if let Err(err) = socket
+                .send_to(&reply[..len], (Ipv4Address::BROADCAST, 68))
+                .await
+            {
+                debug!("[Setup] Failed to send a DHCP reply: {:?}", err);
+            }
+        }
+    }
+}
+
+/// Points every lookup on the setup network at the Sprig.
+#[embassy_executor::task]
+async fn dns_task(stack: &'static Stack<cyw43::NetDriver<'static>>) -> ! {
+    let mut rx_meta = [PacketMetadata::EMPTY; 4];
+    let mut rx_buffer = [0; 1024];
+    let mut tx_meta = [PacketMetadata::EMPTY; 4];
+    let mut tx_buffer = [0; 1024];
+    let mut socket = UdpSocket::new(
+        stack,
+        &mut rx_meta,
+        &mut rx_buffer,
+        &mut tx_meta,
+        &mut tx_buffer,
+    );
+    socket.bind(53).unwrap();
+
+    let mut query = [0; 512];
+    let mut reply = [0; 512];
+    loop {
+        let Ok((len, from)) = socket.recv_from(&mut query).await else {
+            continue;
+        };
+        if let Some(len) = provision::dns_reply(&query[..len], &mut reply) {
+            if let Err(err) = socket.send_to(&reply[..len], from).await {
+                debug!("[Setup] Failed to send a DNS reply: {:?}", err);
+            }
+        }
+    }
+}
+
 #[embassy_executor::task]
 pub async fn wifi_trigger() {
     loop {
@@ -314,7 +589,8 @@ pub async fn wifi_trigger() {
         loop {
             Timer::after_secs(60).await;
             i += 1;
-            if i == UPDATE_INTERVAL.load(core::sync::atomic::Ordering::Relaxed) {
+            // lowering the interval can leave it below what's already counted
+            if i >= UPDATE_INTERVAL.load(core::sync::atomic::Ordering::Relaxed) {
                 break;
             }
         }
@@ -322,220 +598,439 @@ pub async fn wifi_trigger() {
     }
 }
 
+type Client<'a> = HttpClient<
+    'a,
+    TcpClient<'a, cyw43::NetDriver<'static>, 1, 1024, 1024>,
+    DnsSocket<'a, cyw43::NetDriver<'static>>,
+>;
+
+const API_URL: &str = "https://hackhour.hackclub.com/api/";
+
+/// Big enough for a full TLS record.
+const TLS_BUFFER: usize = 16384;
+
+struct TlsBuffers {
+    read: [u8; TLS_BUFFER],
+    write: [u8; TLS_BUFFER],
+}
+
+/// Shared by every HTTPS request, which are too big to keep one each of on
+/// the stack. Holding the lock is what makes a request the only one.
+static TLS: Mutex<CriticalSectionRawMutex, TlsBuffers> = Mutex::new(TlsBuffers {
+    read: [0; TLS_BUFFER],
+    write: [0; TLS_BUFFER],
+});
+
+/// A client that only makes HTTPS requests through `tls`. reqwless can only
+/// check a server with a pre-shared key, so the certificate isn't verified.
+fn https_client<'a>(
+    tcp_client: &'a TcpClient<'a, cyw43::NetDriver<'static>, 1, 1024, 1024>,
+    dns_client: &'a DnsSocket<'a, cyw43::NetDriver<'static>>,
+    tls: &'a mut TlsBuffers,
+) -> Client<'a> {
+    let TlsBuffers { read, write } = tls;
+    HttpClient::new_with_tls(
+        tcp_client,
+        dns_client,
+        TlsConfig::new(RoscRng.next_u64(), read, write, TlsVerify::None),
+    )
+}
+
 #[embassy_executor::task]
-pub async fn fetch_data(stack: &'static Stack<cyw43::NetDriver<'static>>) {
+pub async fn fetch_data(stack: &'static Stack<cyw43::NetDriver<'static>>) -> ! {
     let client_state = TcpClientState::<1, 1024, 1024>::new();
     let tcp_client = TcpClient::new(stack, &client_state);
     let dns_client = DnsSocket::new(stack);
 
-    let mut http_client = HttpClient::new(&tcp_client, &dns_client);
     static mut RX_BUF: [u8; 8192] = [0; 8192];
+    let mut backoff = Backoff::new();
 
     loop {
-        unsafe {
-            let rx_buffer = &mut RX_BUF;
-            rx_buffer.fill(0);
-            debug!("[Wifi] Fetching Hack Hour info");
-            Timer::after_nanos(200000).await;
-
-            debug!("got client");
-            Timer::after_nanos(200000).await;
+        // no point trying while the supervisor is joining again
+        stack.wait_config_up().await;
+
+        // the last response has been handled by the time RUN is signalled again
+        let rx_buffer = unsafe { &mut *addr_of_mut!(RX_BUF) };
+        let mut tls = TLS.lock().await;
+        let mut http_client = https_client(&tcp_client, &dns_client, &mut tls);
+
+        // session controls go out before the poll, so it picks up their result
+        let action = SESSION_ACTION.lock().await.take();
+        if let Some(action) = action {
+            debug!("[Wifi] Sending session action {:?}", action);
+            if let Err(err) = send_action(&mut http_client, &action, rx_buffer).await {
+                report!(
+                    Source::Api,
+                    err.kind(),
+                    "Session {:?} failed: {}",
+                    action,
+                    err
+                );
+            }
+        }
 
-            let mut data = RequestData::None;
-            {
-                let mut url = String::<52>::new();
-                let typ = *(REQUEST_TYPE.lock().await);
-                match typ {
-                    RequestType::Stats => {
-                        url.push_str("http://hackhour.hackclub.com/api/stats/")
-                            .unwrap();
-                    }
-                    RequestType::Session => {
-                        url.push_str("http://hackhour.hackclub.com/api/session/")
-                            .unwrap();
-                    }
-                }
-                //url.push_str("http://hackhour.hackclub.com/api/stats/")
-                //    .unwrap();
-                url.push_str(env!("SLACK_ID")).unwrap();
-
-                debug!("making request");
-                Timer::after_nanos(200000).await;
-
-                let mut req = http_client
-                    .request(reqwless::request::Method::GET, &url)
-                    .await
-                    .unwrap();
+        let typ = *(REQUEST_TYPE.lock().await);
+        debug!("[Wifi] Fetching {:?}", typ);
+        let retry = match fetch(&mut http_client, typ, rx_buffer).await {
+            Ok(data) => {
+                backoff.success();
+                EVENTS.send(Events::DataUpdate(data)).await;
+                debug!("[Wifi] Sent event successfully!");
+                Retry::Wait
+            }
+            Err(err) => {
+                report!(Source::Api, err.kind(), "{:?} request failed: {}", typ, err);
+                backoff.failure(&err, RoscRng.next_u32())
+            }
+        };
+        // other requests can go out while this one waits
+        drop(tls);
 
-                let mut header = String::<46>::from_str("Bearer ").unwrap();
-                header.push_str(env!("API_TOKEN")).unwrap();
-                let headers = [("Authorization", header.as_str())];
+        RUN.reset();
+        match retry {
+            Retry::Wait => {
+                RUN.wait().await;
+            }
+            Retry::After(ms) => {
+                debug!("[Wifi] Retrying in {}ms", ms);
+                select(RUN.wait(), Timer::after_millis(ms as u64)).await;
+            }
+            // updates asked for in the meantime are picked up by the retry
+            Retry::Throttled(ms) => {
+                debug!("[Wifi] Throttled, retrying in {}ms", ms);
+                Timer::after_millis(ms as u64).await;
+                RUN.reset();
+            }
+        }
+        debug!("[Wifi] Starting again!");
+    }
+}
 
-                req = req.headers(&headers);
+async fn send_action(
+    client: &mut Client<'_>,
+    action: &SessionAction,
+    rx_buffer: &mut [u8],
+) -> Result<(), FetchError> {
+    let config = CONFIG.lock().await;
+    let mut url = String::<64>::from_str(API_URL).unwrap();
+    url.push_str(action.path()).unwrap();
+    url.push('/').unwrap();
+    url.push_str(&config.slack_id).unwrap();
+    let header = bearer(&config.api_token);
+    drop(config);
+
+    let headers = [("Authorization", header.as_str())];
+    let body = action.body();
+
+    let mut req = client
+        .request(Method::POST, &url)
+        .await
+        .map_err(fetch_error)?
+        .headers(&headers)
+        .content_type(ContentType::ApplicationJson)
+        .body(body.as_bytes());
+    let resp = req.send(rx_buffer).await.map_err(fetch_error)?;
+    check_status(resp.status, resp.headers())?;
+    let body = resp.body().read_to_end().await.map_err(fetch_error)?;
+
+    match serde_json_core::from_slice::<ActionResponse>(body) {
+        Ok((resp, _)) => resp.into_result(),
+        Err(e) => {
+            debug!(
+                "[Wifi] Recieved response `{:?}` and failed with error `{:?}`",
+                from_utf8(body),
+                e
+            );
+            Err(FetchError::Json)
+        }
+    }
+}
 
-                debug!("got headers");
-                Timer::after_nanos(200000).await;
+async fn fetch(
+    client: &mut Client<'_>,
+    typ: RequestType,
+    rx_buffer: &'static mut [u8],
+) -> Result<RequestData, FetchError> {
+    let mut url = String::<80>::new();
+    let path = match typ {
+        RequestType::Stats => "stats/",
+        RequestType::Session => "session/",
+        RequestType::Goals => "goals/",
+        RequestType::Leaderboard(_) => "leaderboard/",
+        RequestType::Projects => "projects/",
+        RequestType::Shop(_) => "shop",
+    };
+    write!(url, "{}{}", API_URL, path).unwrap();
+
+    let config = CONFIG.lock().await;
+    match typ {
+        // the shop is the same for everyone
+        RequestType::Shop(page) => {
+            write!(url, "?page={}&limit={}", page, SHOP_PAGE).unwrap();
+        }
+        RequestType::Leaderboard(page) => {
+            url.push_str(&config.slack_id).unwrap();
+            write!(url, "?page={}&limit={}", page, LEADERBOARD_PAGE).unwrap();
+        }
+        _ => url.push_str(&config.slack_id).unwrap(),
+    }
+    let header = bearer(&config.api_token);
+    drop(config);
+
+    let headers = [("Authorization", header.as_str())];
+
+    let mut req = client
+        .request(Method::GET, &url)
+        .await
+        .map_err(fetch_error)?
+        .headers(&headers);
+    let resp = req.send(rx_buffer).await.map_err(fetch_error)?;
+    check_status(resp.status, resp.headers())?;
+    let body: &'static [u8] = resp.body().read_to_end().await.map_err(fetch_error)?;
+
+    debug!("[Wifi] Recieved {:?}", from_utf8(body));
+
+    Ok(match typ {
+        RequestType::Stats => RequestData::Stats(parse::<StatsResponse>(body)?.sessions as u16),
+        RequestType::Session => {
+            let session = parse::<SessionResponse>(body)?;
+            RequestData::Session(
+                session.elapsed,
+                session.goal,
+                session.paused,
+                session.completed,
+            )
+        }
+        RequestType::Goals => RequestData::Goals(
+            parse::<GoalsResponse>(body)?
+                .iter()
+                .take(MAX_GOALS)
+                .map(|goal| goal.name)
+                .collect(),
+        ),
+        RequestType::Leaderboard(page) => {
+            RequestData::Leaderboard(page, parse::<LeaderboardResponse>(body)?)
+        }
+        RequestType::Projects => RequestData::Projects(
+            parse::<ProjectsResponse>(body)?
+                .into_iter()
+                .take(MAX_PROJECTS)
+                .collect(),
+        ),
+        RequestType::Shop(page) => RequestData::Shop(page, parse::<ShopResponse>(body)?),
+    })
+}
 
-                let resp = req
-                    .send(rx_buffer)
-                    .await
-                    .unwrap()
-                    .body()
-                    .read_to_end()
-                    .await
-                    .unwrap();
+fn parse<R>(body: &'static [u8]) -> Result<R::Data, FetchError>
+where
+    R: Response + Deserialize<'static>,
+{
+    match serde_json_core::from_slice::<R>(body) {
+        Ok((resp, _)) => resp.into_data(),
+        Err(e) => {
+            debug!(
+                "[Wifi] Recieved response `{:?}` and failed with error `{:?}`",
+                from_utf8(body),
+                e
+            );
+            Err(FetchError::Json)
+        }
+    }
+}
 
-                debug!("sent request {:?}", from_utf8(resp));
-                Timer::after_nanos(200000).await;
-
-                match typ {
-                    RequestType::Stats => {
-                        let body: StatsResponse = match serde_json_core::from_slice(resp) {
-                            Ok(b) => b.0,
-                            Err(e) => {
-                                error!(
-                                    "[Wifi] Failed to parse the Hack Hour response. Is it down?",
-                                );
-                                error!(
-                                    "[Wifi] Recieved response `{:?}` and failed with error `{:?}",
-                                    from_utf8(resp),
-                                    e
-                                );
-                                return;
-                            }
-                        };
-
-                        if !body.ok {
-                            error!(
-                                "[Wifi] Hack Hour response failed. Are you authenticated properly?"
-                            );
-                            error!("[Wifi] Error is `{}`", body.error.unwrap());
-                            return;
-                        }
-                        data = RequestData::Stats(body.data.clone().unwrap().sessions as u16);
-                    }
-                    RequestType::Session => {
-                        let body: SessionResponse = match serde_json_core::from_slice(resp) {
-                            Ok(b) => b.0,
-                            Err(e) => {
-                                error!(
-                                    "[Wifi] Failed to parse the Hack Hour response. Is it down?",
-                                );
-                                error!(
-                                    "[Wifi] Recieved response `{:?}` and failed with error `{:?}",
-                                    from_utf8(resp),
-                                    e
-                                );
-                                return;
-                            }
-                        };
-
-                        if !body.ok {
-                            error!(
-                                "[Wifi] Hack Hour response failed. Are you authenticated properly?"
-                            );
-                            return;
-                        }
-                        let d = body.data.unwrap();
-                        data = RequestData::Session(d.elapsed, d.goal, d.paused);
-                    }
-                }
-            }
+fn bearer(token: &str) -> String<72> {
+    let mut header = String::from_str("Bearer ").unwrap();
+    // the token is at most 64 characters
+    header.push_str(token).unwrap();
+    header
+}
 
-            debug!("got tickets");
-            Timer::after_nanos(200000).await;
-            debug!("making request");
-            Timer::after_nanos(200000).await;
+fn check_status(status: StatusCode, mut headers: HeaderIterator) -> Result<(), FetchError> {
+    match status.0 {
+        200..=299 => Ok(()),
+        status => Err(FetchError::Status(
+            status,
+            headers
+                .find(|(name, _)| name.eq_ignore_ascii_case("retry-after"))
+                .and_then(|(_, value)| retry_after(value)),
+        )),
+    }
+}
 
-            EVENTS.send(crate::Events::DataUpdate(data)).await;
+fn fetch_error(err: Error) -> FetchError {
+    match err {
+        Error::Dns => FetchError::Dns,
+        Error::Network(_) | Error::ConnectionAborted => FetchError::Tcp,
+        Error::Tls(_) => FetchError::Tls,
+        Error::BufferTooSmall => FetchError::TooLarge,
+        Error::Codec | Error::InvalidUrl(_) | Error::AlreadySent | Error::IncorrectBodyWritten => {
+            FetchError::Http
         }
-        debug!("[Wifi] Sent event successfully!");
-        RUN.reset();
-        debug!("[Wifi] {:?}", RUN.signaled());
-        RUN.wait().await;
-        debug!("[Wifi] Starting again!");
     }
 }
 
-//#[embassy_executor::task]
-pub async fn configure_rtc(stack: &'static Stack<cyw43::NetDriver<'static>>) {
-    static RAN: AtomicBool = AtomicBool::new(false);
-    if RAN.load(core::sync::atomic::Ordering::Relaxed) {
-        error!("[Wifi] Tried to configure RTC twice!");
-        return;
+/// How often the RTC is set again, to correct its drift.
+const RESYNC_SECS: u64 = 6 * 60 * 60;
+/// How soon to try again when nothing told us the time.
+const RESYNC_RETRY_SECS: u64 = 5 * 60;
+const SNTP_TIMEOUT_SECS: u64 = 3;
+const TIME_URL: &str = "https://time.eny.hackclub.app/et";
+
+/// Sets the RTC, returning whether anything told us the time.
+pub async fn configure_rtc(stack: &'static Stack<cyw43::NetDriver<'static>>) -> bool {
+    debug!("[Wifi] Configuring RTC");
+    match sync_time(stack).await {
+        Ok(time) => {
+            debug!("[Wifi] Configured RTC");
+            EVENTS
+                .send(Events::RtcUpdate(time.with_timezone(&Utc)))
+                .await;
+            true
+        }
+        Err(err) => {
+            report!(Source::Rtc, err.kind(), "Time request failed: {}", err);
+            false
+        }
     }
-    RAN.store(true, core::sync::atomic::Ordering::Relaxed);
-
-    unsafe {
-        debug!("[Wifi] Configuring RTC");
-        let client_state = TcpClientState::<1, 1024, 1024>::new();
-        let tcp_client = TcpClient::new(stack, &client_state);
-        let dns_client = DnsSocket::new(stack);
-
-        let tls_read = &mut [0u8; 16384];
-        let tls_write = &mut [0u8; 16384];
-
-        let mut http_client = HttpClient::new_with_tls(
-            &tcp_client,
-            &dns_client,
-            TlsConfig::new(312, tls_read, tls_write, TlsVerify::None),
-        );
-        static mut RX_BUF: StaticCell<[u8; 8192]> = StaticCell::new();
-        let rx_buffer = RX_BUF.init([0; 8192]);
-
-        let mut req = match http_client
-            .request(
-                reqwless::request::Method::GET,
-                "https://time.eny.hackclub.app/et",
-            )
-            .await
-        {
-            Ok(r) => r,
-            Err(e) => {
-                error!("[Wifi] Failed to make time request. ");
-                error!("[Wifi] Error is `{:?}`", e);
-                Timer::after_nanos(4000000).await;
-                return;
-            }
-        };
+}
 
-        debug!("made request");
-        Timer::after_nanos(200000).await;
-
-        debug!("filled buffer");
-
-        let resp = req
-            .send(rx_buffer)
-            .await
-            .unwrap()
-            .body()
-            .read_to_end()
-            .await
-            .unwrap();
-
-        debug!("sent request");
-        Timer::after_nanos(200000).await;
-
-        let body: TimeData = match serde_json_core::from_slice(resp) {
-            Ok(b) => b.0,
-            Err(e) => {
-                error!("[Wifi] Failed to parse time response. ");
-                error!(
-                    "[Wifi] Recieved response `{:?}` and failed with error `{:?}`",
-                    from_utf8(resp),
-                    e
-                );
-                return;
-            }
+/// Keeps the RTC in step after the first `configure_rtc`.
+#[embassy_executor::task]
+pub async fn time_task(stack: &'static Stack<cyw43::NetDriver<'static>>, mut synced: bool) -> ! {
+    loop {
+        let wait = if synced {
+            RESYNC_SECS
+        } else {
+            RESYNC_RETRY_SECS
         };
+        Timer::after_secs(wait).await;
+        stack.wait_config_up().await;
+        synced = configure_rtc(stack).await;
+    }
+}
+
+/// Tries each SNTP server, then the time endpoint and the API's `Date`
+/// header over HTTPS in case UDP is blocked.
+async fn sync_time(
+    stack: &'static Stack<cyw43::NetDriver<'static>>,
+) -> Result<DateTime<FixedOffset>, FetchError> {
+    let servers = CONFIG.lock().await.time_servers();
+    for server in &servers {
+        if let Some(time) = sntp_time(stack, server).await {
+            return Ok(time.fixed_offset());
+        }
+    }
+
+    debug!("[Wifi] No SNTP server answered, falling back to HTTPS");
+    match fetch_time(stack, TIME_URL).await {
+        Ok(time) => Ok(time),
+        Err(err) => {
+            debug!("[Wifi] Time endpoint failed: {}", err);
+            fetch_time(stack, API_URL).await
+        }
+    }
+}
+
+async fn sntp_time(
+    stack: &'static Stack<cyw43::NetDriver<'static>>,
+    server: &str,
+) -> Option<DateTime<Utc>> {
+    let address = match stack.dns_query(server, DnsQueryType::A).await {
+        Ok(addresses) => *addresses.first()?,
+        Err(err) => {
+            debug!("[Wifi] Failed to look up {}: {:?}", server, err);
+            return None;
+        }
+    };
+
+    let mut rx_meta = [PacketMetadata::EMPTY; 1];
+    let mut rx_buffer = [0; 128];
+    let mut tx_meta = [PacketMetadata::EMPTY; 1];
+    let mut tx_buffer = [0; 128];
+    let mut socket = UdpSocket::new(
+        stack,
+        &mut rx_meta,
+        &mut rx_buffer,
+        &mut tx_meta,
+        &mut tx_buffer,
+    );
+    socket.bind(0).ok()?;
+
+    let transmit = RoscRng.next_u64();
+    if let Err(err) = socket
+        .send_to(&sntp::request(transmit), (address, sntp::PORT))
+        .await
+    {
+        debug!("[Wifi] Failed to send to {}: {:?}", server, err);
+        return None;
+    }
 
-        debug!("[Wifi] Configured RTC");
-        Timer::after_nanos(200000).await;
+    let mut reply = [0; sntp::PACKET_LEN];
+    let timeout = Duration::from_secs(SNTP_TIMEOUT_SECS);
+    let (len, from) = match with_timeout(timeout, socket.recv_from(&mut reply)).await {
+        Ok(Ok(received)) => received,
+        Ok(Err(err)) => {
+            debug!("[Wifi] Failed to hear from {}: {:?}", server, err);
+            return None;
+        }
+        Err(_) => {
+            debug!("[Wifi] {} didn't answer", server);
+            return None;
+        }
+    };
+    if from.addr != address {
+        return None;
+    }
+    match sntp::decode(&reply[..len], transmit) {
+        Ok(time) => Some(time),
+        Err(err) => {
+            debug!("[Wifi] Bad reply from {}: {:?}", server, err);
+            None
+        }
+    }
+}
 
-        EVENTS
-            .send(crate::Events::RtcUpdate(
-                DateTime::parse_from_rfc3339(body.datetime).unwrap(),
-            ))
-            .await;
+/// The time from `url`, or failing that the `Date` header it came with.
+async fn fetch_time(
+    stack: &'static Stack<cyw43::NetDriver<'static>>,
+    url: &str,
+) -> Result<DateTime<FixedOffset>, FetchError> {
+    let client_state = TcpClientState::<1, 1024, 1024>::new();
+    let tcp_client = TcpClient::new(stack, &client_state);
+    let dns_client = DnsSocket::new(stack);
+
+    let mut tls = TLS.lock().await;
+    let mut http_client = https_client(&tcp_client, &dns_client, &mut tls);
+    let mut rx_buffer = [0; 4096];
+
+    let mut req = http_client
+        .request(Method::GET, url)
+        .await
+        .map_err(fetch_error)?;
+    let resp = req.send(&mut rx_buffer).await.map_err(fetch_error)?;
+    let date = resp
+        .headers()
+        .find(|(name, _)| name.eq_ignore_ascii_case("date"))
+        .and_then(|(_, value)| sntp::http_date(value))
+        .map(|date| date.fixed_offset());
+
+    if let Err(err) = check_status(resp.status, resp.headers()) {
+        return date.ok_or(err);
+    }
+    let body = match resp.body().read_to_end().await {
+        Ok(body) => body,
+        Err(err) => return date.ok_or(fetch_error(err)),
+    };
+
+    match serde_json_core::from_slice::<TimeData>(body) {
+        Ok((time, _)) => DateTime::parse_from_rfc3339(time.datetime).map_err(|_| FetchError::Json),
+        Err(e) => {
+            debug!(
+                "[Wifi] Recieved response `{:?}` and failed with error `{:?}`",
+                from_utf8(body),
+                e
+            );
+            date.ok_or(FetchError::Json)
+        }
     }
 }
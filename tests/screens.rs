@@ -0,0 +1,679 @@
+//! Golden image tests for every screen, run with `cargo test-host`.
+//!
+//! Each render is compared pixel for pixel against `tests/snapshots/<name>.png`.
+//! Run with `UPDATE_SNAPSHOTS=1` to write new references after an intended
+//! layout change, and check the PNGs before committing them.
+
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::{Mutex, MutexGuard};
+
+use chrono::{NaiveDate, NaiveDateTime};
+use embassy_futures::block_on;
+use embedded_graphics::{draw_target::DrawTarget, geometry::Size, pixelcolor::Rgb565};
+use embedded_graphics_simulator::{OutputSettingsBuilder, SimulatorDisplay};
+use sprig_arcade::{
+    apply_update_interval,
+    gui::{
+        errors, home, leaderboard,
+        nav::{update_active, update_selected, NAV},
+        session, settings, shop, wishlist, Router, ScreenId,
+    },
+    report,
+    util::{self, Button, Events, Link, ERRORS, LONG_PRESS, REQUEST_TYPE, SESSION_ACTION},
+    App, CONFIG, EVENTS, HISTORY, TICKETS, UPDATE_INTERVAL,
+};
+use sprig_arcade_core::{
+    api::{
+        LeaderboardData, LeaderboardUser, ProjectData, RequestData, RequestType, SessionAction,
+        ShopItem,
+    },
+    config::{Config, ConfigStore, WishlistItem},
+    errors::{ErrorKind, Source},
+    history::{Days, DAYS},
+    storage::MemFlash,
+};
+
+/// The screens keep state in statics, so renders can't overlap.
+static SCREEN: Mutex<()> = Mutex::new(());
+
+fn lock() -> MutexGuard<'static, ()> {
+    SCREEN.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// A `format!` that doesn't fit its buffer logs an error and draws nothing,
+/// which is easy to miss when blessing a snapshot, so it fails the test.
+struct FormatCheck;
+
+impl log::Log for FormatCheck {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Error
+    }
+
+    fn log(&self, record: &log::Record) {
+        let message = record.args().to_string();
+        assert!(!message.starts_with("Failed to format string"), "{message}");
+    }
+
+    fn flush(&self) {}
+}
+
+fn display() -> SimulatorDisplay<Rgb565> {
+    // only the first call sets it
+    if log::set_logger(&FormatCheck).is_ok() {
+        log::set_max_level(log::LevelFilter::Error);
+    }
+
+    let mut disp = SimulatorDisplay::new(Size::new(160, 128));
+    disp.clear(Rgb565::new(31, 60, 27)).unwrap();
+    disp
+}
+
+fn date(month: u32, day: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2024, month, day)
+        .unwrap()
+        .and_hms_opt(12, 0, 0)
+        .unwrap()
+}
+
+/// The ticket counts at the end of each day up to `last`.
+fn set_history(last: NaiveDateTime, counts: &[u16]) {
+    let mut days = Days::new();
+    days.last = last.date();
+    for (count, tickets) in days.counts[DAYS - counts.len()..].iter_mut().zip(counts) {
+        *count = Some(*tickets);
+    }
+    *block_on(HISTORY.lock()) = days;
+}
+
+fn assert_snapshot(name: &str, disp: &SimulatorDisplay<Rgb565>) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots")
+        .join(format!("{name}.png"));
+    let settings = OutputSettingsBuilder::new().scale(1).build();
+
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        disp.to_rgb_output_image(&settings).save_png(&path).unwrap();
+        return;
+    }
+
+    let expected = SimulatorDisplay::<Rgb565>::load_png(&path).unwrap_or_else(|err| {
+        panic!("Failed to load {path:?} ({err}), run with UPDATE_SNAPSHOTS=1 to create it")
+    });
+
+    if let Some(diff) = expected.diff(disp) {
+        let actual = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{name}.png"));
+        let changed = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{name}.diff.png"));
+        disp.to_rgb_output_image(&settings)
+            .save_png(&actual)
+            .unwrap();
+        diff.to_rgb_output_image(&settings)
+            .save_png(&changed)
+            .unwrap();
+        panic!("`{name}` doesn't match its snapshot, see {actual:?} and {changed:?}");
+    }
+}
+
+#[test]
+fn progress() {
+    let _lock = lock();
+
+    // the ideal bar only animates the first time it is drawn
+    let mut disp = display();
+    block_on(home::update_progress(&mut disp, 100, 90, date(8, 1)));
+    assert_snapshot("progress_first", &disp);
+
+    let cases = [
+        ("progress", 100, 100, date(8, 1)),
+        ("progress_single_digit", 20, 20, date(6, 20)),
+        ("progress_done", 174, 170, date(9, 30)),
+        ("progress_upcoming", 0, 0, date(6, 10)),
+        ("progress_over", 174, 174, date(10, 5)),
+    ];
+    for (name, tickets, old, now) in cases {
+        let mut disp = display();
+        block_on(home::update_progress(&mut disp, tickets, old, now));
+        assert_snapshot(name, &disp);
+    }
+}
+
+#[test]
+fn stats() {
+    let _lock = lock();
+    // a four day streak, after a best of five
+    set_history(date(8, 1), &[60, 61, 63, 64, 65, 66, 66, 70, 72, 73, 74]);
+
+    let cases = [
+        ("stats", 100, date(8, 1)),
+        ("stats_start", 15, date(6, 18)),
+        ("stats_end", 150, date(9, 29)),
+    ];
+    for (name, tickets, now) in cases {
+        let mut disp = display();
+        block_on(home::update_stats(&mut disp, tickets, now));
+        assert_snapshot(name, &disp);
+    }
+
+    // behind at the fortnight pace, which only has ten days to go on
+    let mut disp = display();
+    block_on(home::input(Button::Down, &mut disp));
+    block_on(home::input(Button::A, &mut disp));
+    block_on(home::update_stats(&mut disp, 74, date(8, 1)));
+    assert_snapshot("stats_behind", &disp);
+
+    block_on(home::input(Button::A, &mut disp));
+    block_on(home::input(Button::A, &mut disp));
+    block_on(home::input(Button::Up, &mut disp));
+}
+
+#[test]
+fn chart() {
+    let _lock = lock();
+
+    // twelve days of history, so the first bars are empty
+    set_history(
+        date(8, 1),
+        &[60, 62, 65, 65, 66, 70, 73, 74, 74, 78, 80, 81],
+    );
+
+    // the clock is left unset for the Errors screen, so the chart is drawn by hand
+    let mut disp = display();
+    block_on(home::input(Button::Down, &mut disp));
+    block_on(home::input(Button::Down, &mut disp));
+    assert!(home::on_chart());
+    block_on(home::update_chart(&mut disp, date(8, 1)));
+    assert_snapshot("chart", &disp);
+
+    block_on(home::input(Button::Left, &mut disp));
+    block_on(home::update_chart(&mut disp, date(8, 1)));
+    assert_snapshot("chart_scrolled", &disp);
+
+    block_on(home::input(Button::Up, &mut disp));
+    block_on(home::input(Button::Up, &mut disp));
+    assert!(!home::on_chart());
+}
+
+#[test]
+fn session() {
+    let _lock = lock();
+
+    let cases = [
+        (
+            "session",
+            RequestData::Session(25, "Writing snapshot tests", false, false),
+        ),
+        (
+            "session_start",
+            RequestData::Session(0, "Fixing the nav bar", false, false),
+        ),
+        (
+            "session_paused",
+            RequestData::Session(42, "Lunch", true, false),
+        ),
+        (
+            "session_finished",
+            RequestData::Session(60, "Shipping it", false, true),
+        ),
+        (
+            "session_cancelled",
+            RequestData::Session(12, "Shipping it", false, true),
+        ),
+    ];
+    for (name, data) in cases {
+        TICKETS.store(100, core::sync::atomic::Ordering::Relaxed);
+        let mut disp = display();
+        block_on(session::update(&mut disp, data, date(8, 1)));
+        assert_snapshot(name, &disp);
+    }
+}
+
+fn pending_action() -> Option<SessionAction> {
+    block_on(SESSION_ACTION.lock()).take()
+}
+
+#[test]
+fn session_controls() {
+    let _lock = lock();
+
+    TICKETS.store(100, core::sync::atomic::Ordering::Relaxed);
+    let mut disp = display();
+    let mut router = Router::new(ScreenId::Session);
+    let data = RequestData::Session(25, "Writing snapshot tests", false, false);
+    block_on(router.on_data(&mut disp, data, 0, date(8, 1)));
+
+    // pausing shows straight away, before the request goes out
+    block_on(router.input(Button::A, &mut disp));
+    assert_snapshot("session_controls_paused", &disp);
+    assert_eq!(pending_action(), Some(SessionAction::Pause));
+
+    // pausing and resuming before the poll cancels out
+    block_on(router.input(Button::A, &mut disp));
+    block_on(router.input(Button::A, &mut disp));
+    assert_eq!(pending_action(), None);
+
+    block_on(router.long_press(Button::B, &mut disp));
+    assert_snapshot("session_controls_cancelled", &disp);
+    assert_eq!(pending_action(), Some(SessionAction::Cancel));
+
+    // with nothing running, A asks for a goal to start a new session with
+    block_on(router.input(Button::A, &mut disp));
+    assert_eq!(router.top(), ScreenId::GoalPicker);
+    assert_eq!(*block_on(REQUEST_TYPE.lock()), RequestType::Goals);
+    assert_snapshot("session_controls_loading", &disp);
+
+    let goals = ["No Goal", "Sprig Arcade"].into_iter().collect();
+    block_on(router.on_data(&mut disp, RequestData::Goals(goals), 0, date(8, 1)));
+    block_on(router.input(Button::Down, &mut disp));
+    assert_snapshot("session_controls_goals", &disp);
+
+    // starting goes back to the session, shown before the next poll
+    block_on(router.input(Button::A, &mut disp));
+    assert_eq!(router.top(), ScreenId::Session);
+    assert_snapshot("session_controls_started", &disp);
+    assert_eq!(
+        pending_action(),
+        Some(SessionAction::Start("Sprig Arcade".try_into().unwrap()))
+    );
+
+    // B closes the picker without starting anything
+    block_on(router.long_press(Button::B, &mut disp));
+    pending_action();
+    block_on(router.input(Button::A, &mut disp));
+    block_on(router.input(Button::B, &mut disp));
+    assert_eq!(router.top(), ScreenId::Session);
+    assert_eq!(pending_action(), None);
+    block_on(router.switch(ScreenId::Home, &mut disp));
+}
+
+fn leaderboard_page(page: u8, users: &[(&'static str, u32)]) -> RequestData {
+    RequestData::Leaderboard(
+        page,
+        LeaderboardData {
+            rank: 9,
+            sessions: 87,
+            users: users
+                .iter()
+                .map(|&(name, sessions)| LeaderboardUser { name, sessions })
+                .collect(),
+        },
+    )
+}
+
+#[test]
+fn leaderboard() {
+    let _lock = lock();
+
+    let mut disp = display();
+    block_on(leaderboard::init());
+    leaderboard::draw_loading(&mut disp, 0);
+    assert_snapshot("leaderboard_loading", &disp);
+
+    let top = [
+        ("Orpheus", 212),
+        ("Heidi", 180),
+        ("A name that is far too long to fit", 166),
+        ("Zara", 151),
+        ("Malted Milk", 140),
+        ("Basil", 133),
+    ];
+    block_on(leaderboard::update(&mut disp, leaderboard_page(0, &top)));
+    assert_snapshot("leaderboard", &disp);
+
+    // A turns to the page the user is on, and their row is highlighted
+    block_on(leaderboard::input(Button::A, &mut disp));
+    assert_eq!(*block_on(REQUEST_TYPE.lock()), RequestType::Leaderboard(1));
+    let page = [("Quinn", 120), ("Juniper", 101), ("Sprig Simulator", 87)];
+    block_on(leaderboard::update(&mut disp, leaderboard_page(1, &page)));
+    assert_snapshot("leaderboard_own", &disp);
+
+    // a page that arrives after turning away is dropped
+    block_on(leaderboard::input(Button::Up, &mut disp));
+    block_on(leaderboard::update(&mut disp, leaderboard_page(1, &page)));
+    assert_snapshot("leaderboard_turned", &disp);
+}
+
+#[test]
+fn projects() {
+    let _lock = lock();
+
+    let mut disp = display();
+    let mut router = Router::new(ScreenId::Home);
+    block_on(router.switch(ScreenId::Projects, &mut disp));
+    assert_snapshot("projects_loading", &disp);
+
+    let data = [
+        ("Sprig Arcade", 2460, 41),
+        ("Simulating the Sprig", 1320, 22),
+        ("Hack Hour bot", 780, 13),
+        ("No Goal", 300, 6),
+        ("Pixel art", 240, 4),
+        ("Dotfiles", 120, 2),
+    ]
+    .iter()
+    .map(|&(name, minutes, sessions)| ProjectData {
+        name,
+        minutes,
+        sessions,
+    })
+    .collect();
+    block_on(router.on_data(&mut disp, RequestData::Projects(data), 0, date(8, 1)));
+    assert_snapshot("projects", &disp);
+
+    // scrolls to keep the selection on screen
+    for _ in 0..5 {
+        block_on(router.input(Button::Down, &mut disp));
+    }
+    assert_snapshot("projects_scrolled", &disp);
+
+    for _ in 0..4 {
+        block_on(router.input(Button::Up, &mut disp));
+    }
+    // the project is pushed over the list
+    block_on(router.input(Button::A, &mut disp));
+    assert_eq!(router.top(), ScreenId::ProjectDetail);
+    assert_snapshot("projects_detail", &disp);
+
+    // B goes back to the list as it was
+    block_on(router.input(Button::B, &mut disp));
+    assert_eq!(router.top(), ScreenId::Projects);
+    assert_snapshot("projects_back", &disp);
+    block_on(router.switch(ScreenId::Home, &mut disp));
+}
+
+/// Whether a screen asked for the config to be saved since the last call.
+fn saved_config() -> bool {
+    let mut saved = false;
+    while let Ok(event) = EVENTS.try_receive() {
+        saved |= matches!(event, Events::SaveConfig);
+    }
+    saved
+}
+
+#[test]
+fn shop() {
+    let _lock = lock();
+    block_on(CONFIG.lock()).wishlist.clear();
+    saved_config();
+
+    let mut disp = display();
+    block_on(shop::init());
+    block_on(shop::draw(&mut disp));
+    assert_snapshot("shop_loading", &disp);
+
+    let items = [
+        ("Sprig", 20),
+        ("Pinecil", 60),
+        ("Raspberry Pi Pico W", 15),
+        ("Flipper Zero", 300),
+        ("A very long name for a very small sticker", 5),
+        ("Mechanical keyboard", 120),
+    ]
+    .iter()
+    .map(|&(name, tickets)| ShopItem { name, tickets })
+    .collect();
+    block_on(shop::update(&mut disp, RequestData::Shop(0, items)));
+    assert_snapshot("shop", &disp);
+
+    // A stars the selected item and saves it
+    block_on(shop::input(Button::Down, &mut disp));
+    block_on(shop::input(Button::A, &mut disp));
+    assert_snapshot("shop_starred", &disp);
+    assert!(saved_config());
+    assert_eq!(
+        block_on(CONFIG.lock()).wishlist[0],
+        WishlistItem {
+            name: "Pinecil".try_into().unwrap(),
+            tickets: 60,
+        }
+    );
+
+    // going past the last item fetches the next page
+    for _ in 0..5 {
+        block_on(shop::input(Button::Down, &mut disp));
+    }
+    assert_eq!(*block_on(REQUEST_TYPE.lock()), RequestType::Shop(1));
+    assert_snapshot("shop_next_page", &disp);
+
+    // a price change is copied to the wishlist
+    let items = [("Pinecil", 75)]
+        .iter()
+        .map(|&(name, tickets)| ShopItem { name, tickets })
+        .collect();
+    block_on(shop::update(&mut disp, RequestData::Shop(1, items)));
+    assert_eq!(block_on(CONFIG.lock()).wishlist[0].tickets, 75);
+    assert!(saved_config());
+}
+
+#[test]
+fn wishlist() {
+    let _lock = lock();
+    let items = [
+        ("Sprig", 20),
+        ("Pinecil", 60),
+        ("Flipper Zero", 300),
+        ("Framework Laptop 13", 1000),
+        ("Steam Deck", 900),
+    ];
+    block_on(CONFIG.lock()).wishlist = items
+        .iter()
+        .map(|&(name, tickets)| WishlistItem {
+            name: name.try_into().unwrap(),
+            tickets,
+        })
+        .collect();
+    saved_config();
+    TICKETS.store(100, core::sync::atomic::Ordering::Relaxed);
+
+    // no ETAs until the first fetch
+    let mut disp = display();
+    block_on(wishlist::init());
+    block_on(wishlist::draw(&mut disp));
+    assert_snapshot("wishlist_loading", &disp);
+
+    block_on(wishlist::update(
+        &mut disp,
+        RequestData::Stats(100),
+        date(8, 1),
+    ));
+    assert_snapshot("wishlist", &disp);
+
+    for _ in 0..4 {
+        block_on(wishlist::input(Button::Down, &mut disp));
+    }
+    assert_snapshot("wishlist_scrolled", &disp);
+
+    // A takes the item off
+    block_on(wishlist::input(Button::A, &mut disp));
+    assert_snapshot("wishlist_removed", &disp);
+    assert_eq!(block_on(CONFIG.lock()).wishlist.len(), 4);
+    assert!(saved_config());
+
+    block_on(CONFIG.lock()).wishlist.clear();
+    block_on(wishlist::draw(&mut disp));
+    assert_snapshot("wishlist_empty", &disp);
+}
+
+#[test]
+fn errors() {
+    let _lock = lock();
+    ERRORS.lock(|errors| errors.borrow_mut().clear());
+
+    let mut disp = display();
+    let mut router = Router::new(ScreenId::Home);
+    block_on(router.switch(ScreenId::Errors, &mut disp));
+    assert_snapshot("errors_empty", &disp);
+
+    // one from before the RTC was set
+    report!(
+        Source::Rtc,
+        ErrorKind::Connection,
+        "Time request failed: Dns"
+    );
+    util::set_clock(date(8, 1));
+    report!(
+        Source::Api,
+        ErrorKind::Parse,
+        "Failed to parse the Stats response"
+    );
+    for n in 0..8 {
+        report!(
+            Source::Api,
+            ErrorKind::Rejected,
+            "Leaderboard({}) request failed: rate limited, try again in a minute",
+            n
+        );
+    }
+    errors::draw(&mut disp);
+    assert_snapshot("errors", &disp);
+
+    for _ in 0..9 {
+        block_on(router.input(Button::Down, &mut disp));
+    }
+    assert_snapshot("errors_scrolled", &disp);
+
+    // the details are pushed over the list
+    block_on(router.input(Button::A, &mut disp));
+    assert_eq!(router.top(), ScreenId::ErrorDetail);
+    assert_snapshot("errors_detail", &disp);
+
+    // B goes back to the list as it was, and long messages wrap
+    block_on(router.input(Button::B, &mut disp));
+    assert_eq!(router.top(), ScreenId::Errors);
+    assert_snapshot("errors_scrolled", &disp);
+    block_on(router.input(Button::Up, &mut disp));
+    block_on(router.input(Button::Up, &mut disp));
+    block_on(router.input(Button::A, &mut disp));
+    assert_snapshot("errors_detail_long", &disp);
+
+    // holding B on the list clears it
+    block_on(router.input(Button::B, &mut disp));
+    block_on(router.long_press(Button::B, &mut disp));
+    assert_snapshot("errors_empty", &disp);
+    assert!(ERRORS.lock(|errors| errors.borrow().is_empty()));
+
+    // B does nothing at the bottom of the stack
+    block_on(router.input(Button::B, &mut disp));
+    assert_eq!(router.top(), ScreenId::Errors);
+    block_on(router.switch(ScreenId::Home, &mut disp));
+}
+
+#[test]
+fn long_press_after_back() {
+    let _lock = lock();
+    ERRORS.lock(|errors| errors.borrow_mut().clear());
+    report!(
+        Source::Api,
+        ErrorKind::Parse,
+        "Failed to parse the Stats response"
+    );
+
+    let mut disp = display();
+    let mut app = App::new(&mut disp);
+    let hold = |app: &mut App, button, disp: &mut SimulatorDisplay<Rgb565>| {
+        block_on(app.button_pressed(button, disp));
+        std::thread::sleep(LONG_PRESS.into());
+        block_on(app.button_released(button, disp));
+    };
+
+    // Home is on the left of Settings, which is on the left of Errors
+    block_on(app.button_pressed(Button::Left, &mut disp));
+    block_on(app.button_pressed(Button::Left, &mut disp));
+    block_on(app.button_pressed(Button::A, &mut disp));
+    block_on(app.button_pressed(Button::A, &mut disp));
+
+    // holding B on the details only goes back, the list is cleared from itself
+    hold(&mut app, Button::B, &mut disp);
+    assert_eq!(ERRORS.lock(|errors| errors.borrow().len()), 1);
+    assert_snapshot("errors_held_on_detail", &disp);
+
+    hold(&mut app, Button::B, &mut disp);
+    assert!(ERRORS.lock(|errors| errors.borrow().is_empty()));
+
+    block_on(app.button_pressed(Button::Right, &mut disp));
+    block_on(app.button_pressed(Button::Right, &mut disp));
+    block_on(app.button_pressed(Button::A, &mut disp));
+}
+
+#[test]
+fn saved_interval_at_boot() {
+    let _lock = lock();
+    let before = UPDATE_INTERVAL.load(Ordering::Relaxed);
+
+    let mut store = ConfigStore::new(MemFlash::<8192>::new(), 0);
+    store
+        .save(&Config {
+            update_interval: 15,
+            ..Config::new()
+        })
+        .unwrap();
+
+    // as in main.rs, before the first screen is shown
+    apply_update_interval(&store.load().unwrap());
+    let mut disp = display();
+    App::new(&mut disp);
+    assert_eq!(UPDATE_INTERVAL.load(Ordering::Relaxed), 15);
+
+    UPDATE_INTERVAL.store(before, Ordering::Relaxed);
+}
+
+#[test]
+fn nav() {
+    let _lock = lock();
+
+    let mut disp = display();
+    App::new(&mut disp);
+    assert_snapshot("nav", &disp);
+
+    // home, session, leaderboard and settings
+    let (home, session, leaderboard, settings) = (0, 1, 2, NAV.len() - 1);
+    assert_eq!(NAV[settings].screen, ScreenId::Settings);
+
+    update_selected(session, home, home, &mut disp);
+    assert_snapshot("nav_selected", &disp);
+
+    update_active(session, home, &mut disp);
+    assert_snapshot("nav_active", &disp);
+
+    update_selected(leaderboard, session, session, &mut disp);
+    assert_snapshot("nav_selected_neighbour", &disp);
+
+    let mut disp = display();
+    App::new(&mut disp);
+    update_selected(settings, home, home, &mut disp);
+    assert_snapshot("nav_wrap", &disp);
+
+    let mut app = App::new(&mut disp);
+    app.link_changed(Link::Joining, &mut disp);
+    assert_snapshot("nav_joining", &disp);
+    app.link_changed(Link::Down, &mut disp);
+    assert_snapshot("nav_offline", &disp);
+}
+
+#[test]
+fn settings() {
+    let _lock = lock();
+
+    let mut disp = display();
+    block_on(settings::init());
+    block_on(settings::draw(&mut disp));
+    assert_snapshot("settings", &disp);
+
+    // scrolls to keep the selection on screen
+    for _ in 0..5 {
+        block_on(settings::input(Button::Down, &mut disp));
+    }
+    assert_snapshot("settings_scrolled", &disp);
+
+    // left and right pick which part of the date changes
+    for _ in 0..2 {
+        block_on(settings::input(Button::Up, &mut disp));
+    }
+    block_on(settings::input(Button::A, &mut disp));
+    block_on(settings::input(Button::Right, &mut disp));
+    block_on(settings::input(Button::Down, &mut disp));
+    assert_snapshot("settings_editing", &disp);
+
+    // B throws the edit away
+    block_on(settings::input(Button::B, &mut disp));
+    assert_snapshot("settings_cancelled", &disp);
+}